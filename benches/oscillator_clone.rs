@@ -0,0 +1,65 @@
+#![allow(clippy::needless_return)]
+
+// what play_song does for every note: clone the track's oscillator and play it.
+// counts the allocations made while cloning, next to a deep copy of the same
+// table for comparison, and times both. run with cargo bench
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use rodio_synth::composer::WavetableOscillator;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+		return System.alloc(layout);
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout);
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const TABLE_SIZE: usize = 2048;
+const CLONES: usize = 100_000;
+
+// runs f CLONES times and reports the allocations and time it took
+fn measure(name: &str, mut f: impl FnMut()) {
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+	let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+	let start = Instant::now();
+	for _ in 0..CLONES {
+		f();
+	}
+	let elapsed = start.elapsed();
+	println!(
+		"{:<20} {:>8} allocations {:>12} bytes {:>8.1} ns per clone",
+		name,
+		ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+		ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+		elapsed.as_nanos() as f64 / CLONES as f64,
+	);
+}
+
+fn main() {
+	let table: Vec<f32> = (0..TABLE_SIZE).map(|n| (2.0 * std::f32::consts::PI * n as f32 / TABLE_SIZE as f32).sin()).collect();
+	let mut oscillator = WavetableOscillator::new(44_100, table.clone());
+	oscillator.reset_phase_on_clone = true;
+	oscillator.set_frequency(440.0);
+
+	measure("oscillator clone", || {
+		std::hint::black_box(oscillator.clone());
+	});
+	measure("table deep copy", || {
+		std::hint::black_box(table.clone());
+	});
+}
@@ -0,0 +1,134 @@
+// writes src/effects/cabinets.rs, the speaker cabinet impulse responses CabinetSim
+// plays through. given a measured response for each cabinet type, in the order
+// the types are declared, it converts them:
+//
+//     cargo run --example cabinet_tables -- british_4x12.wav american_1x12.wav bass_reflex.wav > src/effects/cabinets.rs
+//
+// a file can be at any sample rate, integer or float, and only its first channel
+// is used. responses longer than 50 ms are cut short with a quick fade out.
+// without files it writes stand-in responses sketched from how each kind of
+// cabinet tends to measure instead, so the sim has something to play until the
+// measured ones are converted
+#![allow(clippy::needless_return)]
+
+use std::fmt::Write;
+
+//names of the statics, in the order CabinetType declares the cabinets
+const CABINETS: [&str; 3] = ["BRITISH_STACK_4X12", "AMERICAN_COMBO_1X12", "BASS_REFLEX"];
+//responses are kept shorter than this, and the cut end fades out over the last
+//FADE_SECONDS so it doesn't click
+const MAX_SECONDS: f32 = 0.05;
+const FADE_SECONDS: f32 = 0.002;
+
+const SKETCH_SAMPLE_RATE: u32 = 44_100;
+const SKETCH_TAPS: usize = 512;
+// (low cut hz, high cut hz, resonance hz, resonance level) of the sketches
+const SKETCHES: [(f32, f32, f32, f32); 3] = [
+	(80.0, 4800.0, 110.0, 0.35),
+	(70.0, 6500.0, 95.0, 0.2),
+	(40.0, 3500.0, 60.0, 0.4),
+];
+
+fn main() {
+	let files: Vec<String> = std::env::args().skip(1).collect();
+	let (source, responses) = if files.is_empty() {
+		let sketches = SKETCHES.iter().map(|sketch| (SKETCH_SAMPLE_RATE, sketched(*sketch))).collect();
+		("sketches of how each kind of cabinet tends to measure, stand-ins for measured responses", sketches)
+	} else if files.len() == CABINETS.len() {
+		let mut responses: Vec<(u32, Vec<f32>)> = Vec::new();
+		for file in files.iter() {
+			match measured(file) {
+				Ok(response) => responses.push(response),
+				Err(error) => {
+					eprintln!("couldn't read {}: {}", file, error);
+					std::process::exit(1);
+				},
+			}
+		}
+		("measured responses", responses)
+	} else {
+		eprintln!("give a response for each of the {} cabinets, or none for the sketches", CABINETS.len());
+		std::process::exit(1);
+	};
+	print!("{}", table_source(source, &responses));
+}
+
+fn measured(file: &str) -> Result<(u32, Vec<f32>), hound::Error> {
+	let mut reader = hound::WavReader::open(file)?;
+	let spec = reader.spec();
+	let channels = spec.channels as usize;
+	let samples: Vec<f32> = match spec.sample_format {
+		hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<f32>, hound::Error>>()?,
+		hound::SampleFormat::Int => {
+			let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+			reader.samples::<i32>().map(|sample| sample.map(|sample| sample as f32 / full_scale)).collect::<Result<Vec<f32>, hound::Error>>()?
+		},
+	};
+	let mut response: Vec<f32> = samples.iter().step_by(channels).copied().collect();
+
+	//strictly under MAX_SECONDS
+	let max_taps = (spec.sample_rate as f32 * MAX_SECONDS).ceil() as usize - 1;
+	if response.len() > max_taps {
+		response.truncate(max_taps);
+		let fade = (spec.sample_rate as f32 * FADE_SECONDS) as usize;
+		for (n, tap) in response.iter_mut().rev().take(fade).enumerate() {
+			*tap *= 0.5 - 0.5 * (std::f32::consts::PI * n as f32 / fade as f32).cos();
+		}
+	}
+	return Ok((spec.sample_rate, response));
+}
+
+// the speaker's band pass plus a short decaying ring at the cabinet's low resonance
+fn sketched((low_cut, high_cut, resonance_hz, resonance_level): (f32, f32, f32, f32)) -> Vec<f32> {
+	let sample_rate = SKETCH_SAMPLE_RATE as f32;
+	let high = windowed_sinc_lowpass(high_cut / sample_rate, SKETCH_TAPS);
+	let low = windowed_sinc_lowpass(low_cut / sample_rate, SKETCH_TAPS);
+	let middle = (SKETCH_TAPS - 1) / 2;
+
+	let mut response: Vec<f32> = high.iter().zip(low.iter()).map(|(h, l)| h - l).collect();
+	for (n, coefficient) in response.iter_mut().enumerate().skip(middle) {
+		let t = (n - middle) as f32 / sample_rate;
+		let ring = (2.0 * std::f32::consts::PI * resonance_hz * t).sin() * (-t / 0.004).exp();
+		*coefficient += resonance_level * ring * 2.0 * resonance_hz / sample_rate;
+	}
+	return response;
+}
+
+// blackman windowed sinc low pass with unity gain at dc, the cutoff is a fraction
+// of the sample rate
+fn windowed_sinc_lowpass(cutoff: f32, taps: usize) -> Vec<f32> {
+	let middle = (taps - 1) as f32 / 2.0;
+	let mut coefficients: Vec<f32> = (0..taps).map(|n| {
+		let x = n as f32 - middle;
+		let sinc = if x == 0.0 {
+			2.0 * cutoff
+		} else {
+			(2.0 * std::f32::consts::PI * cutoff * x).sin() / (std::f32::consts::PI * x)
+		};
+		let phase = 2.0 * std::f32::consts::PI * n as f32 / (taps - 1) as f32;
+		let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+		return sinc * window;
+	}).collect();
+
+	let sum: f32 = coefficients.iter().sum();
+	for coefficient in coefficients.iter_mut() {
+		*coefficient /= sum;
+	}
+	return coefficients;
+}
+
+fn table_source(source: &str, responses: &[(u32, Vec<f32>)]) -> String {
+	let mut out = String::new();
+	let _ = writeln!(out, "// generated by examples/cabinet_tables.rs from {}, don't edit by hand", source);
+	let _ = writeln!(out);
+	let _ = writeln!(out, "// (sample rate, impulse response) of each cabinet");
+	for (name, (sample_rate, response)) in CABINETS.iter().zip(responses.iter()) {
+		let taps: Vec<String> = response.iter().map(|tap| format!("{:?}", tap)).collect();
+		let _ = writeln!(out, "pub(super) static {}: (u32, &[f32]) = ({}, &[", name, sample_rate);
+		for line in taps.chunks(8) {
+			let _ = writeln!(out, "\t{},", line.join(", "));
+		}
+		let _ = writeln!(out, "]);");
+	}
+	return out;
+}
@@ -0,0 +1,211 @@
+// writes src/hrtf/kemar.rs, the head related impulse responses HrtfPanner plays
+// through. given the folder of the MIT KEMAR "compact" set, which has an
+// elev<elevation> folder per ring holding H<elevation>e<azimuth>a.wav files, it
+// converts the measured responses:
+//
+//     cargo run --example hrtf_tables -- path/to/compact > src/hrtf/kemar.rs
+//
+// each compact file is a 16 bit, 44.1 khz stereo pair of 128 samples, left ear
+// then right, for a source at <azimuth> degrees to the right from 0 to 180.
+// without a folder it writes stand-in responses from a spherical head model (brown
+// and duda) on the same grid instead, so the panner has something to play until
+// the measured set is converted
+#![allow(clippy::needless_return)]
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const HRIR_LENGTH: usize = 128;
+const HRIR_SAMPLE_RATE: u32 = 44_100;
+//elevation of each ring of the compact set and how many responses go round it
+const GRID: [(i16, usize); 14] = [
+	(-40, 56), (-30, 60), (-20, 72), (-10, 72), (0, 72), (10, 72), (20, 72),
+	(30, 60), (40, 56), (50, 45), (60, 36), (70, 24), (80, 12), (90, 1),
+];
+
+//radius of the model head in meters, and the speed of sound
+const HEAD_RADIUS: f32 = 0.0875;
+const SPEED_OF_SOUND: f32 = 343.0;
+//how strongly the pinna reflection cancels its notch, and where the notch moves
+//between the lowest and the highest elevation
+const PINNA_REFLECTION: f32 = 0.5;
+const PINNA_NOTCH_LOW_HZ: f32 = 6_000.0;
+const PINNA_NOTCH_HIGH_HZ: f32 = 10_000.0;
+
+// one response of the grid: its ring, azimuth and both ears
+struct Response {
+	elevation: i16,
+	azimuth: u8,
+	left: Vec<i16>,
+	right: Vec<i16>,
+}
+
+fn main() {
+	let (source, responses) = match std::env::args().nth(1) {
+		Some(folder) => match measured(Path::new(&folder)) {
+			Ok(responses) => ("the measured MIT KEMAR compact set", responses),
+			Err(error) => {
+				eprintln!("couldn't read {}: {}", folder, error);
+				std::process::exit(1);
+			},
+		},
+		None => ("a spherical head model, a stand-in for the measured MIT KEMAR compact set", modelled()),
+	};
+	print!("{}", table_source(source, &responses));
+}
+
+fn measured(folder: &Path) -> Result<Vec<Response>, Box<dyn std::error::Error>> {
+	let mut responses: Vec<Response> = Vec::new();
+	for (elevation, _) in GRID.iter() {
+		let ring_folder = folder.join(format!("elev{}", elevation));
+		let mut ring: Vec<Response> = Vec::new();
+		for entry in fs::read_dir(&ring_folder)? {
+			let name = entry?.file_name().to_string_lossy().to_string();
+			let azimuth = match name.strip_prefix(&format!("H{}e", elevation)).and_then(|rest| rest.strip_suffix("a.wav")) {
+				Some(azimuth) => azimuth.parse::<u16>()?,
+				None => continue,
+			};
+			if azimuth > 180 {
+				continue;
+			}
+			let mut reader = hound::WavReader::open(ring_folder.join(&name))?;
+			let spec = reader.spec();
+			if spec.channels != 2 || spec.sample_rate != HRIR_SAMPLE_RATE || spec.bits_per_sample != 16 {
+				return Err(format!("{} isn't 16 bit 44.1 khz stereo", name).into());
+			}
+			let samples = reader.samples::<i16>().collect::<Result<Vec<i16>, hound::Error>>()?;
+			let mut left: Vec<i16> = samples.iter().step_by(2).copied().collect();
+			let mut right: Vec<i16> = samples.iter().skip(1).step_by(2).copied().collect();
+			left.resize(HRIR_LENGTH, 0);
+			right.resize(HRIR_LENGTH, 0);
+			ring.push(Response { elevation: *elevation, azimuth: azimuth as u8, left, right });
+		}
+		if ring.is_empty() {
+			return Err(format!("no responses in {}", ring_folder.display()).into());
+		}
+		ring.sort_by_key(|response| response.azimuth);
+		responses.extend(ring);
+	}
+	return Ok(responses);
+}
+
+fn modelled() -> Vec<Response> {
+	let mut modelled: Vec<(i16, u8, Vec<f32>, Vec<f32>)> = Vec::new();
+	for (elevation, count) in GRID.iter() {
+		let step = 360.0 / *count as f32;
+		let mut azimuth: f32 = 0.0;
+		while azimuth.round() <= 180.0 {
+			let (left, right) = spherical_head_hrir(azimuth, *elevation as f32);
+			modelled.push((*elevation, azimuth.round() as u8, left, right));
+			azimuth += step;
+		}
+	}
+
+	//the near ear's shadow filter peaks above 1, so the whole set is scaled down
+	//together to fit 16 bits like the measured one is
+	let peak = modelled.iter()
+		.flat_map(|(_, _, left, right)| left.iter().chain(right.iter()))
+		.fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+	let to_16_bit = |response: &[f32]| response.iter().map(|sample| (sample / peak * 32767.0).round() as i16).collect();
+	return modelled.iter().map(|(elevation, azimuth, left, right)| Response {
+		elevation: *elevation,
+		azimuth: *azimuth,
+		left: to_16_bit(left),
+		right: to_16_bit(right),
+	}).collect();
+}
+
+// left and right ear responses at 44.1 khz: each ear gets the head's time delay
+// and a one pole shadow filter that is bright facing the ear and dull behind the
+// head, plus a pinna reflection whose notch climbs with elevation
+fn spherical_head_hrir(azimuth: f32, elevation: f32) -> (Vec<f32>, Vec<f32>) {
+	let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+	//positive azimuths are to the right, 0 is straight ahead
+	let lateral = azimuth.sin() * elevation.cos();
+	let sample_rate = HRIR_SAMPLE_RATE as f32;
+
+	let notch_hz = PINNA_NOTCH_LOW_HZ + (PINNA_NOTCH_HIGH_HZ - PINNA_NOTCH_LOW_HZ) * (elevation.to_degrees() + 90.0) / 180.0;
+	let pinna_delay = sample_rate / (2.0 * notch_hz);
+
+	let ear = |side: f32| -> Vec<f32> {
+		//angle between the source and the ear's axis
+		let incidence = (side * lateral).clamp(-1.0, 1.0).acos();
+		let delay = if incidence < std::f32::consts::FRAC_PI_2 {
+			-incidence.cos()
+		} else {
+			incidence - std::f32::consts::FRAC_PI_2
+		};
+		//shifted so the nearest an ear can be is one sample in
+		let delay = (delay + 1.0) * HEAD_RADIUS / SPEED_OF_SOUND * sample_rate + 1.0;
+
+		let mut impulse = vec![0.0; HRIR_LENGTH];
+		let whole = delay as usize;
+		if whole + 1 < HRIR_LENGTH {
+			impulse[whole] = 1.0 - delay.fract();
+			impulse[whole + 1] = delay.fract();
+		}
+
+		//head shadow, bilinear transform of (1 + alpha s / beta) / (1 + s / beta)
+		let alpha = 1.05 + 0.95 * (incidence.to_degrees() / 150.0 * std::f32::consts::PI).cos();
+		let beta = 2.0 * SPEED_OF_SOUND / HEAD_RADIUS;
+		let k = 2.0 * sample_rate;
+		let (b0, b1, a1) = ((beta + alpha * k) / (beta + k), (beta - alpha * k) / (beta + k), (beta - k) / (beta + k));
+		let (mut x1, mut y1) = (0.0, 0.0);
+		let shadowed: Vec<f32> = impulse.iter().map(|x| {
+			let y = b0 * x + b1 * x1 - a1 * y1;
+			x1 = *x;
+			y1 = y;
+			return y;
+		}).collect();
+
+		return (0..HRIR_LENGTH).map(|n| {
+			let reflected = n as f32 - pinna_delay;
+			let reflection = if reflected >= 0.0 {
+				let index = reflected as usize;
+				let next = shadowed.get(index + 1).copied().unwrap_or(0.0);
+				shadowed[index] * (1.0 - reflected.fract()) + next * reflected.fract()
+			} else {
+				0.0
+			};
+			return shadowed[n] - PINNA_REFLECTION * reflection;
+		}).collect();
+	};
+	return (ear(-1.0), ear(1.0));
+}
+
+fn table_source(source: &str, responses: &[Response]) -> String {
+	let mut out = String::new();
+	let _ = writeln!(out, "// generated by examples/hrtf_tables.rs from {}, don't edit by hand", source);
+	let _ = writeln!(out);
+	let _ = writeln!(out, "pub(super) const HRIR_LENGTH: usize = {};", HRIR_LENGTH);
+	let _ = writeln!(out, "pub(super) const HRIR_SAMPLE_RATE: u32 = {};", HRIR_SAMPLE_RATE);
+	let _ = writeln!(out);
+	let _ = writeln!(out, "// (elevation in degrees, first response, response count) of every ring, lowest first");
+	let _ = writeln!(out, "pub(super) static RINGS: [(i16, usize, usize); {}] = [", GRID.len());
+	let mut first = 0;
+	for (elevation, _) in GRID.iter() {
+		let count = responses.iter().filter(|response| response.elevation == *elevation).count();
+		let _ = writeln!(out, "\t({}, {}, {}),", elevation, first, count);
+		first += count;
+	}
+	let _ = writeln!(out, "];");
+	let _ = writeln!(out);
+	let _ = writeln!(out, "// azimuth of every response in degrees to the right, 0 to 180 round each ring");
+	let _ = writeln!(out, "pub(super) static AZIMUTHS: [u8; {}] = [", responses.len());
+	for ring in GRID.iter() {
+		let azimuths: Vec<String> = responses.iter().filter(|response| response.elevation == ring.0).map(|response| response.azimuth.to_string()).collect();
+		let _ = writeln!(out, "\t{},", azimuths.join(", "));
+	}
+	let _ = writeln!(out, "];");
+	let _ = writeln!(out);
+	let _ = writeln!(out, "// left then right ear response for every azimuth, 16 bit full scale");
+	let _ = writeln!(out, "pub(super) static HRIRS: [[[i16; HRIR_LENGTH]; 2]; {}] = [", responses.len());
+	for response in responses.iter() {
+		let join = |samples: &[i16]| samples.iter().map(|sample| sample.to_string()).collect::<Vec<String>>().join(", ");
+		let _ = writeln!(out, "\t[[{}],", join(&response.left));
+		let _ = writeln!(out, "\t[{}]],", join(&response.right));
+	}
+	let _ = writeln!(out, "];");
+	return out;
+}
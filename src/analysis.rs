@@ -0,0 +1,420 @@
+use core::time::Duration;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use rodio::source::Source;
+
+use crate::composer::ProtoTrack;
+use crate::effects::{Biquad, db_to_gain};
+use crate::fft;
+use crate::theory::{self, ChordQuality};
+
+// the two stage k-weighting filter from itu-r bs.1770: a high shelf modelling the
+// head followed by a high pass. the coefficients are derived for any sample rate
+fn k_weighting(sample_rate: u32) -> (Biquad, Biquad) {
+	let fs = sample_rate as f32;
+
+	let f0 = 1681.9745;
+	let gain_db = 3.999_844;
+	let q = 0.707_175_2;
+	let k = (std::f32::consts::PI * f0 / fs).tan();
+	let vh = 10.0_f32.powf(gain_db / 20.0);
+	let vb = vh.powf(0.499_666_8);
+	let shelf = Biquad::new(
+		vh + vb * k / q + k * k,
+		2.0 * (k * k - vh),
+		vh - vb * k / q + k * k,
+		1.0 + k / q + k * k,
+		2.0 * (k * k - 1.0),
+		1.0 - k / q + k * k,
+	);
+
+	let f0 = 38.135_47;
+	let q = 0.500_327;
+	let k = (std::f32::consts::PI * f0 / fs).tan();
+	let high_pass = Biquad::new(
+		1.0,
+		-2.0,
+		1.0,
+		1.0 + k / q + k * k,
+		2.0 * (k * k - 1.0),
+		1.0 - k / q + k * k,
+	);
+
+	return (shelf, high_pass);
+}
+
+// integrated loudness of mono samples in lufs. 400 ms blocks overlapping by 75%
+// go through the absolute (-70 lufs) and relative (-10 lu) gates before averaging.
+// returns negative infinity for silence or anything shorter than one block
+pub fn measure_lufs(samples: &[f32], sample_rate: u32) -> f32 {
+	let (mut shelf, mut high_pass) = k_weighting(sample_rate);
+	let weighted: Vec<f32> = samples.iter().map(|sample| high_pass.process(shelf.process(*sample))).collect();
+
+	let block_size = (0.4 * sample_rate as f32) as usize;
+	let step = block_size / 4;
+	if block_size == 0 || weighted.len() < block_size {
+		return f32::NEG_INFINITY;
+	}
+
+	let mut block_powers: Vec<f32> = Vec::new();
+	let mut start = 0;
+	while start + block_size <= weighted.len() {
+		let power: f32 = weighted[start..start + block_size].iter().map(|s| s * s).sum::<f32>() / block_size as f32;
+		block_powers.push(power);
+		start += step;
+	}
+
+	let loudness = |power: f32| -0.691 + 10.0 * power.log10();
+	let mean = |powers: &[f32]| powers.iter().sum::<f32>() / powers.len() as f32;
+
+	let absolute_gated: Vec<f32> = block_powers.into_iter().filter(|power| loudness(*power) > -70.0).collect();
+	if absolute_gated.is_empty() {
+		return f32::NEG_INFINITY;
+	}
+
+	let relative_threshold = loudness(mean(&absolute_gated)) - 10.0;
+	let relative_gated: Vec<f32> = absolute_gated.into_iter().filter(|power| loudness(*power) > relative_threshold).collect();
+	if relative_gated.is_empty() {
+		return f32::NEG_INFINITY;
+	}
+
+	return loudness(mean(&relative_gated));
+}
+
+// scales the samples so they measure at target_lufs, silence is left alone
+pub fn normalize_to_lufs(samples: &mut [f32], sample_rate: u32, target_lufs: f32) {
+	let measured = measure_lufs(samples, sample_rate);
+	if !measured.is_finite() {
+		return;
+	}
+
+	let gain = db_to_gain(target_lufs - measured);
+	for sample in samples.iter_mut() {
+		*sample *= gain;
+	}
+}
+
+// total harmonic distortion as a ratio, 0.01 is 1%: the rms of harmonics 2 and up
+// (as far as nyquist) over the amplitude of the fundamental. every harmonic's level
+// is summed over the few bins the hann window spreads it across
+pub fn measure_thd(samples: &[f32], fundamental_hz: f32, sample_rate: u32) -> f32 {
+	//a fundamental at or past nyquist has no bin to be measured in
+	if samples.is_empty() || fundamental_hz <= 0.0 || fundamental_hz >= sample_rate as f32 / 2.0 {
+		return 0.0;
+	}
+
+	let size = samples.len().next_power_of_two();
+	let window = fft::hann_window(samples.len());
+	let windowed: Vec<f32> = samples.iter().zip(window.iter()).map(|(sample, gain)| sample * gain).collect();
+	let bins = fft::real_fft(&windowed, size);
+
+	let bin_hz = sample_rate as f32 / size as f32;
+	let harmonic_level = |harmonic: usize| -> f32 {
+		let center = ((harmonic as f32 * fundamental_hz / bin_hz).round() as usize).min(size / 2);
+		let spread = (size / samples.len()).max(1) * 2;
+		let low = center.saturating_sub(spread);
+		let high = (center + spread).min(size / 2);
+		return bins[low..=high].iter().map(|bin| bin.norm() * bin.norm()).sum::<f32>().sqrt();
+	};
+
+	let fundamental = harmonic_level(1);
+	if fundamental <= 0.0 {
+		return 0.0;
+	}
+	let nyquist = sample_rate as f32 / 2.0;
+	let mut harmonics = 0.0;
+	let mut harmonic = 2;
+	while (harmonic as f32 + 0.5) * fundamental_hz < nyquist {
+		harmonics += harmonic_level(harmonic).powi(2);
+		harmonic += 1;
+	}
+	return harmonics.sqrt() / fundamental;
+}
+
+// passes the signal through untouched and every `interval` posts the magnitude
+// spectrum of the last fft_size samples (mixed down to mono, hann windowed) to a
+// shared buffer a ui thread can poll. fft_size is rounded to a power of two between
+// 256 and 4096, the spectrum has fft_size / 2 bins from 0 hz up to just below nyquist
+pub struct SpectrumAnalyzer<S: Source<Item = f32>> {
+	source: S,
+	spectrum: Arc<Mutex<Vec<f32>>>,
+	window: Vec<f32>,
+	history: Vec<f32>,
+	position: usize,
+	frame_sum: f32,
+	channel: u16,
+	interval_frames: usize,
+	frames_until_update: usize,
+}
+
+impl<S: Source<Item = f32>> SpectrumAnalyzer<S> {
+	pub fn new(source: S, fft_size: usize, interval: Duration) -> SpectrumAnalyzer<S> {
+		let fft_size = fft_size.clamp(256, 4096).next_power_of_two().min(4096);
+		let interval_frames = ((interval.as_secs_f32() * source.sample_rate() as f32) as usize).max(1);
+		return SpectrumAnalyzer {
+			source,
+			spectrum: Arc::new(Mutex::new(vec![0.0; fft_size / 2])),
+			window: fft::hann_window(fft_size),
+			history: vec![0.0; fft_size],
+			position: 0,
+			frame_sum: 0.0,
+			channel: 0,
+			interval_frames,
+			frames_until_update: interval_frames,
+		}
+	}
+
+	pub fn spectrum_arc(&self) -> Arc<Mutex<Vec<f32>>> {
+		return self.spectrum.clone();
+	}
+
+	fn update_spectrum(&mut self) {
+		let len = self.history.len();
+		//oldest sample first, so the window lines up with the buffer
+		let windowed: Vec<f32> = (0..len)
+			.map(|n| self.history[(self.position + n) % len] * self.window[n])
+			.collect();
+		let bins = fft::real_fft(&windowed, len);
+		//a full scale sine reads 1.0 in its bin, the hann window halves the amplitude
+		let scale = 4.0 / len as f32;
+		if let Ok(mut spectrum) = self.spectrum.lock() {
+			for (magnitude, bin) in spectrum.iter_mut().zip(bins.iter()) {
+				*magnitude = bin.norm() * scale;
+			}
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for SpectrumAnalyzer<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for SpectrumAnalyzer<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let channels = self.source.channels();
+
+		self.frame_sum += sample;
+		self.channel += 1;
+		if self.channel >= channels {
+			self.history[self.position] = self.frame_sum / channels as f32;
+			self.position = (self.position + 1) % self.history.len();
+			self.frame_sum = 0.0;
+			self.channel = 0;
+
+			self.frames_until_update -= 1;
+			if self.frames_until_update == 0 {
+				self.update_spectrum();
+				self.frames_until_update = self.interval_frames;
+			}
+		}
+
+		return Some(sample);
+	}
+}
+
+//analyze_song reads every bar as 4/4
+const BEATS_PER_BAR: f32 = 4.0;
+//a bar whose best chord matches its notes worse than this gets no label
+const MIN_CHORD_SIMILARITY: f32 = 0.5;
+
+// a chord as a pitch class root, 0 is C, and its quality
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChordLabel {
+	pub root: u8,
+	pub quality: ChordQuality,
+}
+
+impl fmt::Display for ChordLabel {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let quality = match self.quality {
+			ChordQuality::Major => "major",
+			ChordQuality::Minor => "minor",
+			ChordQuality::Dominant7 => "7",
+		};
+		return write!(f, "{} {}", theory::pitch_class_name(self.root), quality);
+	}
+}
+
+// what analyze_song found. chords has one entry per bar, None where nothing
+// plays or nothing fits. the velocities are None without any notes
+#[derive(Clone, Debug, PartialEq)]
+pub struct SongAnalysis {
+	pub chords: Vec<Option<ChordLabel>>,
+	pub average_notes_per_bar: f32,
+	pub loudest_velocity: Option<f32>,
+	pub quietest_velocity: Option<f32>,
+	//standard deviation of the time between note starts, in seconds. 0.0 is a
+	//perfectly steady pulse
+	pub onset_interval_deviation: f32,
+	pub most_common_pitch_class: Option<u8>,
+}
+
+impl fmt::Display for SongAnalysis {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (bar, chord) in self.chords.iter().enumerate() {
+			match chord {
+				Some(chord) => writeln!(f, "bar {}: {}", bar + 1, chord)?,
+				None => writeln!(f, "bar {}: no chord", bar + 1)?,
+			}
+		}
+		writeln!(f, "notes per bar: {:.2}", self.average_notes_per_bar)?;
+		if let (Some(quietest), Some(loudest)) = (self.quietest_velocity, self.loudest_velocity) {
+			writeln!(f, "velocity: {:.2} to {:.2}", quietest, loudest)?;
+		}
+		writeln!(f, "onset interval deviation: {:.3}s", self.onset_interval_deviation)?;
+		return match self.most_common_pitch_class {
+			Some(pitch_class) => write!(f, "most common pitch class: {}", theory::pitch_class_name(pitch_class)),
+			None => write!(f, "most common pitch class: none"),
+		};
+	}
+}
+
+// looks over the notes of a whole arrangement. bars are four beats of the first
+// track with a tempo, tracks at other tempos are lined up in seconds. chords come
+// from a chroma vector of each bar, how long every pitch class sounds weighted by
+// velocity, matched against every major, minor and dominant 7th chord
+pub fn analyze_song(prototracks: &[ProtoTrack]) -> SongAnalysis {
+	//(start, end, pitch, velocity) of every note in seconds
+	let mut notes: Vec<(f32, f32, f32, f32)> = Vec::new();
+	for track in prototracks.iter().filter(|track| track.tempo > 0) {
+		let mut time = 0.0;
+		for note in track.notes.iter() {
+			let length = note.duration * 60.0 / track.tempo as f32;
+			if !note.is_rest() {
+				for pitch in note.pitches() {
+					notes.push((time, time + length, pitch, note.velocity));
+				}
+			}
+			time += length;
+		}
+	}
+
+	let bar_length = match prototracks.iter().find(|track| track.tempo > 0) {
+		Some(track) => BEATS_PER_BAR * 60.0 / track.tempo as f32,
+		None => 0.0,
+	};
+	let song_length = notes.iter().map(|note| note.1).fold(0.0, f32::max);
+	let bar_count = if bar_length > 0.0 { (song_length / bar_length - 1e-4).ceil().max(0.0) as usize } else { 0 };
+
+	let chords = (0..bar_count).map(|bar| {
+		let (bar_start, bar_end) = (bar as f32 * bar_length, (bar + 1) as f32 * bar_length);
+		let mut chroma = [0.0f32; 12];
+		for (start, end, pitch, velocity) in notes.iter() {
+			let overlap = end.min(bar_end) - start.max(bar_start);
+			if overlap > 0.0 {
+				chroma[pitch_class(*pitch) as usize] += overlap * velocity;
+			}
+		}
+		return label_chord(&chroma);
+	}).collect();
+
+	let average_notes_per_bar = if bar_count > 0 { notes.len() as f32 / bar_count as f32 } else { 0.0 };
+	let loudest_velocity = notes.iter().map(|note| note.3).reduce(f32::max);
+	let quietest_velocity = notes.iter().map(|note| note.3).reduce(f32::min);
+
+	//notes starting together count as one onset
+	let mut onsets: Vec<f32> = notes.iter().map(|note| note.0).collect();
+	onsets.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+	onsets.dedup_by(|a, b| (*a - *b).abs() < 1e-4);
+	let intervals: Vec<f32> = onsets.windows(2).map(|pair| pair[1] - pair[0]).collect();
+	let onset_interval_deviation = if intervals.is_empty() {
+		0.0
+	} else {
+		let mean = intervals.iter().sum::<f32>() / intervals.len() as f32;
+		(intervals.iter().map(|interval| (interval - mean).powi(2)).sum::<f32>() / intervals.len() as f32).sqrt()
+	};
+
+	let mut counts = [0usize; 12];
+	for note in notes.iter() {
+		counts[pitch_class(note.2) as usize] += 1;
+	}
+	let most_common_pitch_class = (0..12u8).filter(|pitch_class| counts[*pitch_class as usize] > 0)
+		.max_by_key(|pitch_class| (counts[*pitch_class as usize], std::cmp::Reverse(*pitch_class)));
+
+	return SongAnalysis {
+		chords,
+		average_notes_per_bar,
+		loudest_velocity,
+		quietest_velocity,
+		onset_interval_deviation,
+		most_common_pitch_class,
+	};
+}
+
+fn pitch_class(frequency: f32) -> u8 {
+	return theory::freq_to_midi_note(frequency) % 12;
+}
+
+// the chord whose tones line up best with the chroma, by cosine similarity. ties
+// go to the simpler chord, so a plain triad isn't called a 7th
+fn label_chord(chroma: &[f32; 12]) -> Option<ChordLabel> {
+	let energy = chroma.iter().map(|value| value * value).sum::<f32>().sqrt();
+	if energy <= 0.0 {
+		return None;
+	}
+	let mut best: Option<(f32, ChordLabel)> = None;
+	for quality in [ChordQuality::Major, ChordQuality::Minor, ChordQuality::Dominant7] {
+		let intervals = quality.intervals();
+		for root in 0..12u8 {
+			let matched: f32 = intervals.iter().map(|interval| chroma[((root + interval) % 12) as usize]).sum();
+			let similarity = matched / (energy * (intervals.len() as f32).sqrt());
+			if best.map_or(true, |(score, _)| similarity > score + 1e-6) {
+				best = Some((similarity, ChordLabel { root, quality }));
+			}
+		}
+	}
+	return best.filter(|(score, _)| *score >= MIN_CHORD_SIMILARITY).map(|(_, label)| label);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::composer::WavetableOscillator;
+	use crate::wavetable;
+
+	const TABLE_SIZE: usize = 2048;
+	const SAMPLE_RATE: u32 = 44_100;
+
+	fn render(table: Vec<f32>, frequency: f32) -> Vec<f32> {
+		let mut oscillator = WavetableOscillator::new(SAMPLE_RATE, table);
+		oscillator.set_frequency(frequency);
+		return oscillator.take(SAMPLE_RATE as usize / 2).collect();
+	}
+
+	#[test]
+	fn sine_has_low_thd() {
+		let table = (0..TABLE_SIZE).map(|n| (2.0 * std::f32::consts::PI * n as f32 / TABLE_SIZE as f32).sin()).collect();
+		let thd = measure_thd(&render(table, 1000.0), 1000.0, SAMPLE_RATE);
+		assert!(thd < 0.01, "sine thd {}", thd);
+	}
+
+	#[test]
+	fn saw_has_high_thd() {
+		let table = wavetable::build_bandlimited_saw(TABLE_SIZE, 1000.0, SAMPLE_RATE, None);
+		let thd = measure_thd(&render(table, 1000.0), 1000.0, SAMPLE_RATE);
+		assert!(thd > 0.5, "saw thd {}", thd);
+	}
+
+	#[test]
+	fn fundamental_past_nyquist_measures_nothing() {
+		let samples = vec![0.5; 1024];
+		assert_eq!(measure_thd(&samples, 30_000.0, SAMPLE_RATE), 0.0);
+	}
+}
@@ -1,10 +1,38 @@
 use core::time::Duration;
-use rodio::{OutputStream, source::Source, Sink};
+use std::fmt;
+use std::sync::{Arc, Barrier, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use rodio::{source::{Source, Zero}, Sink};
 use rand::Rng;
 
+use crate::effects::{AtomicF32, AudioEffect, EffectChain, FadeOut, Gain, LowPassFilter, ParamConsumer, Reverb, SideChainCompressor, SoftClip, SoftClipMode, Varispeed};
+use crate::modulation::{Adsr, AftertouchEvent, AftertouchRouting, BreathCurve, BreathEvent, Lfo, PitchBendEvent, Vca, Vibrato};
+use crate::oscillators::{AmOscillator, ExpressionVoice, MultiOscVoice, Oscillator, SubOscillator, SubWaveform, VibratoSource};
+use crate::patch::{Patch, PatchEffect, Waveform};
+use crate::theory;
+use crate::wavetable;
+
 const VOL_MULTIPLIER: f32 = 0.5;
-const SAMPLE_RATE: u32 = 44100;
+pub(crate) const SAMPLE_RATE: u32 = 44100;
+//how many samples a blend change is spread over, 10 ms at 44.1 khz
+const BLEND_SMOOTHING_SAMPLES: u32 = 441;
+//how hard a side chained track ducks under its key
+const SIDECHAIN_THRESHOLD_DB: f32 = -30.0;
+const SIDECHAIN_RATIO: f32 = 8.0;
+const SIDECHAIN_ATTACK: Duration = Duration::from_millis(5);
+const SIDECHAIN_RELEASE: Duration = Duration::from_millis(150);
+const PRINT_PLAYBACK_INTERVAL: Duration = Duration::from_millis(100);
+//how often Playback::wait checks on tracks that don't play through a sink
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const LATENCY_QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+//the usual two semitones of a synth's pitch wheel
+const DEFAULT_PITCH_BEND_RANGE: f32 = 2.0;
 
+pub(crate) type NoteSource = Box<dyn Source<Item = f32> + Send>;
+
+#[derive(Clone)]
 pub enum Instruments {
 	Sine,
 	Saw,
@@ -12,67 +40,852 @@ pub enum Instruments {
 	Triangle,
 	Snare,
 	Kick,
+	Custom(Patch),
+	//amplitude modulation of a sine by a sine at mod_ratio times the note's pitch
+	Am { mod_ratio: f32, mod_depth: f32 },
+	//saw with a square an octave below at sub_level
+	SubSaw(f32),
+	//two oscillators, the second detune semitones above the first, blended by mix
+	DualOsc { osc1_type: Waveform, osc2_type: Waveform, detune: f32, mix: f32 },
 }
 
-#[derive(Copy, Clone)]
+impl Instruments {
+	pub fn name(&self) -> &'static str {
+		return match self {
+			Instruments::Sine => "Sine",
+			Instruments::Saw => "Saw",
+			Instruments::Square => "Square",
+			Instruments::Triangle => "Triangle",
+			Instruments::Snare => "Snare",
+			Instruments::Kick => "Kick",
+			Instruments::Custom(_) => "Custom",
+			Instruments::Am { .. } => "Am",
+			Instruments::SubSaw(_) => "SubSaw",
+			Instruments::DualOsc { .. } => "DualOsc",
+		};
+	}
+}
+
+#[derive(Clone)]
 pub struct Note {
 	pub pitch: f32,
 	pub duration: f32,
+	//how hard the note is played, scales its amplitude. 1.0 is full
+	pub velocity: f32,
+	//chance of the note being heard each time the song is played, otherwise it's a rest
+	pub play_probability: f32,
+	pub vibrato: Option<Vibrato>,
+	//effects for this note alone, applied in order after its envelope. the effects
+	//hold state, so every time the note is played it builds new ones
+	pub effects: Option<Arc<[EffectFactory]>>,
+	//pitches sounding along with pitch for the whole note, making it a chord
+	pub chord: Option<Arc<[f32]>>,
 }
 
 impl Note {
-	fn new(pitch: f32, duration: f32) -> Note {
+	pub fn new(pitch: f32, duration: f32) -> Note {
 		return Note {
 			pitch,
 			duration,
+			velocity: 1.0,
+			play_probability: 1.0,
+			vibrato: None,
+			effects: None,
+			chord: None,
+		}
+	}
+
+	// the first pitch is the note's own, the rest go in its chord. no pitches is a rest
+	pub fn chord(pitches: &[f32], duration: f32) -> Note {
+		let mut note = Note::new(pitches.first().copied().unwrap_or(0.0), duration);
+		if pitches.len() > 1 {
+			note.chord = Some(pitches[1..].into());
+		}
+		return note;
+	}
+
+	// a rest is a note without a pitch, it only takes up time
+	pub fn rest(duration: f32) -> Note {
+		return Note::new(0.0, duration);
+	}
+
+	// the note's pitch followed by its chord's
+	pub fn pitches(&self) -> impl Iterator<Item = f32> + '_ {
+		return std::iter::once(self.pitch).chain(self.chord.iter().flat_map(|chord| chord.iter().copied()));
+	}
+
+	pub fn is_rest(&self) -> bool {
+		return self.pitch <= 0.0;
+	}
+}
+
+// builds a fresh copy of an effect, once for every time the note is played
+pub type EffectFactory = Arc<dyn Fn() -> Box<dyn AudioEffect> + Send + Sync>;
+
+// moves the cutoff of a note's LowPassFilter with its velocity, so harder notes
+// sound brighter. with tracking_amount 1.0 velocity 0.0 opens the filter to
+// min_cutoff and 1.0 to max_cutoff, lower amounts pull soft notes up towards
+// max_cutoff until at 0.0 every note gets max_cutoff
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VelocityFilterRouting {
+	pub min_cutoff: f32,
+	pub max_cutoff: f32,
+	pub tracking_amount: f32,
+}
+
+impl VelocityFilterRouting {
+	pub fn new(min_cutoff: f32, max_cutoff: f32, tracking_amount: f32) -> VelocityFilterRouting {
+		return VelocityFilterRouting {
+			min_cutoff,
+			max_cutoff,
+			tracking_amount,
 		}
 	}
+
+	pub fn cutoff(&self, velocity: f32) -> f32 {
+		let tracking = self.tracking_amount.clamp(0.0, 1.0);
+		let position = 1.0 - tracking * (1.0 - velocity.clamp(0.0, 1.0));
+		return self.min_cutoff + (self.max_cutoff - self.min_cutoff) * position;
+	}
 }
 
+// maps a note's velocity to the amplitude it's played at. the curves bend the
+// response so soft notes get quieter faster, Exponential and Logarithmic take
+// the steepness and Custom is a table of (velocity, amplitude) points
+#[derive(Clone, Debug, PartialEq)]
+pub enum VelocityCurve {
+	Linear,
+	Quadratic,
+	Cubic,
+	Exponential(f32),
+	Logarithmic(f32),
+	Custom(Vec<(f32, f32)>),
+}
+
+impl VelocityCurve {
+	pub fn apply(&self, velocity: f32) -> f32 {
+		let velocity = velocity.clamp(0.0, 1.0);
+		return match self {
+			VelocityCurve::Linear => velocity,
+			VelocityCurve::Quadratic => velocity * velocity,
+			VelocityCurve::Cubic => velocity * velocity * velocity,
+			VelocityCurve::Exponential(steepness) => {
+				if steepness.abs() < f32::EPSILON {
+					velocity
+				} else {
+					((steepness * velocity).exp() - 1.0) / (steepness.exp() - 1.0)
+				}
+			},
+			VelocityCurve::Logarithmic(steepness) => {
+				if *steepness <= 0.0 {
+					velocity
+				} else {
+					(1.0 + steepness * velocity).ln() / (1.0 + steepness).ln()
+				}
+			},
+			VelocityCurve::Custom(points) => lookup(points, velocity),
+		};
+	}
+}
+
+//linear interpolation between the points of a table sorted by velocity
+fn lookup(points: &[(f32, f32)], velocity: f32) -> f32 {
+	let (first, last) = match (points.first(), points.last()) {
+		(Some(first), Some(last)) => (first, last),
+		_ => return velocity,
+	};
+	if velocity <= first.0 {
+		return first.1;
+	}
+	for pair in points.windows(2) {
+		let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+		if velocity <= x1 {
+			if x1 - x0 <= 0.0 {
+				return y1;
+			}
+			return y0 + (y1 - y0) * (velocity - x0) / (x1 - x0);
+		}
+	}
+	return last.1;
+}
+
+#[derive(Clone)]
 pub struct ProtoTrack {
 	pub instrument: Instruments,
 	pub notes: Vec<Note>,
 	pub tempo: u32,
+	pub velocity_curve: VelocityCurve,
+	pub velocity_to_filter: Option<VelocityFilterRouting>,
+	//index of another track whose level ducks this one, see SideChainCompressor
+	pub sidechain_from: Option<usize>,
+	//-1.0 is hard left, 1.0 hard right. only the stereo mix from render::mix_tracks
+	//and jack place tracks, other live playback is mono
+	pub pan: f32,
+	pub aftertouch_routing: Option<AftertouchRouting>,
+	pub aftertouch: Vec<AftertouchEvent>,
+	pub pitch_bend_range: f32,
+	pub pitch_bend: Vec<PitchBendEvent>,
+	pub breath_curve: BreathCurve,
+	pub breath: Vec<BreathEvent>,
+	//ramps over the start and end of the whole track, see Track
+	pub fade_in: Option<Duration>,
+	pub fade_out: Option<Duration>,
 }
 
 impl ProtoTrack {
-	fn new(instrument: Instruments) -> ProtoTrack {
+	pub fn new(instrument: Instruments) -> ProtoTrack {
 		return ProtoTrack {
 			instrument, 
 			notes: Vec::new(),
 			tempo: 0,
+			velocity_curve: VelocityCurve::Linear,
+			velocity_to_filter: None,
+			sidechain_from: None,
+			pan: 0.0,
+			aftertouch_routing: None,
+			aftertouch: Vec::new(),
+			pitch_bend_range: DEFAULT_PITCH_BEND_RANGE,
+			pitch_bend: Vec::new(),
+			breath_curve: BreathCurve::default(),
+			breath: Vec::new(),
+			fade_in: None,
+			fade_out: None,
+		}
+	}
+
+	pub fn set_note_probability(&mut self, probability: f32) {
+		for note in self.notes.iter_mut() {
+			note.play_probability = probability;
+		}
+	}
+
+	// moves every note up or down by whole octaves, negative goes down
+	pub fn transpose_octaves(&mut self, octaves: i32) {
+		let ratio = 2.0_f32.powi(octaves);
+		for note in self.notes.iter_mut().filter(|note| !note.is_rest()) {
+			note.pitch *= ratio;
+		}
+	}
+
+	// the same track with every note factor times as long, at the same tempo
+	pub fn augment(&self, factor: f32) -> ProtoTrack {
+		let mut track = self.clone();
+		for note in track.notes.iter_mut() {
+			note.duration *= factor;
+		}
+		//automation is timed in beats too and has to stretch along with the notes
+		for event in track.pitch_bend.iter_mut() {
+			event.beat *= factor;
+		}
+		for event in track.aftertouch.iter_mut() {
+			event.offset *= factor;
+		}
+		for event in track.breath.iter_mut() {
+			event.offset *= factor;
+		}
+		return track;
+	}
+
+	// the opposite of augment, every note lasts 1 / factor as long
+	pub fn diminute(&self, factor: f32) -> ProtoTrack {
+		return self.augment(1.0 / factor);
+	}
+
+	// how long the track plays for. None if the tempo is 0, which can't be played
+	pub fn total_duration_secs(&self) -> Option<f32> {
+		if self.tempo == 0 {
+			return None;
+		}
+		return Some(self.notes.iter().map(|note| note.duration * 60.0 / self.tempo as f32).sum());
+	}
+
+	// when the note at index starts, in seconds from the start of the track. None
+	// past the last note or if the tempo is 0
+	pub fn note_onset_secs(&self, index: usize) -> Option<f32> {
+		if self.tempo == 0 || index >= self.notes.len() {
+			return None;
+		}
+		return Some(self.notes[..index].iter().map(|note| note.duration * 60.0 / self.tempo as f32).sum());
+	}
+
+	// detunes every note by its own random amount, up to cents_deviation / 2 either
+	// way. rests stay rests
+	pub fn humanize_pitch(&mut self, cents_deviation: f32, rng: &mut impl Rng) {
+		for note in self.notes.iter_mut().filter(|note| !note.is_rest()) {
+			let offset_cents = (rng.gen::<f32>() - 0.5) * cents_deviation;
+			note.pitch *= 2.0_f32.powf(offset_cents / 1200.0);
+		}
+	}
+
+	// swing_amount 0.0 leaves the notes straight, 1.0 turns each pair of equal notes
+	// into a triplet long-short. the pair keeps its total length so bars stay in place
+	pub fn apply_swing(&mut self, swing_amount: f32) {
+		let mut n = 0;
+		while n + 1 < self.notes.len() {
+			if (self.notes[n].duration - self.notes[n + 1].duration).abs() < f32::EPSILON {
+				let shift = swing_amount * self.notes[n].duration / 3.0;
+				self.notes[n].duration += shift;
+				self.notes[n + 1].duration -= shift;
+				n += 2;
+			} else {
+				n += 1;
+			}
+		}
+	}
+
+	// bends the track's timing to follow the tempo map while the notes keep their
+	// lengths in beats. the track's own tempo holds from beat 0 until the first event
+	// and the durations are rewritten in beats of that tempo, so it keeps working
+	// with everything else that reads `tempo`
+	pub fn apply_tempo_map(&mut self, events: &[TempoEvent]) {
+		if self.tempo == 0 || events.is_empty() {
+			return;
+		}
+		let mut events = events.to_vec();
+		events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+		let base_bpm = self.tempo as f32;
+
+		let mut beat = 0.0;
+		for note in self.notes.iter_mut() {
+			//integrate seconds per beat over the note in small steps
+			let steps = (note.duration / TEMPO_MAP_STEP).ceil().max(1.0) as usize;
+			let step = note.duration / steps as f32;
+			let mut seconds = 0.0;
+			for n in 0..steps {
+				seconds += step * 60.0 / tempo_at(&events, base_bpm, beat + (n as f32 + 0.5) * step);
+			}
+			beat += note.duration;
+			note.duration = seconds * base_bpm / 60.0;
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TempoChangeMode {
+	Instant,
+	Linear,
+	Exponential,
+}
+
+// the tempo reaches `bpm` at `beat`. the mode says how it gets there from the
+// previous event: jumps on the beat, or glides there linearly or exponentially
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TempoEvent {
+	pub beat: f32,
+	pub bpm: f32,
+	pub mode: TempoChangeMode,
+}
+
+impl TempoEvent {
+	pub fn new(beat: f32, bpm: f32, mode: TempoChangeMode) -> TempoEvent {
+		return TempoEvent {
+			beat,
+			bpm,
+			mode,
+		}
+	}
+}
+
+//beats per integration step of the tempo map
+const TEMPO_MAP_STEP: f32 = 1.0 / 64.0;
+
+// tempo at a beat, events have to be sorted
+fn tempo_at(events: &[TempoEvent], base_bpm: f32, beat: f32) -> f32 {
+	let mut previous = (0.0, base_bpm);
+	for event in events.iter() {
+		if beat < event.beat {
+			let progress = (beat - previous.0) / (event.beat - previous.0);
+			return match event.mode {
+				TempoChangeMode::Instant => previous.1,
+				TempoChangeMode::Linear => previous.1 + (event.bpm - previous.1) * progress,
+				TempoChangeMode::Exponential => previous.1 * (event.bpm / previous.1).powf(progress),
+			};
+		}
+		previous = (event.beat, event.bpm);
+	}
+	return previous.1;
+}
+
+// a note placed at an absolute time instead of after the note before it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PianoRollNote {
+	pub start_beat: f32,
+	pub duration_beats: f32,
+	pub pitch: f32,
+	pub velocity: f32,
+}
+
+impl PianoRollNote {
+	pub fn new(start_beat: f32, duration_beats: f32, pitch: f32, velocity: f32) -> PianoRollNote {
+		return PianoRollNote {
+			start_beat,
+			duration_beats,
+			pitch,
+			velocity,
+		}
+	}
+}
+
+// notes entered like on a piano roll: in any order, with gaps and overlapping
+#[derive(Clone)]
+pub struct PianoRollTrack {
+	pub notes: Vec<PianoRollNote>,
+	pub instrument: Instruments,
+	pub tempo: u32,
+}
+
+impl PianoRollTrack {
+	pub fn new(instrument: Instruments, tempo: u32) -> PianoRollTrack {
+		return PianoRollTrack {
+			notes: Vec::new(),
+			instrument,
+			tempo,
+		}
+	}
+
+	pub fn add_note(&mut self, note: PianoRollNote) {
+		self.notes.push(note);
+	}
+
+	// the notes as sequential tracks that play_song can queue. a sink plays one
+	// source at a time, so overlapping notes are spread over as many tracks as are
+	// needed: by start_beat, each note goes on the first track that's done by then,
+	// with a rest for the silence before it
+	pub fn to_prototracks(&self) -> Vec<ProtoTrack> {
+		let mut notes = self.notes.clone();
+		notes.sort_by(|a, b| a.start_beat.total_cmp(&b.start_beat));
+
+		let mut lanes: Vec<(ProtoTrack, f32)> = Vec::new();
+		for note in notes.iter().filter(|note| note.duration_beats > 0.0) {
+			let lane = match lanes.iter().position(|(_, end)| *end <= note.start_beat + f32::EPSILON) {
+				Some(lane) => lane,
+				None => {
+					let mut track = ProtoTrack::new(self.instrument.clone());
+					track.tempo = self.tempo;
+					lanes.push((track, 0.0));
+					lanes.len() - 1
+				},
+			};
+			let (track, end) = &mut lanes[lane];
+			if note.start_beat > *end {
+				track.notes.push(Note::rest(note.start_beat - *end));
+			}
+			let mut played = Note::new(note.pitch, note.duration_beats);
+			played.velocity = note.velocity;
+			track.notes.push(played);
+			*end = end.max(note.start_beat) + note.duration_beats;
 		}
+		return lanes.into_iter().map(|(track, _)| track).collect();
 	}
 }
 
+// a named part of a song: its tracks play together, `repeat` times in a row.
+// track n of every section continues track n of the section before it
+#[derive(Clone)]
+pub struct Section {
+	pub name: String,
+	pub tracks: Vec<ProtoTrack>,
+	pub repeat: u32,
+}
+
+impl Section {
+	pub fn new(name: &str, tracks: Vec<ProtoTrack>) -> Section {
+		return Section {
+			name: name.to_string(),
+			tracks,
+			repeat: 1,
+		}
+	}
+
+	// the piano roll's notes join the section as tracks of their own
+	pub fn add_piano_roll(&mut self, piano_roll: &PianoRollTrack) {
+		self.tracks.extend(piano_roll.to_prototracks());
+	}
+
+	// how long one pass of the section lasts, the longest of its tracks
+	fn length_secs(&self) -> f32 {
+		return self.tracks.iter()
+			.filter(|track| track.tempo > 0)
+			.map(|track| track.notes.iter().map(|note| note.duration).sum::<f32>() * 60.0 / track.tempo as f32)
+			.fold(0.0, f32::max);
+	}
+}
+
+#[derive(Clone, Default)]
+pub struct Song {
+	pub sections: Vec<Section>,
+}
+
+impl Song {
+	// the whole song as a single section, how songs were played before sections
+	pub fn from_prototracks(prototracks: Vec<ProtoTrack>) -> Song {
+		return Song {
+			sections: vec![Section::new("", prototracks)],
+		}
+	}
+
+	// one track per voice with every section and repeat laid end to end. sections can
+	// each have their own tempo, so the flattened tracks run at 60 bpm where a beat is
+	// a second. every section is padded with rests to its longest track so the next
+	// one starts on time, and a track keeps the instrument of the first section it's in
+	pub fn flatten(&self) -> Vec<ProtoTrack> {
+		let voice_count = self.sections.iter().map(|section| section.tracks.len()).max().unwrap_or(0);
+		let mut voices: Vec<Option<ProtoTrack>> = vec![None; voice_count];
+		let mut elapsed = 0.0;
+
+		for section in self.sections.iter() {
+			let section_length = section.length_secs();
+			for _ in 0..section.repeat {
+				for (voice, track) in voices.iter_mut().zip(section.tracks.iter()) {
+					let voice = voice.get_or_insert_with(|| {
+						let mut voice = track.clone();
+						voice.notes = Vec::new();
+						voice.aftertouch = Vec::new();
+						voice.pitch_bend = Vec::new();
+						voice.breath = Vec::new();
+						voice.tempo = 60;
+						if elapsed > 0.0 {
+							voice.notes.push(Note::rest(elapsed));
+						}
+						voice
+					});
+					let mut length = 0.0;
+					if track.tempo > 0 {
+						let first_note = voice.notes.len();
+						for event in track.pitch_bend.iter() {
+							voice.pitch_bend.push(PitchBendEvent::new(elapsed + event.beat * 60.0 / track.tempo as f32, event.value));
+						}
+						for event in track.breath.iter() {
+							let mut event = *event;
+							event.note_index += first_note;
+							event.offset *= 60.0 / track.tempo as f32;
+							voice.breath.push(event);
+						}
+						for event in track.aftertouch.iter() {
+							let mut event = *event;
+							event.note_index += first_note;
+							event.offset *= 60.0 / track.tempo as f32;
+							voice.aftertouch.push(event);
+						}
+						for note in track.notes.iter() {
+							let mut note = note.clone();
+							note.duration *= 60.0 / track.tempo as f32;
+							length += note.duration;
+							voice.notes.push(note);
+						}
+					}
+					if section_length - length > 1e-4 {
+						voice.notes.push(Note::rest(section_length - length));
+					}
+				}
+				//voices missing from this section sit it out
+				for voice in voices.iter_mut().skip(section.tracks.len()).flatten() {
+					voice.notes.push(Note::rest(section_length));
+				}
+				elapsed += section_length;
+			}
+		}
+
+		return voices.into_iter().flatten().collect();
+	}
+}
+
+// settings for how a song is played rather than what is played
+#[derive(Clone, Debug, Default)]
+pub struct SynthConfig {
+	//scale track volumes down so overlapping tracks can't add up past full scale
+	pub auto_headroom: bool,
+	pub backend: AudioBackend,
+	//start every track's oscillator at a random point of its cycle so tracks playing
+	//the same note don't line up perfectly
+	pub randomize_initial_phase: bool,
+	//print the output latency, then the note each track is on about ten times a
+	//second while start_song plays
+	pub print_playback: bool,
+	//measure the output latency for Playback::output_latency even without
+	//print_playback. it opens a second, short stream and can hold up the start by
+	//up to a second
+	pub query_latency: bool,
+}
+
+// where play_song sends its audio. Asio names the device to open and only works
+// on windows with the asio feature, Jack is the client name and needs the jack
+// feature. asking for a backend that isn't built in makes play_song fail
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum AudioBackend {
+	#[default]
+	Default,
+	Asio(String),
+	Jack(String),
+}
+
+// names of the output devices the backend can play through. for jack these are
+// the physical playback ports
+pub fn list_output_devices(backend: &AudioBackend) -> Vec<String> {
+	use cpal::traits::{DeviceTrait, HostTrait};
+
+	let host = match backend {
+		AudioBackend::Default => cpal::default_host(),
+		#[cfg(all(windows, feature = "asio"))]
+		AudioBackend::Asio(_) => match cpal::host_from_id(cpal::HostId::Asio) {
+			Ok(host) => host,
+			Err(_) => return Vec::new(),
+		},
+		#[cfg(feature = "jack")]
+		AudioBackend::Jack(_) => return crate::jack_backend::list_playback_ports(),
+		_ => return Vec::new(),
+	};
+	return match host.output_devices() {
+		Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+		Err(_) => Vec::new(),
+	};
+}
+
+// the time from audio being handed to the output device to it being heard, on the
+// default host. None asks the default output device. a short silent stream is
+// opened to measure it, so it's the latency of the device's default buffer size
+// that play_song also ends up with. more than 20 ms is noticeable when playing live
+pub fn query_output_latency(device_name: Option<&str>) -> Result<Duration, DeviceError> {
+	use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+	let host = cpal::default_host();
+	let device = match device_name {
+		None => host.default_output_device().ok_or(DeviceError::NoDevice)?,
+		Some(name) => host.output_devices().map_err(|_| DeviceError::NoDevice)?
+			.find(|device| device.name().map(|device_name| device_name == name).unwrap_or(false))
+			.ok_or_else(|| DeviceError::DeviceNotFound(name.to_string()))?,
+	};
+	let config = device.default_output_config()?;
+	let sample_format = config.sample_format();
+
+	let (sender, receiver) = std::sync::mpsc::channel();
+	let stream = device.build_output_stream_raw(
+		&config.config(),
+		sample_format,
+		move |data: &mut cpal::Data, info: &cpal::OutputCallbackInfo| {
+			//silence, which for unsigned samples is the middle of the range
+			match data.as_slice_mut::<u16>() {
+				Some(samples) => samples.iter_mut().for_each(|sample| *sample = u16::MAX / 2 + 1),
+				None => data.bytes_mut().iter_mut().for_each(|byte| *byte = 0),
+			}
+			let timestamp = info.timestamp();
+			let _ = sender.send(timestamp.playback.duration_since(&timestamp.callback).unwrap_or_default());
+		},
+		|_| {},
+	)?;
+	stream.play()?;
+	return receiver.recv_timeout(LATENCY_QUERY_TIMEOUT).map_err(|_| DeviceError::Timeout);
+}
+
 pub struct Track {
-	pub oscillator: WavetableOscillator,
+	pub instrument: Instruments,
+	pub oscillator: Oscillator,
 	pub sink: Sink,
 	pub notes: Vec<Note>,
 	pub volume: f32,
 	pub duration: f32,
 	pub tempo: u32,
+	pub envelope: Option<Adsr>,
+	pub velocity_curve: VelocityCurve,
+	pub velocity_to_filter: Option<VelocityFilterRouting>,
+	pub aftertouch: Option<AftertouchRouting>,
+	pub aftertouch_events: Vec<AftertouchEvent>,
+	//semitones the pitch moves with the wheel all the way up or down
+	pub pitch_bend_range: f32,
+	pub pitch_bend: Vec<PitchBendEvent>,
+	pub breath_curve: BreathCurve,
+	pub breath_events: Vec<BreathEvent>,
+	//linear ramps over the track's first and last moments. they're applied to all
+	//of its notes queued together, so a fade longer than a note spans several
+	pub fade_in: Option<Duration>,
+	pub fade_out: Option<Duration>,
+	//-1.0 is hard left, 1.0 hard right. only jack plays live tracks in stereo
+	pub pan: f32,
+	//one shared pressure per note, read by the note while it plays
+	pressures: Vec<Arc<AtomicF32>>,
+	//playback speed of the whole track, changed by PlaybackHandle::set_tempo
+	speed: Arc<AtomicF32>,
+	//samples of the track the sink has played so far
+	played: Arc<AtomicU64>,
+	//the gain the track plays at, the same as the sink's volume. jack reads it
+	//from here since it doesn't play through the sink
+	pub(crate) level: Arc<AtomicF32>,
+	//None while the track plays through its sink. a track played some other way
+	//is done when this is set, by running out of notes or by being stopped
+	pub(crate) finished: Option<Arc<AtomicBool>>,
 }
 
 impl Track {
-	fn new(oscillator: WavetableOscillator, sink: Sink, notes: Vec<Note>, tempo: u32) -> Track {
+	fn new(instrument: Instruments, oscillator: Oscillator, sink: Sink, notes: Vec<Note>, tempo: u32) -> Track {
+		let pressures = notes.iter().map(|_| Arc::new(AtomicF32::new(0.0))).collect();
 		return Track {
+			instrument,
 			oscillator,
 			sink,
 			notes,
 			volume: 1.0,
 			duration: 0.0,
 			tempo,
+			envelope: None,
+			velocity_curve: VelocityCurve::Linear,
+			velocity_to_filter: None,
+			aftertouch: None,
+			aftertouch_events: Vec::new(),
+			pitch_bend_range: DEFAULT_PITCH_BEND_RANGE,
+			pitch_bend: Vec::new(),
+			breath_curve: BreathCurve::default(),
+			breath_events: Vec::new(),
+			fade_in: None,
+			fade_out: None,
+			pan: 0.0,
+			pressures,
+			speed: Arc::new(AtomicF32::new(1.0)),
+			played: Arc::new(AtomicU64::new(0)),
+			level: Arc::new(AtomicF32::new(VOL_MULTIPLIER)),
+			finished: None,
+		}
+	}
+
+	// whether the track still has audio left to play
+	pub fn is_playing(&self) -> bool {
+		return match &self.finished {
+			Some(finished) => !finished.load(Ordering::Relaxed),
+			None => !self.sink.empty(),
+		};
+	}
+
+	// how far into the track playback is, in seconds of the track's own time so a
+	// changed tempo doesn't throw it off
+	pub fn elapsed_secs(&self) -> f32 {
+		return self.played.load(Ordering::Relaxed) as f32 / SAMPLE_RATE as f32;
+	}
+
+	// index of the note playing `time` seconds into the track and how many seconds
+	// of it are left, None once the track is over
+	pub fn note_at(&self, time: f32) -> Option<(usize, f32)> {
+		let mut end = 0.0;
+		for (n, note) in self.notes.iter().enumerate() {
+			end += note.duration * (60.0 / self.tempo as f32);
+			if time < end {
+				return Some((n, end - time));
+			}
+		}
+		return None;
+	}
+
+	// sets the pressure on a note, from 0.0 to 1.0. safe to call while the track
+	// plays, the note picks it up on its next sample. does nothing without routing
+	pub fn apply_aftertouch(&self, note_index: usize, pressure: f32) {
+		if let Some(note_pressure) = self.pressures.get(note_index) {
+			note_pressure.store(pressure.clamp(0.0, 1.0));
 		}
 	}
 }
 
-#[derive(Clone)]
+// a bank of sinks so notes can overlap: every note goes to a sink that has gone
+// quiet. with every sink busy the note is dropped rather than cutting one off
+pub struct PolyTrack {
+	voices: Vec<Sink>,
+	peak_voice_count: usize,
+}
+
+impl PolyTrack {
+	pub fn new(voices: Vec<Sink>) -> PolyTrack {
+		return PolyTrack {
+			voices,
+			peak_voice_count: 0,
+		}
+	}
+
+	// true if a free voice took the note
+	pub fn play_note<S>(&mut self, source: S) -> bool
+	where
+		S: Source<Item = f32> + Send + 'static,
+	{
+		let voice = match self.voices.iter().find(|voice| voice.empty()) {
+			Some(voice) => voice,
+			None => return false,
+		};
+		voice.append(source);
+		voice.play();
+		self.peak_voice_count = self.peak_voice_count.max(self.active_voice_count());
+		return true;
+	}
+
+	// every frequency on the oscillator for `duration`, see theory::voice_chord_spread
+	// for spreading the chord out first. returns how many of the notes got a voice
+	pub fn play_chord(&mut self, oscillator: &WavetableOscillator, frequencies: &[f32], duration: Duration) -> usize {
+		let mut played = 0;
+		for frequency in frequencies.iter() {
+			let mut note = oscillator.clone();
+			note.set_frequency(*frequency);
+			if self.play_note(note.take_duration(duration)) {
+				played += 1;
+			}
+		}
+		return played;
+	}
+
+	pub fn active_voice_count(&self) -> usize {
+		return self.voices.iter().filter(|voice| !voice.empty()).count();
+	}
+
+	// the most voices sounding at once when a note started, since the last reset
+	pub fn peak_voice_count(&self) -> usize {
+		return self.peak_voice_count;
+	}
+
+	pub fn reset_peak_voice_count(&mut self) {
+		self.peak_voice_count = self.active_voice_count();
+	}
+}
+
 pub struct WavetableOscillator {
-	sample_rate: u32,
-	wave_table: Vec<f32>,
-	index: f32,
-	index_increment: f32,
+	pub(crate) sample_rate: u32,
+	pub(crate) wave_table: Arc<[f32]>,
+	pub(crate) index: f32,
+	pub(crate) index_increment: f32,
+	pub lfo: Option<Lfo>,
+	pub breath_curve: BreathCurve,
+	//saturation as the very last stage of the oscillator, None leaves it clean
+	pub soft_clip: Option<SoftClip>,
+	//clones start from the top of the cycle instead of where this one is. notes
+	//are clones of their track's oscillator, so this also undoes
+	//randomize_initial_phase
+	pub reset_phase_on_clone: bool,
+	//new frequencies sent from another thread, picked up at the start of fill_block.
+	//a queue has only one reader, so clones start without one
+	pub frequency_updates: Option<ParamConsumer<f32>>,
+	//level of the oscillator, fill_block reads it once per block
+	pub vca: Vca,
+	secondary_table: Option<Arc<[f32]>>,
+	blend: f32,
+	blend_target: f32,
+	breath_pitch: f32,
+	breath_gain: f32,
+}
+
+// the tables are shared, so a clone costs the same whatever their size
+impl Clone for WavetableOscillator {
+	fn clone(&self) -> WavetableOscillator {
+		return WavetableOscillator {
+			sample_rate: self.sample_rate,
+			wave_table: self.wave_table.clone(),
+			index: if self.reset_phase_on_clone { 0.0 } else { self.index },
+			index_increment: self.index_increment,
+			lfo: self.lfo,
+			breath_curve: self.breath_curve,
+			soft_clip: self.soft_clip,
+			reset_phase_on_clone: self.reset_phase_on_clone,
+			frequency_updates: None,
+			vca: self.vca.clone(),
+			secondary_table: self.secondary_table.clone(),
+			blend: self.blend,
+			blend_target: self.blend_target,
+			breath_pitch: self.breath_pitch,
+			breath_gain: self.breath_gain,
+		}
+	}
 }
 
 // follows the oscillator code directly copied from a tutorial
@@ -81,39 +894,186 @@ pub struct WavetableOscillator {
 // each time we create a new note
 impl WavetableOscillator {
 
-	fn new(sample_rate: u32, wave_table: Vec<f32>) -> WavetableOscillator {
+	// takes a Vec<f32> or an Arc<[f32]> that's shared with other oscillators
+	pub fn new(sample_rate: u32, wave_table: impl Into<Arc<[f32]>>) -> WavetableOscillator {
 		return WavetableOscillator {
 			sample_rate,
-			wave_table,
+			wave_table: wave_table.into(),
 			index: 0.0,
 			index_increment: 0.0,
+			lfo: None,
+			breath_curve: BreathCurve::default(),
+			soft_clip: None,
+			reset_phase_on_clone: false,
+			frequency_updates: None,
+			vca: Vca::default(),
+			secondary_table: None,
+			blend: 0.0,
+			blend_target: 0.0,
+			breath_pitch: 1.0,
+			breath_gain: 1.0,
 		};
 	}
 
-	fn set_frequency(&mut self, frequency: f32) {
+	// samples `f` over one cycle, phase going from 0.0 to 1.0, and keeps only its
+	// first partial_limit harmonics so sharp edges in the function don't alias
+	pub fn from_fn(sample_rate: u32, table_size: usize, f: impl Fn(f32) -> f32, partial_limit: usize) -> WavetableOscillator {
+		let table_size = table_size.max(1);
+		let table: Vec<f32> = (0..table_size).map(|n| f(n as f32 / table_size as f32)).collect();
+		return WavetableOscillator::new(sample_rate, wavetable::bandlimit(&table, partial_limit));
+	}
+
+	// a new note: the pitch changes and a retriggering lfo starts its cycle over
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.retune(frequency);
+		if let Some(lfo) = self.lfo.as_mut() {
+			lfo.note_on();
+		}
+	}
+
+	// changes the pitch of the note already playing, for bends, vibrato and glides
+	pub fn retune(&mut self, frequency: f32) {
 		self.index_increment = frequency * self.wave_table.len() as f32 
 								/ self.sample_rate as f32;
 	}
 
-	fn get_sample(&mut self) -> f32 {
-		let sample = self.lerp();
-		self.index += self.index_increment;
+	// mean of the wave table, anything but zero pushes the speaker cone off center
+	pub fn dc_offset(&self) -> f32 {
+		return self.wave_table.iter().sum::<f32>() / self.wave_table.len() as f32;
+	}
+
+	pub fn remove_dc(&mut self) {
+		let offset = self.dc_offset();
+		self.wave_table = self.wave_table.iter().map(|sample| sample - offset).collect();
+	}
+
+	// a copy of the oscillator as it is right now, same table, pitch and phase, that
+	// fades out over `duration` on its own. append it to a sink to hold a note as a drone
+	pub fn freeze_to_pad(&self, duration: Duration) -> impl Source<Item = f32> + Send {
+		let fade_out = Adsr::new(0.0, 0.0, 1.0, duration.as_secs_f32());
+		return fade_out.apply(self.clone().take_duration(duration), duration);
+	}
+
+	pub fn set_random_start_phase(&mut self, rng: &mut impl Rng) {
+		self.index = rng.gen::<f32>() * self.wave_table.len() as f32;
+		self.index %= self.wave_table.len() as f32;
+	}
+
+	// breath pressure from 0.0 to 1.0, shaped by breath_curve: more pressure plays
+	// louder and slightly sharp. holds until the next call
+	pub fn apply_breath_control(&mut self, pressure: f32) {
+		let (cents, gain) = self.breath_curve.response(pressure);
+		self.breath_pitch = 2.0_f32.powf(cents / 1200.0);
+		self.breath_gain = gain;
+	}
+
+	// blend 0.0 plays only the oscillator's own table and 1.0 only the secondary one.
+	// changes glide over a few milliseconds so moving the blend doesn't click
+	pub fn set_blend(&mut self, secondary_table: &[f32], blend: f32) {
+		if self.secondary_table.as_deref() != Some(secondary_table) {
+			self.secondary_table = Some(secondary_table.into());
+		}
+		self.blend_target = blend.clamp(0.0, 1.0);
+	}
+
+	pub(crate) fn get_sample(&mut self) -> f32 {
+		return self.get_sample_shifted(0.0);
+	}
+
+	// get_sample reading the table `offset` places away from the oscillator's phase,
+	// for phase modulation. the phase itself moves on as usual so the shifts never
+	// add up, and the sample goes through the same gain and clipping
+	pub(crate) fn get_sample_shifted(&mut self, offset: f32) -> f32 {
+		if self.blend != self.blend_target {
+			let step = 1.0 / BLEND_SMOOTHING_SAMPLES as f32;
+			self.blend += (self.blend_target - self.blend).clamp(-step, step);
+		}
+
+		let raw = if offset == 0.0 {
+			self.lerp()
+		} else {
+			let index = self.index;
+			self.index = (index + offset).rem_euclid(self.wave_table.len() as f32);
+			let shifted = self.lerp();
+			self.index = index;
+			shifted
+		};
+		let sample = raw * self.breath_gain * self.vca.amplitude();
+		let mut increment = self.index_increment * self.breath_pitch;
+		// the lfo wobbles the pitch, its value is an offset in semitones
+		if let Some(lfo) = self.lfo.as_mut() {
+			increment *= 2.0_f32.powf(lfo.next_value(self.sample_rate) / 12.0);
+		}
+		self.index += increment;
 		self.index %= self.wave_table.len() as f32;
+		if let Some(clip) = self.soft_clip.as_mut() {
+			return clip.process(sample);
+		}
 		return sample;
 	}
 
-	fn lerp(&self) -> f32 {
-		let truncated_index = self.index as usize;
-		let next_index = (truncated_index + 1) % self.wave_table.len();
-		
-		let next_index_weight = self.index - truncated_index as f32;
-		let truncated_index_weight = 1.0 - next_index_weight;
+	// same samples as calling next() output.len() times. without an lfo or a
+	// secondary table the phase is kept in locals and only stored once at the end
+	pub fn fill_block(&mut self, output: &mut [f32]) {
+		//only the newest frequency matters
+		let newest = self.frequency_updates.as_mut().and_then(|updates| std::iter::from_fn(|| updates.pop()).last());
+		if let Some(frequency) = newest {
+			self.retune(frequency);
+		}
+
+		if self.lfo.is_some() || self.secondary_table.is_some() {
+			for sample in output.iter_mut() {
+				*sample = self.get_sample();
+			}
+			return;
+		}
 
-		return truncated_index_weight * self.wave_table[truncated_index] 
-			   + next_index_weight * self.wave_table[next_index];
+		let table = &self.wave_table;
+		let len = table.len();
+		let table_len = len as f32;
+		let increment = self.index_increment * self.breath_pitch;
+		let gain = self.breath_gain * self.vca.amplitude();
+		let mut index = self.index;
+		for sample in output.iter_mut() {
+			let truncated = index as usize % len;
+			let weight = index.fract();
+			*sample = ((1.0 - weight) * table[truncated] + weight * table[(truncated + 1) % len]) * gain;
+			index += increment;
+			if index >= table_len {
+				index -= table_len;
+			}
+		}
+		self.index = index % table_len;
+		if let Some(clip) = self.soft_clip.as_mut() {
+			for sample in output.iter_mut() {
+				*sample = clip.process(*sample);
+			}
+		}
+	}
+
+	fn lerp(&self) -> f32 {
+		let primary = interpolate(&self.wave_table, self.index);
+		if let Some(secondary_table) = &self.secondary_table {
+			//the secondary table is read at the same point of the cycle even if it's a different size
+			let secondary_index = self.index * secondary_table.len() as f32 / self.wave_table.len() as f32;
+			let secondary = interpolate(secondary_table, secondary_index);
+			return primary + self.blend * (secondary - primary);
+		}
+		return primary;
 	}
 } 
 
+pub(crate) fn interpolate(wave_table: &[f32], index: f32) -> f32 {
+	let truncated_index = index as usize % wave_table.len();
+	let next_index = (truncated_index + 1) % wave_table.len();
+	
+	let next_index_weight = index.fract();
+	let truncated_index_weight = 1.0 - next_index_weight;
+
+	return truncated_index_weight * wave_table[truncated_index] 
+		   + next_index_weight * wave_table[next_index];
+}
+
 impl Source for WavetableOscillator {
 	fn current_frame_len(&self) -> Option<usize> {
 		return None;
@@ -140,8 +1100,21 @@ impl Iterator for WavetableOscillator {
 	}
 }
 
-pub fn play_song(prototracks: Vec<ProtoTrack>) -> Result<char, ()> {
+//turns prototracks into tracks, queueing every note into the track's paused sink.
+//the sinks come from the caller so the same tracks can be played or rendered offline
+pub(crate) fn prepare_tracks(prototracks: &[ProtoTrack], sinks: Vec<Sink>, config: &SynthConfig) -> Vec<Track> {
+	let (tracks, queues) = build_tracks(prototracks, sinks, config);
+	for (track, queue) in tracks.iter().zip(queues) {
+		for source in queue {
+			track.sink.append(source);
+		}
+	}
+	return tracks;
+}
 
+//the tracks and, for each of them, the sources to play one after the other. they
+//aren't queued anywhere yet, jack plays them without going through the sinks
+pub(crate) fn build_tracks(prototracks: &[ProtoTrack], sinks: Vec<Sink>, config: &SynthConfig) -> (Vec<Track>, Vec<Vec<NoteSource>>) {
 	let mut rng = rand::thread_rng();
 
 	//initialize wave tables
@@ -178,48 +1151,800 @@ pub fn play_song(prototracks: Vec<ProtoTrack>) -> Result<char, ()> {
 		})
 	}
 
-	//create output stream
-	let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+	//every oscillator and note built from a table shares it instead of copying it
+	let sine_table: Arc<[f32]> = sine_table.into();
+	let saw_table: Arc<[f32]> = saw_table.into();
+	let square_table: Arc<[f32]> = square_table.into();
+	let triangle_table: Arc<[f32]> = triangle_table.into();
+	let noise_table: Arc<[f32]> = noise_table.into();
+
+	let waveform_table = |waveform: Waveform| -> Arc<[f32]> {
+		return match waveform {
+			Waveform::Sine => sine_table.clone(),
+			Waveform::Saw => saw_table.clone(),
+			Waveform::Square => square_table.clone(),
+			Waveform::Triangle => triangle_table.clone(),
+			Waveform::Noise => noise_table.clone(),
+		};
+	};
 
 	//convert prototracks to tracks
 	let mut tracks: Vec<Track> = Vec::new();
 
-	for proto in prototracks.iter(){
-		tracks.push(
-			Track::new(WavetableOscillator::new(SAMPLE_RATE, match &proto.instrument {
-				Instruments::Sine => sine_table.clone(),
-				Instruments::Saw => saw_table.clone(),
-				Instruments::Square => square_table.clone(),
-				Instruments::Triangle => triangle_table.clone(),
-				Instruments::Snare => noise_table.clone(),
-				Instruments::Kick => noise_table.clone(),
-			}), 
-			Sink::try_new(&stream_handle).unwrap(), 
-			proto.notes.clone(),
-			proto.tempo)
-		)
+	for (proto, sink) in prototracks.iter().zip(sinks){
+		let mut oscillator = WavetableOscillator::new(SAMPLE_RATE, match &proto.instrument {
+			Instruments::Sine => sine_table.clone(),
+			Instruments::Saw => saw_table.clone(),
+			Instruments::Square => square_table.clone(),
+			Instruments::Triangle => triangle_table.clone(),
+			Instruments::Snare => noise_table.clone(),
+			Instruments::Kick => noise_table.clone(),
+			Instruments::Custom(patch) => waveform_table(patch.waveform),
+			Instruments::Am { .. } => sine_table.clone(),
+			Instruments::SubSaw(_) => saw_table.clone(),
+			Instruments::DualOsc { osc1_type, .. } => waveform_table(*osc1_type),
+		});
+
+		//custom instruments carry the rest of their sound in the patch
+		if let Instruments::Custom(patch) = &proto.instrument {
+			oscillator.lfo = patch.lfo.map(|mut lfo| {
+				//tempo locked lfos follow the track they play on
+				lfo.bpm = proto.tempo;
+				lfo
+			});
+		}
+		if config.randomize_initial_phase {
+			oscillator.set_random_start_phase(&mut rng);
+		}
+
+		//instruments built from more than one oscillator wrap the base one
+		let oscillator = match &proto.instrument {
+			Instruments::Am { mod_ratio, mod_depth } => {
+				Oscillator::Am(AmOscillator::new(oscillator.clone(), oscillator, *mod_depth, 1.0, *mod_ratio))
+			},
+			Instruments::SubSaw(sub_level) => {
+				Oscillator::Sub(SubOscillator::new(oscillator, *sub_level, SubWaveform::Square))
+			},
+			Instruments::DualOsc { osc2_type, detune, mix, .. } => {
+				let mut osc2 = WavetableOscillator::new(SAMPLE_RATE, waveform_table(*osc2_type));
+				if config.randomize_initial_phase {
+					osc2.set_random_start_phase(&mut rng);
+				}
+				Oscillator::Multi(MultiOscVoice::new(oscillator, osc2, *mix, *detune))
+			},
+			_ => Oscillator::Wavetable(oscillator),
+		};
+
+		let mut track = Track::new(proto.instrument.clone(), oscillator, sink, proto.notes.clone(), proto.tempo);
+		track.velocity_curve = proto.velocity_curve.clone();
+		track.velocity_to_filter = proto.velocity_to_filter;
+		track.aftertouch = proto.aftertouch_routing;
+		track.aftertouch_events = proto.aftertouch.clone();
+		track.pitch_bend_range = proto.pitch_bend_range;
+		track.pitch_bend = proto.pitch_bend.clone();
+		track.breath_curve = proto.breath_curve;
+		track.breath_events = proto.breath.clone();
+		track.fade_in = proto.fade_in;
+		track.fade_out = proto.fade_out;
+		track.pan = proto.pan;
+		if let Instruments::Custom(patch) = &proto.instrument {
+			track.envelope = Some(patch.envelope);
+			track.volume = patch.volume;
+		}
+
+		tracks.push(track);
+	}
+
+	if config.auto_headroom {
+		for (track, gain) in tracks.iter_mut().zip(compute_headroom_gains(prototracks)) {
+			track.volume *= gain;
+		}
 	}
 
 	for track in tracks.iter_mut() {
-		track.duration = 0.0;
 		track.sink.pause();
-		track.sink.set_volume(VOL_MULTIPLIER);
-		for note in track.notes.iter() {
-			track.duration += note.duration * (60.0 / track.tempo as f32);
-			track.oscillator.set_frequency(note.pitch);
-			track.sink.append(track.oscillator.clone().take_duration(std::time::Duration::from_secs_f32(note.duration * (60.0 / track.tempo as f32))));
+		track.sink.set_volume(VOL_MULTIPLIER * track.volume);
+		track.level.store(VOL_MULTIPLIER * track.volume);
+		track.duration = track.notes.iter().map(|note| note.duration * (60.0 / track.tempo as f32)).sum();
+	}
+
+	let mut queues: Vec<Vec<NoteSource>> = Vec::with_capacity(tracks.len());
+	for (n, proto) in prototracks.iter().enumerate() {
+		let mut sources = note_sources(&tracks[n], &mut rng);
+		//a patch's effects run over the whole track, reverb tails carry on past each note
+		if let Instruments::Custom(patch) = &proto.instrument {
+			if !patch.effects.is_empty() {
+				sources = vec![patch_effects(NoteQueue::new(sources), &patch.effects)];
+			}
+		}
+		//a fade covers the whole track, so the notes are joined into one stream first
+		if tracks[n].fade_in.is_some() || tracks[n].fade_out.is_some() {
+			sources = vec![fade_edges(NoteQueue::new(sources), &tracks[n])];
+		}
+		match proto.sidechain_from.filter(|key| *key != n && *key < tracks.len()) {
+			Some(key) => {
+				//the key track is played a second time, silently, just to drive the compressor
+				let key_sources = note_sources(&tracks[key], &mut rng);
+				let compressed = SideChainCompressor::new(
+					NoteQueue::new(sources),
+					NoteQueue::new(key_sources),
+					SIDECHAIN_THRESHOLD_DB,
+					SIDECHAIN_RATIO,
+					SIDECHAIN_ATTACK,
+					SIDECHAIN_RELEASE,
+				);
+				let counted = PlayedCounter::new(compressed, tracks[n].played.clone());
+				queues.push(vec![Box::new(Varispeed::new(counted, tracks[n].speed.clone()))]);
+			},
+			None => {
+				queues.push(sources.into_iter().map(|source| {
+					let counted = PlayedCounter::new(source, tracks[n].played.clone());
+					return Box::new(Varispeed::new(counted, tracks[n].speed.clone())) as NoteSource;
+				}).collect());
+			},
 		}
 	}
 
-	//we set each track to play at the same time; we also keep track on which track is the longest
-	//so we don't stop executing program while it's still running.
-	let mut longest_duration : f32 = 0.0;
-	for track in tracks.iter_mut() {
-		track.sink.play();
-		if track.duration > longest_duration {longest_duration = track.duration}
+	return (tracks, queues);
+}
+
+// the track's fades around its joined up notes
+fn fade_edges(source: impl Source<Item = f32> + Send + 'static, track: &Track) -> NoteSource {
+	let faded: NoteSource = match track.fade_in {
+		Some(fade_in) => Box::new(source.fade_in(fade_in)),
+		None => Box::new(source),
+	};
+	return match track.fade_out {
+		Some(fade_out) => Box::new(FadeOut::new(faded, fade_out, Duration::from_secs_f32(track.duration))),
+		None => faded,
+	};
+}
+
+// runs the joined up notes through a patch's effects chain, first effect first
+fn patch_effects(source: impl Source<Item = f32> + Send + 'static, effects: &[PatchEffect]) -> NoteSource {
+	let mut chained: NoteSource = Box::new(source);
+	for effect in effects.iter() {
+		let sample_rate = chained.sample_rate();
+		chained = match *effect {
+			PatchEffect::LowPass { cutoff, q } => Box::new(EffectChain::new(chained, vec![
+				Box::new(LowPassFilter::new(cutoff, q, sample_rate)) as Box<dyn AudioEffect>,
+			])),
+			PatchEffect::SoftClip { drive, output_gain } => Box::new(EffectChain::new(chained, vec![
+				Box::new(SoftClip::new(SoftClipMode::Tanh, drive, output_gain)) as Box<dyn AudioEffect>,
+			])),
+			PatchEffect::Gain { db } => Box::new(EffectChain::new(chained, vec![
+				Box::new(Gain::new(db)) as Box<dyn AudioEffect>,
+			])),
+			PatchEffect::Reverb { decay, damping, mix } => {
+				//from_toml checks the decay, but the fields can be set by hand too
+				let decay = Duration::try_from_secs_f32(decay.max(0.0)).unwrap_or(Duration::ZERO);
+				Box::new(Reverb::new(chained, decay, damping, mix))
+			},
+		};
+	}
+	return chained;
+}
+
+// plays note sources one after the other like source::from_iter, but holds on to
+// the finished ones instead of dropping them on the way, so pulling samples never
+// frees anything. that matters where samples are pulled on a real time thread,
+// as jack does. the sources go when the queue itself is dropped
+pub(crate) struct NoteQueue {
+	sources: Vec<NoteSource>,
+	current: usize,
+}
+
+impl NoteQueue {
+	pub(crate) fn new(sources: Vec<NoteSource>) -> NoteQueue {
+		return NoteQueue {
+			sources,
+			current: 0,
+		}
+	}
+}
+
+impl Source for NoteQueue {
+	fn current_frame_len(&self) -> Option<usize> {
+		return match self.sources.get(self.current) {
+			Some(source) => source.current_frame_len(),
+			None => Some(0),
+		};
+	}
+
+	fn channels(&self) -> u16 {
+		return self.sources.get(self.current).map_or(1, |source| source.channels());
 	}
 
-	std::thread::sleep(std::time::Duration::from_secs_f32(longest_duration));
+	fn sample_rate(&self) -> u32 {
+		return self.sources.get(self.current).map_or(SAMPLE_RATE, |source| source.sample_rate());
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for NoteQueue {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while let Some(source) = self.sources.get_mut(self.current) {
+			if let Some(sample) = source.next() {
+				return Some(sample);
+			}
+			self.current += 1;
+		}
+		return None;
+	}
+}
+
+// counts the samples pulled through it into a counter shared with the track
+struct PlayedCounter<S: Source<Item = f32>> {
+	source: S,
+	played: Arc<AtomicU64>,
+}
+
+impl<S: Source<Item = f32>> PlayedCounter<S> {
+	fn new(source: S, played: Arc<AtomicU64>) -> PlayedCounter<S> {
+		return PlayedCounter {
+			source,
+			played,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for PlayedCounter<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for PlayedCounter<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		self.played.fetch_add(1, Ordering::Relaxed);
+		return Some(sample);
+	}
+}
+
+// one source per note, shaped the way the track plays them
+fn note_sources(track: &Track, rng: &mut impl Rng) -> Vec<NoteSource> {
+	let mut oscillator = track.oscillator.clone();
+	let mut sources: Vec<NoteSource> = Vec::with_capacity(track.notes.len());
+	let mut beat = 0.0;
+	for (n, note) in track.notes.iter().enumerate() {
+		let note_start = beat;
+		beat += note.duration;
+		let note_duration = Duration::from_secs_f32(note.duration * (60.0 / track.tempo as f32));
+		let skipped = note.play_probability < 1.0 && rng.gen::<f32>() >= note.play_probability;
+		if note.is_rest() || skipped {
+			sources.push(Box::new(Zero::<f32>::new(1, SAMPLE_RATE).take_duration(note_duration)));
+			continue;
+		}
+		oscillator.set_frequency(note.pitch);
+		let breath: Vec<(i64, f32)> = track.breath_events.iter()
+			.filter(|event| event.note_index == n)
+			.map(|event| ((event.offset * SAMPLE_RATE as f32 * 60.0 / track.tempo as f32) as i64, event.pressure))
+			.collect();
+		let voice: NoteSource = if track.aftertouch.is_some() || !track.pitch_bend.is_empty() || !breath.is_empty() {
+			let samples_per_beat = SAMPLE_RATE as f32 * 60.0 / track.tempo as f32;
+			let mut voice = ExpressionVoice::new(oscillator.clone(), note.pitch);
+			if let Some(routing) = track.aftertouch {
+				let scheduled = track.aftertouch_events.iter()
+					.filter(|event| event.note_index == n)
+					.map(|event| ((event.offset.max(0.0) * samples_per_beat) as u64, event.pressure))
+					.collect();
+				voice = voice.with_aftertouch(routing, track.pressures[n].clone(), scheduled);
+			}
+			if !track.pitch_bend.is_empty() {
+				let points = track.pitch_bend.iter()
+					.map(|event| (((event.beat - note_start) * samples_per_beat) as i64, event.value))
+					.collect();
+				voice = voice.with_pitch_bend(points, track.pitch_bend_range);
+			}
+			if !breath.is_empty() {
+				voice = voice.with_breath(breath, track.breath_curve);
+			}
+			if let Some(vibrato) = note.vibrato {
+				voice = voice.with_vibrato(vibrato);
+			}
+			Box::new(voice)
+		} else if let Some(vibrato) = note.vibrato {
+			Box::new(VibratoSource::new(oscillator.clone(), note.pitch, vibrato))
+		} else {
+			Box::new(oscillator.clone())
+		};
+		//the chord's other pitches are plain copies of the oscillator mixed in, the
+		//note's expression only moves its own pitch
+		let voice: NoteSource = match &note.chord {
+			Some(chord) if !chord.is_empty() => {
+				let mut mixed = voice;
+				for pitch in chord.iter() {
+					let mut tone = oscillator.clone();
+					tone.set_frequency(*pitch);
+					mixed = Box::new(mixed.mix(tone));
+				}
+				Box::new(mixed.amplify(1.0 / (chord.len() + 1) as f32))
+			},
+			_ => voice,
+		};
+		let source = voice.take_duration(note_duration).amplify(track.velocity_curve.apply(note.velocity));
+		let shaped: NoteSource = match track.envelope {
+			Some(envelope) => Box::new(envelope.apply(source, note_duration)),
+			None => Box::new(source),
+		};
+		let mut effects: Vec<Box<dyn AudioEffect>> = note.effects.iter()
+			.flat_map(|effects| effects.iter().map(|build| build()))
+			.collect();
+		if let Some(routing) = track.velocity_to_filter {
+			for effect in effects.iter_mut() {
+				if let Some(filter) = effect.as_low_pass_mut() {
+					filter.set_cutoff(routing.cutoff(note.velocity));
+				}
+			}
+		}
+		if effects.is_empty() {
+			sources.push(shaped);
+		} else {
+			sources.push(Box::new(EffectChain::new(shaped, effects)));
+		}
+	}
+	return sources;
+}
+
+// a track's gain is one over the largest number of tracks that ever sound together
+// with any of its notes, so however the notes line up the gains of the tracks
+// playing at one moment never add up to more than one
+pub fn compute_headroom_gains(prototracks: &[ProtoTrack]) -> Vec<f32> {
+	//the (start, end) time in seconds of every sounding note, per track
+	let spans: Vec<Vec<(f32, f32)>> = prototracks.iter().map(|proto| {
+		let mut spans: Vec<(f32, f32)> = Vec::new();
+		if proto.tempo == 0 {
+			return spans;
+		}
+		let mut time = 0.0;
+		for note in proto.notes.iter() {
+			let length = note.duration * (60.0 / proto.tempo as f32);
+			if !note.is_rest() {
+				spans.push((time, time + length));
+			}
+			time += length;
+		}
+		return spans;
+	}).collect();
+
+	return spans.iter().map(|track_spans| {
+		let mut most_simultaneous = 1;
+		for (start, end) in track_spans.iter() {
+			let simultaneous = spans.iter()
+				.filter(|other| other.iter().any(|(other_start, other_end)| other_start < end && start < other_end))
+				.count();
+			most_simultaneous = most_simultaneous.max(simultaneous);
+		}
+		return 1.0 / most_simultaneous as f32;
+	}).collect();
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlignmentMode {
+	ToLongest,
+	ToShortest,
+	ToFixed(Duration),
+}
+
+// makes every track last equally long. tracks that are too short get a rest at the
+// end, tracks that are too long lose their trailing notes and the note crossing the
+// end is cut short. tracks without a tempo can't be measured and are left alone
+pub fn align_track_durations(prototracks: &mut [ProtoTrack], alignment: AlignmentMode) {
+	let lengths: Vec<f32> = prototracks.iter()
+		.filter(|proto| proto.tempo > 0)
+		.map(|proto| proto.notes.iter().map(|note| note.duration).sum::<f32>() * 60.0 / proto.tempo as f32)
+		.collect();
+	if lengths.is_empty() {
+		return;
+	}
+	let target = match alignment {
+		AlignmentMode::ToLongest => lengths.iter().cloned().fold(0.0, f32::max),
+		AlignmentMode::ToShortest => lengths.iter().cloned().fold(f32::MAX, f32::min),
+		AlignmentMode::ToFixed(duration) => duration.as_secs_f32(),
+	};
+
+	for proto in prototracks.iter_mut().filter(|proto| proto.tempo > 0) {
+		let target_beats = target * proto.tempo as f32 / 60.0;
+		let mut beats = 0.0;
+		let mut kept = 0;
+		for note in proto.notes.iter_mut() {
+			if beats >= target_beats {
+				break;
+			}
+			note.duration = note.duration.min(target_beats - beats);
+			beats += note.duration;
+			kept += 1;
+		}
+		proto.notes.truncate(kept);
+
+		//rounding leaves tiny gaps, don't pad those with rests nobody can hear
+		if target_beats - beats > 1e-4 {
+			proto.notes.push(Note::rest(target_beats - beats));
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum PlayError {
+	Stream(rodio::StreamError),
+	Play(rodio::PlayError),
+	UnsupportedBackend(AudioBackend),
+	DeviceNotFound(String),
+	#[cfg(feature = "jack")]
+	Jack(jack::Error),
+}
 
-	Ok('👍')
-}
\ No newline at end of file
+impl fmt::Display for PlayError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			PlayError::Stream(error) => write!(f, "couldn't open audio output: {}", error),
+			PlayError::Play(error) => write!(f, "couldn't play on audio output: {}", error),
+			PlayError::UnsupportedBackend(backend) => write!(f, "audio backend {:?} isn't available in this build", backend),
+			PlayError::DeviceNotFound(name) => write!(f, "no output device called {}", name),
+			#[cfg(feature = "jack")]
+			PlayError::Jack(error) => write!(f, "jack error: {}", error),
+		};
+	}
+}
+
+impl std::error::Error for PlayError {}
+
+#[derive(Debug)]
+pub enum DeviceError {
+	NoDevice,
+	DeviceNotFound(String),
+	Config(cpal::DefaultStreamConfigError),
+	Build(cpal::BuildStreamError),
+	Play(cpal::PlayStreamError),
+	//the device never asked for audio
+	Timeout,
+}
+
+impl fmt::Display for DeviceError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			DeviceError::NoDevice => write!(f, "no output device available"),
+			DeviceError::DeviceNotFound(name) => write!(f, "no output device called {}", name),
+			DeviceError::Config(error) => write!(f, "couldn't get the device's output config: {}", error),
+			DeviceError::Build(error) => write!(f, "couldn't open an output stream: {}", error),
+			DeviceError::Play(error) => write!(f, "couldn't start the output stream: {}", error),
+			DeviceError::Timeout => write!(f, "the output device didn't start playing"),
+		};
+	}
+}
+
+impl std::error::Error for DeviceError {}
+
+impl From<cpal::DefaultStreamConfigError> for DeviceError {
+	fn from(error: cpal::DefaultStreamConfigError) -> DeviceError {
+		return DeviceError::Config(error);
+	}
+}
+
+impl From<cpal::BuildStreamError> for DeviceError {
+	fn from(error: cpal::BuildStreamError) -> DeviceError {
+		return DeviceError::Build(error);
+	}
+}
+
+impl From<cpal::PlayStreamError> for DeviceError {
+	fn from(error: cpal::PlayStreamError) -> DeviceError {
+		return DeviceError::Play(error);
+	}
+}
+
+impl From<rodio::StreamError> for PlayError {
+	fn from(error: rodio::StreamError) -> PlayError {
+		return PlayError::Stream(error);
+	}
+}
+
+impl From<rodio::PlayError> for PlayError {
+	fn from(error: rodio::PlayError) -> PlayError {
+		return PlayError::Play(error);
+	}
+}
+
+#[cfg(feature = "jack")]
+impl From<jack::Error> for PlayError {
+	fn from(error: jack::Error) -> PlayError {
+		return PlayError::Jack(error);
+	}
+}
+
+// a song playing in the background on a rodio output stream or as a jack client.
+// the output stops when this is dropped, hand out handle()s to control the song
+// from elsewhere
+pub struct Playback {
+	_output: PlaybackOutput,
+	handle: PlaybackHandle,
+	latency: Option<Duration>,
+}
+
+// what has to stay alive for the song to keep playing
+pub(crate) enum PlaybackOutput {
+	Rodio {
+		_stream: rodio::OutputStream,
+	},
+	#[cfg(feature = "jack")]
+	Jack {
+		_client: jack::AsyncClient<(), crate::jack_backend::Process>,
+	},
+}
+
+impl Playback {
+	pub fn handle(&self) -> PlaybackHandle {
+		return self.handle.clone();
+	}
+
+	// see query_output_latency. only known on the default backend, when
+	// SynthConfig::query_latency or print_playback was set
+	pub fn output_latency(&self) -> Option<Duration> {
+		return self.latency;
+	}
+
+	// blocks until every track has played to the end
+	pub fn wait(&self) {
+		for track in self.handle.state.tracks.iter() {
+			match &track.finished {
+				Some(_) => while track.is_playing() {
+					thread::sleep(WAIT_POLL_INTERVAL);
+				},
+				None => track.sink.sleep_until_end(),
+			}
+		}
+	}
+}
+
+#[cfg(feature = "jack")]
+impl Playback {
+	pub(crate) fn new(output: PlaybackOutput, handle: PlaybackHandle) -> Playback {
+		return Playback {
+			_output: output,
+			handle,
+			latency: None,
+		}
+	}
+}
+
+// controls for a playing song that can be cloned and sent to other threads.
+// track indices are the same as in the flattened song
+#[derive(Clone)]
+pub struct PlaybackHandle {
+	state: Arc<PlaybackState>,
+}
+
+struct PlaybackState {
+	tracks: Vec<Track>,
+	mix: Mutex<Vec<TrackMix>>,
+	base_tempo: f32,
+	//the name the jack server gave the client, empty on the other backends
+	#[cfg(feature = "jack")]
+	jack_client_name: String,
+}
+
+#[derive(Copy, Clone)]
+struct TrackMix {
+	volume: f32,
+	muted: bool,
+}
+
+impl PlaybackHandle {
+	fn new(tracks: Vec<Track>, base_tempo: f32) -> PlaybackHandle {
+		let mix = tracks.iter().map(|_| TrackMix { volume: 1.0, muted: false }).collect();
+		return PlaybackHandle {
+			state: Arc::new(PlaybackState {
+				tracks,
+				mix: Mutex::new(mix),
+				base_tempo,
+				#[cfg(feature = "jack")]
+				jack_client_name: String::new(),
+			}),
+		}
+	}
+
+	// see SynthConfig::print_playback
+	pub(crate) fn print_while_playing(&self) {
+		let state = Arc::downgrade(&self.state);
+		thread::spawn(move || print_playback(state));
+	}
+
+	#[cfg(feature = "jack")]
+	pub(crate) fn with_jack_client(tracks: Vec<Track>, base_tempo: f32, client_name: &str) -> PlaybackHandle {
+		let mix = tracks.iter().map(|_| TrackMix { volume: 1.0, muted: false }).collect();
+		return PlaybackHandle {
+			state: Arc::new(PlaybackState {
+				tracks,
+				mix: Mutex::new(mix),
+				base_tempo,
+				jack_client_name: client_name.to_string(),
+			}),
+		}
+	}
+
+	// the name the jack server gave the client, it can differ from the one asked for
+	// if that was already taken. empty when the song isn't playing through jack
+	#[cfg(feature = "jack")]
+	pub fn jack_client_name(&self) -> &str {
+		return &self.state.jack_client_name;
+	}
+
+	pub fn track_count(&self) -> usize {
+		return self.state.tracks.len();
+	}
+
+	pub fn is_playing(&self) -> bool {
+		return self.state.tracks.iter().any(|track| track.is_playing());
+	}
+
+	// volume on top of the track's own, 1.0 leaves it as it was written
+	pub fn set_track_volume(&self, index: usize, volume: f32) {
+		self.update_mix(index, |mix| mix.volume = volume.max(0.0));
+	}
+
+	pub fn set_track_muted(&self, index: usize, muted: bool) {
+		self.update_mix(index, |mix| mix.muted = muted);
+	}
+
+	// the notes are already queued when the song starts, so a new tempo is played by
+	// speeding the whole song up or down like a turntable: the pitch moves with it
+	pub fn set_tempo(&self, bpm: f32) {
+		if self.state.base_tempo <= 0.0 || bpm <= 0.0 {
+			return;
+		}
+		for track in self.state.tracks.iter() {
+			track.speed.store(bpm / self.state.base_tempo);
+		}
+	}
+
+	// stops every track at the same moment, a stopped song can't be started again
+	pub fn stop(&self) {
+		for finished in self.state.tracks.iter().filter_map(|track| track.finished.as_ref()) {
+			finished.store(true, Ordering::Relaxed);
+		}
+		all_at_once(&self.state.tracks, |sink| sink.stop());
+	}
+
+	fn update_mix(&self, index: usize, change: impl FnOnce(&mut TrackMix)) {
+		let track = match self.state.tracks.get(index) {
+			Some(track) => track,
+			None => return,
+		};
+		let mut mix = match self.state.mix.lock() {
+			Ok(mix) => mix,
+			Err(poisoned) => poisoned.into_inner(),
+		};
+		change(&mut mix[index]);
+		let gain = if mix[index].muted { 0.0 } else { mix[index].volume };
+		track.sink.set_volume(VOL_MULTIPLIER * track.volume * gain);
+		track.level.store(VOL_MULTIPLIER * track.volume * gain);
+	}
+}
+
+// starts the song on the configured backend and returns while it plays
+pub fn start_song(song: Song, config: &SynthConfig) -> Result<Playback, PlayError> {
+	//jack does its own mixing, the other backends go through a rodio output stream
+	#[cfg(feature = "jack")]
+	if let AudioBackend::Jack(client_name) = &config.backend {
+		return crate::jack_backend::play_song_jack(song, config, client_name);
+	}
+
+	let base_tempo = song.sections.first()
+		.and_then(|section| section.tracks.first())
+		.map(|track| track.tempo as f32)
+		.unwrap_or(0.0);
+	let prototracks = song.flatten();
+
+	//measured before the real stream is opened, some devices only take one at a
+	//time. only when asked for, it holds up the start
+	let latency = match &config.backend {
+		AudioBackend::Default if config.print_playback || config.query_latency => query_output_latency(None).ok(),
+		_ => None,
+	};
+
+	//create output stream
+	let (stream, stream_handle) = match &config.backend {
+		AudioBackend::Default => rodio::OutputStream::try_default()?,
+		#[cfg(all(windows, feature = "asio"))]
+		AudioBackend::Asio(device_name) => {
+			use cpal::traits::{DeviceTrait, HostTrait};
+			let unsupported = || PlayError::UnsupportedBackend(config.backend.clone());
+			let host = cpal::host_from_id(cpal::HostId::Asio).map_err(|_| unsupported())?;
+			let device = host.output_devices().map_err(|_| unsupported())?
+				.find(|device| device.name().map(|name| &name == device_name).unwrap_or(false))
+				.ok_or_else(|| PlayError::DeviceNotFound(device_name.clone()))?;
+			rodio::OutputStream::try_from_device(&device)?
+		},
+		backend => return Err(PlayError::UnsupportedBackend(backend.clone())),
+	};
+
+	let sinks = prototracks.iter().map(|_| Sink::try_new(&stream_handle)).collect::<Result<Vec<Sink>, rodio::PlayError>>()?;
+	let tracks = prepare_tracks(&prototracks, sinks, config);
+
+	//every track was queued paused, now they all start at once
+	all_at_once(&tracks, |sink| sink.play());
+
+	let handle = PlaybackHandle::new(tracks, base_tempo);
+	if config.print_playback {
+		if let Some(latency) = latency {
+			println!("Output latency: {:.1} ms", latency.as_secs_f32() * 1000.0);
+		}
+		handle.print_while_playing();
+	}
+
+	return Ok(Playback {
+		_output: PlaybackOutput::Rodio {
+			_stream: stream,
+		},
+		handle,
+		latency,
+	});
+}
+
+// does the same thing to every track's sink from a thread of its own, all of them
+// let go by one barrier. in a plain loop the last track would start (or stop) a
+// little after the first, more so the more tracks there are
+fn all_at_once(tracks: &[Track], action: impl Fn(&Sink) + Sync) {
+	let barrier = Barrier::new(tracks.len());
+	thread::scope(|scope| {
+		for track in tracks.iter() {
+			scope.spawn(|| {
+				barrier.wait();
+				action(&track.sink);
+			});
+		}
+	});
+}
+
+// prints a line per track every PRINT_PLAYBACK_INTERVAL until the song is over or
+// the playback is dropped
+fn print_playback(state: Weak<PlaybackState>) {
+	loop {
+		thread::sleep(PRINT_PLAYBACK_INTERVAL);
+		let state = match state.upgrade() {
+			Some(state) => state,
+			None => return,
+		};
+		if !state.tracks.iter().any(|track| track.is_playing()) {
+			return;
+		}
+		let mut table = String::new();
+		for (n, track) in state.tracks.iter().enumerate() {
+			let current = match track.note_at(track.elapsed_secs()) {
+				Some((index, remaining)) if track.notes[index].is_rest() => format!("rest ({:.2}s remaining)", remaining),
+				Some((index, remaining)) => format!("{} ({:.2}s remaining)", theory::note_name(track.notes[index].pitch), remaining),
+				None => "done".to_string(),
+			};
+			table.push_str(&format!("Track {} [{}]: {}\n", n, track.instrument.name(), current));
+		}
+		println!("{}", table);
+	}
+}
+
+// plays the song on the configured backend and returns once it has finished
+pub fn play_song(song: Song, config: &SynthConfig) -> Result<(), PlayError> {
+	let playback = start_song(song, config)?;
+	playback.wait();
+
+	return Ok(());
+}
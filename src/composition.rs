@@ -0,0 +1,902 @@
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+
+use crate::composer::{Instruments, Note, ProtoTrack};
+use crate::theory::{self, Chord, ChordQuality, Direction, Interval, Mode, RomanNumeral, Scale, ScaleType, VoicingStyle};
+
+// every voice plays the same melody, each one entering offset_beats after the
+// previous one. voices take their instrument from the slice in order, wrapping
+// around if there are more voices than instruments
+pub fn make_canon(melody: &[Note], voice_count: u8, offset_beats: f32, tempo: u32, instruments: &[Instruments]) -> Vec<ProtoTrack> {
+	let mut voices: Vec<ProtoTrack> = Vec::with_capacity(voice_count as usize);
+	if instruments.is_empty() {
+		return voices;
+	}
+
+	for voice_index in 0..voice_count as usize {
+		let mut voice = ProtoTrack::new(instruments[voice_index % instruments.len()].clone());
+		voice.tempo = tempo;
+		if voice_index > 0 {
+			voice.notes.push(Note::rest(offset_beats * voice_index as f32));
+		}
+		voice.notes.extend_from_slice(melody);
+		voices.push(voice);
+	}
+
+	return voices;
+}
+
+// tempo given to generated tracks, change it on the returned tracks as needed
+const DEFAULT_TEMPO: u32 = 120;
+
+// three voices for a progression: the bass plays roots an octave below the key,
+// the middle voice plays thirds and the top voice plays fifths, or sevenths on
+// dominant seventh chords. duration_per_chord is in beats
+pub fn chord_progression(numerals: &[RomanNumeral], key: f32, mode: Mode, duration_per_chord: f32) -> Vec<ProtoTrack> {
+	let mut bass = ProtoTrack::new(Instruments::Saw);
+	let mut mid = ProtoTrack::new(Instruments::Triangle);
+	let mut melody = ProtoTrack::new(Instruments::Sine);
+
+	for numeral in numerals.iter() {
+		let root = numeral.root(key, mode);
+		let intervals = numeral.quality().intervals();
+		let top_interval = intervals[intervals.len() - 1];
+
+		bass.notes.push(Note::new(root / 2.0, duration_per_chord));
+		mid.notes.push(Note::new(theory::transpose(root, intervals[1] as f32), duration_per_chord));
+		melody.notes.push(Note::new(theory::transpose(root, top_interval as f32), duration_per_chord));
+	}
+
+	let mut tracks = vec![bass, mid, melody];
+	for track in tracks.iter_mut() {
+		track.tempo = DEFAULT_TEMPO;
+	}
+	return tracks;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BassStyle {
+	Whole,
+	Half,
+	Walking,
+	Octave,
+}
+
+// one bar of 4/4 per chord, played on Instruments::Saw
+pub fn generate_bass_line(chords: &[Chord], style: BassStyle, tempo: u32) -> ProtoTrack {
+	let mut bass = ProtoTrack::new(Instruments::Saw);
+	bass.tempo = tempo;
+
+	for (n, chord) in chords.iter().enumerate() {
+		let root = chord.root;
+		let fifth = theory::transpose(root, chord.quality.intervals()[2] as f32);
+		match style {
+			BassStyle::Whole => bass.notes.push(Note::new(root, 4.0)),
+			BassStyle::Half => {
+				bass.notes.push(Note::new(root, 2.0));
+				bass.notes.push(Note::new(fifth, 2.0));
+			},
+			BassStyle::Octave => {
+				for step in 0..8 {
+					bass.notes.push(Note::new(if step % 2 == 0 { root } else { root * 2.0 }, 0.5));
+				}
+			},
+			BassStyle::Walking => {
+				let next_root = chords.get(n + 1).map(|next| next.root).unwrap_or(root);
+				for semitones in walk(root, next_root, chord.quality) {
+					bass.notes.push(Note::new(theory::transpose(root, semitones), 1.0));
+				}
+			},
+		}
+	}
+
+	return bass;
+}
+
+// four quarter notes from this root towards the next one, in semitones above the
+// current root. the last note is a chromatic approach into the next root. when
+// the roots are too close to walk between, the chord is arpeggiated instead
+fn walk(root: f32, next_root: f32, quality: ChordQuality) -> [f32; 4] {
+	//pick the nearest octave of the next root so the line doesn't leap
+	let mut distance = (12.0 * (next_root / root).log2()).round();
+	while distance > 6.0 {
+		distance -= 12.0;
+	}
+	while distance < -6.0 {
+		distance += 12.0;
+	}
+
+	let approach = if distance >= 0.0 { distance - 1.0 } else { distance + 1.0 };
+	if distance.abs() < 3.0 {
+		let third = quality.intervals()[1] as f32;
+		return [0.0, third, 7.0, approach];
+	}
+	return [0.0, (distance / 3.0).round(), (2.0 * distance / 3.0).round(), approach];
+}
+
+// bjorklund's algorithm: spreads `onsets` hits as evenly as possible over `steps`
+pub fn euclidean_pattern(steps: u8, onsets: u8) -> Vec<bool> {
+	let onsets = onsets.min(steps);
+	if onsets == 0 {
+		return vec![false; steps as usize];
+	}
+
+	let mut groups: Vec<Vec<bool>> = (0..onsets).map(|_| vec![true]).collect();
+	let mut remainders: Vec<Vec<bool>> = (0..steps - onsets).map(|_| vec![false]).collect();
+	while remainders.len() > 1 {
+		let count = groups.len().min(remainders.len());
+		let mut merged: Vec<Vec<bool>> = Vec::with_capacity(count);
+		for n in 0..count {
+			let mut group = groups[n].clone();
+			group.extend_from_slice(&remainders[n]);
+			merged.push(group);
+		}
+		remainders = if groups.len() > count { groups.split_off(count) } else { remainders.split_off(count) };
+		groups = merged;
+	}
+
+	return groups.into_iter().chain(remainders).flatten().collect();
+}
+
+pub fn euclidean_rhythm(steps: u8, onsets: u8, note: Note, rest: Note) -> Vec<Note> {
+	return euclidean_pattern(steps, onsets).into_iter().map(|hit| if hit { note.clone() } else { rest.clone() }).collect();
+}
+
+// like euclidean_rhythm but the hits themselves get a second euclidean pass:
+// `accent_steps` of the onsets are played with accent_note, the rest with base_note
+pub fn euclidean_with_accents(steps: u8, onsets: u8, accent_steps: u8, base_note: Note, accent_note: Note, rest: Note) -> Vec<Note> {
+	let onsets = onsets.min(steps);
+	let accents = euclidean_pattern(onsets, accent_steps);
+	let mut onset_index = 0;
+
+	return euclidean_pattern(steps, onsets).into_iter().map(|hit| {
+		if !hit {
+			return rest.clone();
+		}
+		let accented = accents[onset_index];
+		onset_index += 1;
+		return if accented { accent_note.clone() } else { base_note.clone() };
+	}).collect();
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OrnamentKind {
+	//a soft grace hit offset_ms before the main hit
+	Flam,
+	//a soft hit offset_ms after the main hit, before the next beat
+	GhostNote,
+}
+
+// velocity_scale is the extra hit's velocity relative to the main note's
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ornament {
+	pub kind: OrnamentKind,
+	pub offset_ms: f32,
+	pub velocity_scale: f32,
+}
+
+impl Ornament {
+	pub fn flam(offset_ms: f32) -> Ornament {
+		return Ornament {
+			kind: OrnamentKind::Flam,
+			offset_ms,
+			velocity_scale: 0.3,
+		}
+	}
+
+	pub fn ghost_note(offset_ms: f32) -> Ornament {
+		return Ornament {
+			kind: OrnamentKind::GhostNote,
+			offset_ms,
+			velocity_scale: 0.2,
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct PercussionNote {
+	pub note: Note,
+	pub ornament: Option<Ornament>,
+}
+
+impl PercussionNote {
+	pub fn new(note: Note, ornament: Option<Ornament>) -> PercussionNote {
+		return PercussionNote {
+			note,
+			ornament,
+		}
+	}
+}
+
+// adds the note and its ornament to a drum track. tracks are a sequence, so the
+// extra hit takes its time from a neighbour and everything after stays on the grid:
+// a flam's grace note shortens the note before it, or the main hit itself if
+// there's nothing before it long enough, and a ghost note splits the main note
+// in two. an offset that doesn't fit leaves the ornament out
+pub fn append_percussion_note(notes: &mut Vec<Note>, percussion_note: PercussionNote, tempo: u32) {
+	let main = percussion_note.note;
+	let ornament = match percussion_note.ornament {
+		Some(ornament) if !main.is_rest() && tempo > 0 => ornament,
+		_ => {
+			notes.push(main);
+			return;
+		},
+	};
+	let offset = ornament.offset_ms / 1000.0 * tempo as f32 / 60.0;
+	let mut extra = main.clone();
+	extra.velocity = main.velocity * ornament.velocity_scale;
+	extra.duration = offset;
+
+	match ornament.kind {
+		OrnamentKind::Flam => {
+			if let Some(previous) = notes.last_mut().filter(|previous| previous.duration > offset) {
+				previous.duration -= offset;
+				notes.push(extra);
+				notes.push(main);
+			} else if main.duration > offset {
+				notes.push(extra);
+				notes.push(Note { duration: main.duration - offset, ..main });
+			} else {
+				notes.push(main);
+			}
+		},
+		OrnamentKind::GhostNote => {
+			if main.duration > offset {
+				extra.duration = main.duration - offset;
+				notes.push(Note { duration: offset, ..main });
+				notes.push(extra);
+			} else {
+				notes.push(main);
+			}
+		},
+	}
+}
+
+// a whole drum pattern with its ornaments expanded into plain notes
+pub fn percussion_pattern(percussion_notes: &[PercussionNote], tempo: u32) -> Vec<Note> {
+	let mut notes: Vec<Note> = Vec::with_capacity(percussion_notes.len() * 2);
+	for percussion_note in percussion_notes.iter() {
+		append_percussion_note(&mut notes, percussion_note.clone(), tempo);
+	}
+	return notes;
+}
+
+// a short phrase of notes meant to be looped
+#[derive(Clone)]
+pub struct Pattern {
+	pub notes: Vec<Note>,
+}
+
+impl Pattern {
+	pub fn new(notes: Vec<Note>) -> Pattern {
+		return Pattern {
+			notes,
+		}
+	}
+
+	pub fn repeat(&self, n: u32) -> Vec<Note> {
+		return self.repeat_with_variation(n, |_, _| {});
+	}
+
+	// every repeat starts as a fresh copy of the pattern and is handed to `variation`
+	// with its zero based index before being added, e.g. to put a fill on every 4th bar
+	pub fn repeat_with_variation(&self, n: u32, variation: impl Fn(u32, &mut Vec<Note>)) -> Vec<Note> {
+		let mut notes: Vec<Note> = Vec::with_capacity(self.notes.len() * n as usize);
+		for repeat in 0..n {
+			let mut copy = self.notes.clone();
+			variation(repeat, &mut copy);
+			notes.append(&mut copy);
+		}
+		return notes;
+	}
+}
+
+// one way of changing a track's notes for a variation. mutations only ever change
+// notes in place and never add or remove any, so events kept by note index still
+// land on the same notes
+pub trait Mutation {
+	fn apply(&self, notes: &mut Vec<Note>, rng: &mut dyn RngCore);
+}
+
+// moves notes up or down by `semitones`, each with the given chance
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PitchTranspose {
+	pub semitones: i32,
+	pub probability: f32,
+}
+
+impl Mutation for PitchTranspose {
+	fn apply(&self, notes: &mut Vec<Note>, rng: &mut dyn RngCore) {
+		let ratio = 2.0_f32.powf(self.semitones as f32 / 12.0);
+		for note in notes.iter_mut().filter(|note| !note.is_rest()) {
+			if rng.gen::<f32>() < self.probability {
+				note.pitch *= ratio;
+			}
+		}
+	}
+}
+
+// turns notes into rests of the same length, each with the given chance
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NoteDropper {
+	pub probability: f32,
+}
+
+impl Mutation for NoteDropper {
+	fn apply(&self, notes: &mut Vec<Note>, rng: &mut dyn RngCore) {
+		for note in notes.iter_mut().filter(|note| !note.is_rest()) {
+			if rng.gen::<f32>() < self.probability {
+				*note = Note::rest(note.duration);
+			}
+		}
+	}
+}
+
+// pushes every note's start up to max_beats earlier or later. the time comes out
+// of the note before it, so the phrase keeps its length and nothing overlaps
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RhythmicDisplacer {
+	pub max_beats: f32,
+}
+
+//shortest a displaced note can get, in beats
+const MIN_DISPLACED_DURATION: f32 = 0.01;
+
+impl Mutation for RhythmicDisplacer {
+	fn apply(&self, notes: &mut Vec<Note>, rng: &mut dyn RngCore) {
+		if self.max_beats <= 0.0 {
+			return;
+		}
+		for n in 1..notes.len() {
+			let earliest = -(self.max_beats.min(notes[n - 1].duration - MIN_DISPLACED_DURATION)).max(0.0);
+			let latest = self.max_beats.min(notes[n].duration - MIN_DISPLACED_DURATION).max(0.0);
+			if latest - earliest <= 0.0 {
+				continue;
+			}
+			let shift = rng.gen_range(earliest, latest);
+			notes[n - 1].duration += shift;
+			notes[n].duration -= shift;
+		}
+	}
+}
+
+// moves notes an octave up or down, each with the given chance
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OctaveFlip {
+	pub probability: f32,
+}
+
+impl Mutation for OctaveFlip {
+	fn apply(&self, notes: &mut Vec<Note>, rng: &mut dyn RngCore) {
+		for note in notes.iter_mut().filter(|note| !note.is_rest()) {
+			if rng.gen::<f32>() < self.probability {
+				note.pitch *= if rng.gen::<bool>() { 2.0 } else { 0.5 };
+			}
+		}
+	}
+}
+
+// makes a new take on a track every time it's asked, by running the original's
+// notes through the mutations in order. each take starts from the original, so
+// the changes don't pile up
+pub struct VariationEngine {
+	pub track: ProtoTrack,
+	pub mutations: Vec<Box<dyn Mutation>>,
+	//set by seed(), used instead of the caller's rng so the takes come out the same
+	//every run
+	rng: Option<StdRng>,
+}
+
+impl VariationEngine {
+	pub fn new(track: ProtoTrack) -> VariationEngine {
+		return VariationEngine {
+			track,
+			mutations: Vec::new(),
+			rng: None,
+		}
+	}
+
+	pub fn add_mutation(&mut self, mutation: impl Mutation + 'static) {
+		self.mutations.push(Box::new(mutation));
+	}
+
+	// from here on the engine draws from its own rng started at `seed` and ignores
+	// the one passed to generate, so the same seed gives the same takes
+	pub fn seed(&mut self, seed: u64) {
+		self.rng = Some(StdRng::seed_from_u64(seed));
+	}
+
+	pub fn generate(&mut self, rng: &mut impl Rng) -> ProtoTrack {
+		let rng: &mut dyn RngCore = match self.rng.as_mut() {
+			Some(seeded) => seeded,
+			None => rng,
+		};
+		let mut track = self.track.clone();
+		for mutation in self.mutations.iter() {
+			mutation.apply(&mut track.notes, rng);
+		}
+		return track;
+	}
+}
+
+// how morph_prototracks lines up the notes of the two tracks. Pointwise pairs
+// the nth note of one with the nth of the other, TimeWarped cuts both tracks at
+// every note start of either and pairs whatever sounds at the same beat
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MorphMode {
+	Pointwise,
+	TimeWarped,
+}
+
+// a track part way between a and b, t 0.0 is a copy of a and 1.0 a copy of b.
+// pitches move evenly in semitones, durations, velocities and the tempo linearly,
+// and where only one side has a rest the nearer track wins. the instrument and
+// everything else comes from a below t 0.5 and from b from there on. Pointwise
+// needs both tracks to have as many notes and falls back to TimeWarped otherwise,
+// and as TimeWarped makes new notes it drops the events kept by note index
+pub fn morph_prototracks(a: &ProtoTrack, b: &ProtoTrack, t: f32, interpolation: MorphMode) -> ProtoTrack {
+	if t <= 0.0 {
+		return a.clone();
+	}
+	if t >= 1.0 {
+		return b.clone();
+	}
+
+	let mut morphed = if t < 0.5 { a.clone() } else { b.clone() };
+	morphed.tempo = (a.tempo as f32 + (b.tempo as f32 - a.tempo as f32) * t).round() as u32;
+
+	if interpolation == MorphMode::Pointwise && a.notes.len() == b.notes.len() {
+		morphed.notes = a.notes.iter().zip(b.notes.iter()).map(|(from, to)| {
+			let mut note = morph_note(from, to, t);
+			note.duration = from.duration + (to.duration - from.duration) * t;
+			note
+		}).collect();
+		return morphed;
+	}
+
+	//every beat where a note of either track starts, and the ends of both
+	let onsets = |notes: &[Note]| -> Vec<f32> {
+		let mut beat = 0.0;
+		let mut onsets = vec![0.0];
+		for note in notes.iter() {
+			beat += note.duration;
+			onsets.push(beat);
+		}
+		return onsets;
+	};
+	let a_onsets = onsets(&a.notes);
+	let b_onsets = onsets(&b.notes);
+	let mut cuts: Vec<f32> = a_onsets.iter().chain(b_onsets.iter()).copied().collect();
+	cuts.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+	cuts.dedup_by(|x, y| (*x - *y).abs() < 1e-4);
+
+	//the note sounding at a beat, a rest past the end
+	let note_at = |notes: &[Note], onsets: &[f32], beat: f32| -> Note {
+		return match onsets.windows(2).position(|span| beat >= span[0] && beat < span[1]) {
+			Some(index) => notes[index].clone(),
+			None => Note::rest(0.0),
+		};
+	};
+
+	morphed.notes = cuts.windows(2).map(|span| {
+		let from = note_at(&a.notes, &a_onsets, span[0]);
+		let to = note_at(&b.notes, &b_onsets, span[0]);
+		let mut note = morph_note(&from, &to, t);
+		note.duration = span[1] - span[0];
+		note
+	}).collect();
+	morphed.aftertouch = Vec::new();
+	morphed.breath = Vec::new();
+	return morphed;
+}
+
+// everything but the duration, which depends on how the notes were paired
+fn morph_note(from: &Note, to: &Note, t: f32) -> Note {
+	let mut note = if t < 0.5 { from.clone() } else { to.clone() };
+	if !from.is_rest() && !to.is_rest() {
+		note.pitch = from.pitch * (to.pitch / from.pitch).powf(t);
+		note.velocity = from.velocity + (to.velocity - from.velocity) * t;
+	}
+	return note;
+}
+
+// the range of frequencies, lowest and highest, that each instrument's notes
+// should sit in. instruments are told apart by name, so every Custom patch shares
+// one range
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterConfig {
+	ranges: Vec<(&'static str, (f32, f32))>,
+}
+
+impl RegisterConfig {
+	// no ranges, every track is left where it is
+	pub fn new() -> RegisterConfig {
+		return RegisterConfig {
+			ranges: Vec::new(),
+		}
+	}
+
+	pub fn set_range(&mut self, instrument: &Instruments, low: f32, high: f32) {
+		let name = instrument.name();
+		self.ranges.retain(|(other, _)| *other != name);
+		self.ranges.push((name, (low.min(high), low.max(high))));
+	}
+
+	pub fn range(&self, instrument: &Instruments) -> Option<(f32, f32)> {
+		let name = instrument.name();
+		return self.ranges.iter().find(|(other, _)| *other == name).map(|(_, range)| *range);
+	}
+}
+
+// sine from c3 to c5, saw from c2 to c4 and square from c4 to c6
+impl Default for RegisterConfig {
+	fn default() -> RegisterConfig {
+		let mut config = RegisterConfig::new();
+		config.set_range(&Instruments::Sine, theory::midi_note_to_freq(48), theory::midi_note_to_freq(72));
+		config.set_range(&Instruments::Saw, theory::midi_note_to_freq(36), theory::midi_note_to_freq(60));
+		config.set_range(&Instruments::Square, theory::midi_note_to_freq(60), theory::midi_note_to_freq(84));
+		return config;
+	}
+}
+
+// moves each track by whole octaves into its instrument's range, as few octaves
+// as it takes. a track too wide to fit is centered on its range instead, and
+// tracks without a range or without notes stay as they are
+pub fn assign_registers(prototracks: &mut [ProtoTrack], config: &RegisterConfig) {
+	for track in prototracks.iter_mut() {
+		let (low, high) = match config.range(&track.instrument) {
+			Some(range) => range,
+			None => continue,
+		};
+		let pitches = track.notes.iter().filter(|note| !note.is_rest()).map(|note| note.pitch);
+		let lowest = pitches.clone().fold(f32::INFINITY, f32::min);
+		let highest = pitches.fold(0.0, f32::max);
+		if !lowest.is_finite() {
+			continue;
+		}
+
+		//a little slack so notes right on the edge of the range count as inside
+		let fewest = ((low / lowest).log2() - 1e-3).ceil() as i32;
+		let most = ((high / highest).log2() + 1e-3).floor() as i32;
+		let octaves = if fewest <= most {
+			0.clamp(fewest, most)
+		} else {
+			((low * high).sqrt() / (lowest * highest).sqrt()).log2().round() as i32
+		};
+		track.transpose_octaves(octaves);
+	}
+}
+
+// the voicings jazz_comp picks from. Shell is the root, third and seventh only,
+// or the root, third and fifth on a triad
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CompVoicing {
+	RootPosition,
+	FirstInversion,
+	SecondInversion,
+	Drop2,
+	Shell,
+}
+
+impl CompVoicing {
+	const ALL: [CompVoicing; 5] = [
+		CompVoicing::RootPosition,
+		CompVoicing::FirstInversion,
+		CompVoicing::SecondInversion,
+		CompVoicing::Drop2,
+		CompVoicing::Shell,
+	];
+
+	// the chord's tones in this voicing, lowest first
+	pub fn voice(&self, chord: &Chord) -> Vec<f32> {
+		let mut tones = chord.frequencies();
+		let inversions = match self {
+			CompVoicing::FirstInversion => 1,
+			CompVoicing::SecondInversion => 2,
+			_ => 0,
+		};
+		for _ in 0..inversions.min(tones.len().saturating_sub(1)) {
+			let lowest = tones.remove(0);
+			tones.push(lowest * 2.0);
+		}
+		return match self {
+			CompVoicing::Drop2 => theory::voice_chord(&tones, VoicingStyle::Drop2),
+			CompVoicing::Shell if tones.len() > 3 => vec![tones[0], tones[1], tones[tones.len() - 1]],
+			_ => tones,
+		};
+	}
+}
+
+//how much less likely the voicing of the chord before is to come straight back
+const COMP_REPEAT_WEIGHT: f32 = 0.2;
+//chance of a chord on each eighth of a 4/4 bar, the backbeats are the favourites
+const COMP_HIT_CHANCES: [f32; 8] = [0.3, 0.15, 0.75, 0.15, 0.3, 0.15, 0.75, 0.15];
+//longest a comped chord rings, in beats
+const COMP_MAX_LENGTH: f32 = 1.5;
+
+// comping over a progression, one chord per 4/4 bar for `bars` bars, going round
+// the progression as often as it takes. every bar picks one voicing, rarely the
+// one the bar before used, and stabs it on a random handful of eighths that leans
+// on beats 2 and 4. every stab is one chord note on the returned track
+pub fn jazz_comp(chord_progression: &[Chord], bars: usize, tempo: u32, rng: &mut impl Rng) -> ProtoTrack {
+	let voicings: Vec<Vec<Vec<f32>>> = chord_progression.iter()
+		.map(|chord| CompVoicing::ALL.iter().map(|voicing| voicing.voice(chord)).collect())
+		.collect();
+	let mut track = ProtoTrack::new(Instruments::Triangle);
+	track.tempo = tempo;
+	if chord_progression.is_empty() {
+		return track;
+	}
+
+	let mut previous: Option<usize> = None;
+	for bar in 0..bars {
+		let weights: Vec<f32> = (0..CompVoicing::ALL.len())
+			.map(|voicing| if Some(voicing) == previous { COMP_REPEAT_WEIGHT } else { 1.0 })
+			.collect();
+		let mut pick = rng.gen::<f32>() * weights.iter().sum::<f32>();
+		let mut voicing = weights.len() - 1;
+		for (n, weight) in weights.iter().enumerate() {
+			if pick < *weight {
+				voicing = n;
+				break;
+			}
+			pick -= weight;
+		}
+		previous = Some(voicing);
+		let tones = &voicings[bar % chord_progression.len()][voicing];
+
+		let mut hits: Vec<usize> = (0..COMP_HIT_CHANCES.len()).filter(|slot| rng.gen::<f32>() < COMP_HIT_CHANCES[*slot]).collect();
+		if hits.is_empty() {
+			hits.push(2);
+		}
+
+		//(sounding, length in beats) of every chord in the bar and the rests between
+		let mut beat = 0.0;
+		let mut events: Vec<(bool, f32)> = Vec::new();
+		for (n, slot) in hits.iter().enumerate() {
+			let start = *slot as f32 * 0.5;
+			let next = hits.get(n + 1).map_or(4.0, |next| *next as f32 * 0.5);
+			if start > beat {
+				events.push((false, start - beat));
+			}
+			let length = (next - start).min(COMP_MAX_LENGTH);
+			events.push((true, length));
+			beat = start + length;
+		}
+		if beat < 4.0 {
+			events.push((false, 4.0 - beat));
+		}
+
+		for (sounding, length) in events.iter() {
+			if *sounding {
+				track.notes.push(Note::chord(tones, *length));
+			} else {
+				track.notes.push(Note::rest(*length));
+			}
+		}
+	}
+	return track;
+}
+
+// a shift register of random bits that loops, after tom whitwell's eurorack
+// module. every tick the bit falling off the end of the loop is fed back in at the
+// start, flipped with the given probability: 0.0 locks the loop and repeats it
+// forever, 0.5 makes up a new pattern every pass. length is the loop length in
+// bits from 1 to 16 and the output is the newest 8 bits
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TuringMachine {
+	pub bits: u16,
+	pub length: u8,
+	pub probability: f32,
+}
+
+impl TuringMachine {
+	pub fn new(bits: u16, length: u8, probability: f32) -> TuringMachine {
+		return TuringMachine {
+			bits,
+			length,
+			probability,
+		}
+	}
+
+	// starts from a register of random bits
+	pub fn random(length: u8, probability: f32, rng: &mut impl Rng) -> TuringMachine {
+		return TuringMachine::new(rng.gen(), length, probability);
+	}
+
+	pub fn tick(&mut self, rng: &mut impl Rng) -> u8 {
+		let length = self.length.clamp(1, 16);
+		let mut bit = (self.bits >> (length - 1)) & 1;
+		if rng.gen::<f32>() < self.probability {
+			bit ^= 1;
+		}
+		self.bits = (self.bits << 1) | bit;
+		return self.output();
+	}
+
+	pub fn output(&self) -> u8 {
+		return (self.bits & 0xff) as u8;
+	}
+}
+
+// a melody from a turing machine: each tick's output picks one of `range` scale
+// degrees counting up from the tonic, so lower outputs play lower notes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TuringMachineTrack {
+	pub machine: TuringMachine,
+	pub scale: Scale,
+	pub range: u8,
+	//beats
+	pub note_duration: f32,
+}
+
+impl TuringMachineTrack {
+	pub fn new(machine: TuringMachine, scale: Scale, range: u8, note_duration: f32) -> TuringMachineTrack {
+		return TuringMachineTrack {
+			machine,
+			scale,
+			range,
+			note_duration,
+		}
+	}
+
+	// the next `length` notes, the machine carries on from there on the next call
+	pub fn generate(&mut self, length: usize, rng: &mut impl Rng) -> Vec<Note> {
+		let range = self.range.max(1) as u32;
+		return (0..length).map(|_| {
+			let degree = 1 + self.machine.tick(rng) as u32 * range / 256;
+			Note::new(self.scale.degree_frequency(degree as i32), self.note_duration)
+		}).collect();
+	}
+}
+
+// the first note is start_freq and every interval moves from the previous note,
+// so a melody of n intervals has n + 1 notes. duration is in beats
+pub fn melody_from_intervals(start_freq: f32, intervals: &[(Interval, Direction)], duration: f32) -> Vec<Note> {
+	let mut notes: Vec<Note> = Vec::with_capacity(intervals.len() + 1);
+	let mut frequency = start_freq;
+	notes.push(Note::new(frequency, duration));
+	for (interval, direction) in intervals.iter() {
+		let semitones = interval.semitones() as f32;
+		frequency = match direction {
+			Direction::Up => theory::transpose(frequency, semitones),
+			Direction::Down => theory::transpose(frequency, -semitones),
+		};
+		notes.push(Note::new(frequency, duration));
+	}
+	return notes;
+}
+
+// one note per scale degree, see Scale::degree_frequency for how degrees count
+pub fn melody_from_scale_degrees(scale: &Scale, degrees: &[i32], duration_per_note: f32) -> Vec<Note> {
+	return degrees.iter().map(|degree| Note::new(scale.degree_frequency(*degree), duration_per_note)).collect();
+}
+
+//how far a note can be from a scale note and still count as in key
+const KEY_TOLERANCE_CENTS: f32 = 10.0;
+
+#[derive(Clone)]
+pub struct OutOfKeyNote {
+	pub index: usize,
+	pub note: Note,
+	pub nearest_in_key: f32,
+}
+
+// every note that isn't within KEY_TOLERANCE_CENTS of a scale note in any octave,
+// together with the scale note it's closest to. rests are never out of key
+pub fn validate_key(notes: &[Note], key: f32, scale_type: ScaleType) -> Vec<OutOfKeyNote> {
+	let intervals = scale_type.intervals();
+	let mut out_of_key: Vec<OutOfKeyNote> = Vec::new();
+
+	for (index, note) in notes.iter().enumerate().filter(|(_, note)| !note.is_rest()) {
+		let pitch_class = (12.0 * (note.pitch / key).log2()).rem_euclid(12.0);
+		//signed distance in semitones to the closest scale note, wrapping around the octave
+		let offset = intervals.iter()
+			.map(|interval| (*interval as f32 - pitch_class + 6.0).rem_euclid(12.0) - 6.0)
+			.fold(f32::MAX, |closest, offset| if offset.abs() < closest.abs() { offset } else { closest });
+		if offset.abs() * 100.0 > KEY_TOLERANCE_CENTS {
+			out_of_key.push(OutOfKeyNote {
+				index,
+				note: note.clone(),
+				nearest_in_key: theory::transpose(note.pitch, offset),
+			});
+		}
+	}
+
+	return out_of_key;
+}
+
+// snaps every note validate_key would report onto its nearest scale note
+pub fn fix_out_of_key(notes: &mut [Note], key: f32, scale_type: ScaleType) {
+	for out_of_key in validate_key(notes, key, scale_type) {
+		notes[out_of_key.index].pitch = out_of_key.nearest_in_key;
+	}
+}
+
+// how far below the melody the countermelody moves, in semitones
+const COUNTER_MIN_DISTANCE: f32 = 3.0;
+const COUNTER_MAX_DISTANCE: f32 = 19.0;
+
+// a note against note (first species) line under the melody, made of tones of the
+// scale's mode starting from `key`. every candidate is scored on the usual rules:
+// consonant intervals, thirds and sixths over fifths and octaves and more strictly
+// on downbeats, no parallel fifths or octaves, contrary motion, small steps and
+// starting and ending on the tonic. a little randomness picks between equally
+// good notes. rests in the melody stay rests
+pub fn generate_countermelody(melody: &[Note], key: f32, scale: &Scale, rng: &mut impl Rng) -> Vec<Note> {
+	let scale = Scale::new(key, scale.mode);
+	let semitones = |from: f32, to: f32| 12.0 * (to / from).log2();
+	let last_index = melody.iter().rposition(|note| !note.is_rest());
+
+	let mut counter: Vec<Note> = Vec::with_capacity(melody.len());
+	//the previous sounding pair, melody first
+	let mut previous: Option<(f32, f32)> = None;
+	let mut beat = 0.0;
+	for (index, note) in melody.iter().enumerate() {
+		let onset = beat;
+		beat += note.duration;
+		if note.is_rest() {
+			counter.push(note.clone());
+			continue;
+		}
+
+		let downbeat = onset.fract() < 0.01 || onset.fract() > 0.99;
+		let edge = previous.is_none() || Some(index) == last_index;
+		//scale tones from three octaves under the melody's octave up to it
+		let octave = (note.pitch / key).log2().floor() as i32;
+		let candidates = (7 * (octave - 3)..=7 * (octave + 1))
+			.map(|step| scale.degree_frequency(if step >= 0 { step + 1 } else { step }))
+			.filter(|candidate| (COUNTER_MIN_DISTANCE - 0.5..=COUNTER_MAX_DISTANCE + 0.5).contains(&semitones(*candidate, note.pitch)));
+
+		let mut best: Option<(f32, f32)> = None;
+		for candidate in candidates {
+			let interval = semitones(candidate, note.pitch).round().rem_euclid(12.0) as u8;
+			let perfect = interval == 0 || interval == 7;
+			let mut score = match interval {
+				3 | 4 | 8 | 9 => if edge { 1.0 } else { 3.0 },
+				0 | 7 => if edge { 4.0 } else { 1.0 },
+				_ => if downbeat { -20.0 } else { -8.0 },
+			};
+			//the line opens and closes on the tonic when it can
+			if edge && semitones(key, candidate).round().rem_euclid(12.0) == 0.0 {
+				score += 3.0;
+			}
+
+			if let Some((previous_melody, previous_counter)) = previous {
+				let melody_motion = semitones(previous_melody, note.pitch);
+				let counter_motion = semitones(previous_counter, candidate);
+				let previous_interval = semitones(previous_counter, previous_melody).round().rem_euclid(12.0) as u8;
+				if perfect && previous_interval == interval && melody_motion.abs() > 0.5 && counter_motion.abs() > 0.5 {
+					score -= 20.0;
+				}
+
+				if melody_motion.abs() > 0.5 && counter_motion.abs() > 0.5 {
+					score += if melody_motion.signum() != counter_motion.signum() { 2.0 } else { -1.0 };
+				}
+
+				score += match counter_motion.abs().round() as u32 {
+					0 => 0.5,
+					1..=2 => 2.0,
+					3..=4 => 1.0,
+					5..=7 => 0.0,
+					8..=12 => -2.0,
+					_ => -5.0,
+				};
+			}
+
+			score += rng.gen::<f32>() * 0.5;
+			if best.map_or(true, |(_, best_score)| score > best_score) {
+				best = Some((candidate, score));
+			}
+		}
+
+		//there's always a scale tone in range, this is only for a key that isn't a frequency
+		let pitch = best.map(|(candidate, _)| candidate).unwrap_or(note.pitch / 2.0);
+		let mut counter_note = note.clone();
+		counter_note.pitch = pitch;
+		counter_note.vibrato = None;
+		counter.push(counter_note);
+		previous = Some((note.pitch, pitch));
+	}
+
+	return counter;
+}
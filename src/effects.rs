@@ -0,0 +1,2877 @@
+use core::time::Duration;
+use std::fmt;
+use std::sync::Arc;
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rodio::source::Source;
+
+use crate::fft::{self, Complex, FftPlan};
+use crate::composer::interpolate;
+use crate::modulation::{Lfo, LfoShape};
+use crate::theory::{Direction, Interval, Scale};
+
+// a fixed length ring buffer, reading gives back what was written `len` samples ago
+#[derive(Clone)]
+pub(crate) struct DelayLine {
+	buffer: Vec<f32>,
+	position: usize,
+}
+
+impl DelayLine {
+	pub(crate) fn new(len: usize) -> DelayLine {
+		return DelayLine {
+			buffer: vec![0.0; len.max(1)],
+			position: 0,
+		}
+	}
+
+	pub(crate) fn read(&self) -> f32 {
+		return self.buffer[self.position];
+	}
+
+	pub(crate) fn write(&mut self, sample: f32) {
+		self.buffer[self.position] = sample;
+		self.position = (self.position + 1) % self.buffer.len();
+	}
+}
+
+// echoes bounce between the left and the right channel: the first repeat is heard
+// on the left, the second on the right and so on. the input is mixed down to mono
+// and the output is always stereo
+pub struct PingPongDelay<S: Source<Item = f32>> {
+	source: S,
+	left: DelayLine,
+	right: DelayLine,
+	feedback: f32,
+	mix: f32,
+	pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> PingPongDelay<S> {
+	// delay_time is the time between two echoes, so one full left-right round takes twice that
+	pub fn new(source: S, delay_time: Duration, feedback: f32, mix: f32) -> PingPongDelay<S> {
+		let delay_samples = (delay_time.as_secs_f32() * source.sample_rate() as f32) as usize;
+		return PingPongDelay {
+			source,
+			left: DelayLine::new(delay_samples),
+			right: DelayLine::new(delay_samples),
+			feedback,
+			mix,
+			pending_right: None,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for PingPongDelay<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len().map(|len| len / self.source.channels() as usize * 2);
+	}
+
+	fn channels(&self) -> u16 {
+		return 2;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for PingPongDelay<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let channels = self.source.channels();
+		let mut input = 0.0;
+		for _ in 0..channels {
+			input += self.source.next()?;
+		}
+		input /= channels as f32;
+
+		let left_echo = self.left.read();
+		let right_echo = self.right.read();
+		//cross feedback: whatever leaves one line goes into the other
+		self.left.write(input + self.feedback * right_echo);
+		self.right.write(self.feedback * left_echo);
+
+		let dry = input * (1.0 - self.mix);
+		self.pending_right = Some(dry + right_echo * self.mix);
+		return Some(dry + left_echo * self.mix);
+	}
+}
+
+//corner of the low pass on the crossfed signal, the head shadows the highs
+const CROSSFEED_CUTOFF_HZ: f32 = 700.0;
+
+// for headphones: each channel gets a delayed, darkened bit of the other one, the
+// way each ear hears both speakers in a room, so the stereo image moves out of the
+// head. level 0.0 is bypass, 0.7 with 17 samples of delay at 44100 hz is close to
+// the classic meier setting. the output is scaled so centered sounds keep their
+// level. anything but a stereo source passes through untouched
+pub struct Crossfeed<S: Source<Item = f32>> {
+	source: S,
+	level: f32,
+	left: DelayLine,
+	right: DelayLine,
+	//one pole low pass state of what crosses over, per side
+	left_lowpass: f32,
+	right_lowpass: f32,
+	lowpass_coefficient: f32,
+	pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> Crossfeed<S> {
+	pub fn new(source: S, level: f32, delay_samples: usize) -> Crossfeed<S> {
+		let lowpass_coefficient = (-2.0 * std::f32::consts::PI * CROSSFEED_CUTOFF_HZ / source.sample_rate() as f32).exp();
+		return Crossfeed {
+			source,
+			level: level.clamp(0.0, 1.0),
+			left: DelayLine::new(delay_samples),
+			right: DelayLine::new(delay_samples),
+			left_lowpass: 0.0,
+			right_lowpass: 0.0,
+			lowpass_coefficient,
+			pending_right: None,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Crossfeed<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Crossfeed<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.source.channels() != 2 || self.level == 0.0 {
+			return self.source.next();
+		}
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let left = self.source.next()?;
+		let right = self.source.next().unwrap_or(0.0);
+
+		self.left_lowpass = left + self.lowpass_coefficient * (self.left_lowpass - left);
+		self.right_lowpass = right + self.lowpass_coefficient * (self.right_lowpass - right);
+		let from_left = self.left.read();
+		let from_right = self.right.read();
+		self.left.write(self.left_lowpass);
+		self.right.write(self.right_lowpass);
+
+		let gain = 1.0 / (1.0 + self.level);
+		self.pending_right = Some((right + self.level * from_left) * gain);
+		return Some((left + self.level * from_right) * gain);
+	}
+}
+
+// the missing counterpart of rodio's FadeIn: the gain ramps linearly from 1.0 down
+// to 0.0 over the last fade_out of the source. the source's own total_duration is
+// used when it has one, otherwise the caller has to say how long it is with `total`
+pub struct FadeOut<S: Source<Item = f32>> {
+	source: S,
+	//samples (not frames) in the whole source and in the ramp at its end
+	total_samples: u64,
+	fade_samples: u64,
+	position: u64,
+}
+
+impl<S: Source<Item = f32>> FadeOut<S> {
+	pub fn new(source: S, fade_out: Duration, total: Duration) -> FadeOut<S> {
+		let samples_per_sec = source.sample_rate() as f32 * source.channels() as f32;
+		let total = source.total_duration().unwrap_or(total);
+		return FadeOut {
+			total_samples: (total.as_secs_f32() * samples_per_sec) as u64,
+			fade_samples: (fade_out.as_secs_f32() * samples_per_sec) as u64,
+			position: 0,
+			source,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for FadeOut<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for FadeOut<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let remaining = self.total_samples.saturating_sub(self.position);
+		self.position += 1;
+		if remaining >= self.fade_samples {
+			return Some(sample);
+		}
+		return Some(sample * remaining as f32 / self.fade_samples as f32);
+	}
+}
+
+// pan goes from -1.0 (left) to 1.0 (right)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DelayTap {
+	pub time: Duration,
+	pub level: f32,
+	pub pan: f32,
+}
+
+// equal power panning, returns the (left, right) gains for a pan position
+pub(crate) fn pan_gains(pan: f32) -> (f32, f32) {
+	let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::PI / 4.0;
+	return (angle.cos(), angle.sin());
+}
+
+// one long ring buffer with a single write position, every tap reads it back
+// at its own offset. the buffer is only as long as the longest tap needs
+pub struct MultiTapDelay<S: Source<Item = f32>> {
+	source: S,
+	buffer: Vec<f32>,
+	write_position: usize,
+	taps: Vec<(usize, f32, (f32, f32))>,
+	dry_level: f32,
+	pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> MultiTapDelay<S> {
+	pub fn new(source: S, taps: Vec<DelayTap>, dry_level: f32) -> MultiTapDelay<S> {
+		let sample_rate = source.sample_rate() as f32;
+		let taps: Vec<(usize, f32, (f32, f32))> = taps.iter()
+			.map(|tap| ((tap.time.as_secs_f32() * sample_rate) as usize, tap.level, pan_gains(tap.pan)))
+			.collect();
+		let longest = taps.iter().map(|tap| tap.0).max().unwrap_or(0);
+
+		return MultiTapDelay {
+			source,
+			buffer: vec![0.0; longest + 1],
+			write_position: 0,
+			taps,
+			dry_level,
+			pending_right: None,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for MultiTapDelay<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len().map(|len| len / self.source.channels() as usize * 2);
+	}
+
+	fn channels(&self) -> u16 {
+		return 2;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for MultiTapDelay<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let channels = self.source.channels();
+		let mut input = 0.0;
+		for _ in 0..channels {
+			input += self.source.next()?;
+		}
+		input /= channels as f32;
+
+		self.buffer[self.write_position] = input;
+		let len = self.buffer.len();
+		let mut left = input * self.dry_level;
+		let mut right = input * self.dry_level;
+		for (offset, level, (left_gain, right_gain)) in self.taps.iter() {
+			let echo = self.buffer[(self.write_position + len - offset) % len] * level;
+			left += echo * left_gain;
+			right += echo * right_gain;
+		}
+		self.write_position = (self.write_position + 1) % len;
+
+		self.pending_right = Some(right);
+		return Some(left);
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FreezeState {
+	Passthrough,
+	Capturing,
+	Frozen,
+}
+
+// grabs fft_size samples of the input when freeze() is called and keeps
+// resynthesizing that spectrum with drifting random phases until unfreeze().
+// frames overlap by half so the pad has no seams. the capture is hann windowed
+// only to measure its magnitudes, the output gets a window once, at synthesis.
+// each bin's phase runs on at its own frequency so neighbouring frames are mostly
+// in step and add up in amplitude, which a hann window at half overlap keeps flat.
+// the inner source keeps being consumed while frozen so it stays in time
+pub struct SpectralFreeze<S: Source<Item = f32>> {
+	source: S,
+	fft_size: usize,
+	state: FreezeState,
+	capture: Vec<f32>,
+	magnitudes: Vec<f32>,
+	phases: Vec<f32>,
+	window: Vec<f32>,
+	plan: FftPlan,
+	spectrum: Vec<Complex>,
+	output: Vec<f32>,
+	tail: Vec<f32>,
+	output_position: usize,
+	rng: StdRng,
+}
+
+impl<S: Source<Item = f32>> SpectralFreeze<S> {
+	// fft_size is rounded up to the next power of two
+	pub fn new(source: S, fft_size: usize) -> SpectralFreeze<S> {
+		let fft_size = fft_size.max(4).next_power_of_two();
+		return SpectralFreeze {
+			source,
+			fft_size,
+			state: FreezeState::Passthrough,
+			capture: Vec::with_capacity(fft_size),
+			magnitudes: vec![0.0; fft_size / 2 + 1],
+			phases: vec![0.0; fft_size / 2 + 1],
+			window: fft::hann_window(fft_size),
+			plan: FftPlan::new(fft_size),
+			spectrum: vec![Complex::default(); fft_size],
+			output: vec![0.0; fft_size / 2],
+			tail: vec![0.0; fft_size / 2],
+			output_position: fft_size / 2,
+			rng: StdRng::from_entropy(),
+		}
+	}
+
+	pub fn freeze(&mut self) {
+		if self.state == FreezeState::Passthrough {
+			self.capture.clear();
+			self.state = FreezeState::Capturing;
+		}
+	}
+
+	pub fn unfreeze(&mut self) {
+		self.state = FreezeState::Passthrough;
+	}
+
+	pub fn is_frozen(&self) -> bool {
+		return self.state == FreezeState::Frozen;
+	}
+
+	fn analyze_capture(&mut self) {
+		for (n, value) in self.spectrum.iter_mut().enumerate() {
+			*value = Complex::new(self.capture[n] * self.window[n], 0.0);
+		}
+		self.plan.fft(&mut self.spectrum);
+		for (magnitude, bin) in self.magnitudes.iter_mut().zip(self.spectrum.iter()) {
+			*magnitude = bin.norm();
+		}
+		for phase in self.phases.iter_mut() {
+			*phase = self.rng.gen::<f32>() * 2.0 * std::f32::consts::PI;
+		}
+		for value in self.tail.iter_mut() {
+			*value = 0.0;
+		}
+		self.output_position = self.output.len();
+	}
+
+	fn synthesize_frame(&mut self) {
+		let hop = self.fft_size / 2;
+		for value in self.spectrum.iter_mut() {
+			*value = Complex::default();
+		}
+		for (bin, magnitude) in self.magnitudes.iter().enumerate() {
+			//each bin moves on at its own frequency plus a little random drift
+			let expected = 2.0 * std::f32::consts::PI * bin as f32 * hop as f32 / self.fft_size as f32;
+			let drift = (self.rng.gen::<f32>() - 0.5) * std::f32::consts::FRAC_PI_2;
+			self.phases[bin] = (self.phases[bin] + expected + drift) % (2.0 * std::f32::consts::PI);
+			self.spectrum[bin] = Complex::from_polar(*magnitude, self.phases[bin]);
+			if bin > 0 && bin < hop {
+				self.spectrum[self.fft_size - bin] = self.spectrum[bin].conj();
+			}
+		}
+		self.plan.ifft(&mut self.spectrum);
+
+		for n in 0..hop {
+			self.output[n] = self.tail[n] + self.spectrum[n].re * self.window[n];
+			self.tail[n] = self.spectrum[n + hop].re * self.window[n + hop];
+		}
+		self.output_position = 0;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for SpectralFreeze<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for SpectralFreeze<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let input = self.source.next()?;
+		match self.state {
+			FreezeState::Passthrough => return Some(input),
+			FreezeState::Capturing => {
+				self.capture.push(input);
+				if self.capture.len() == self.fft_size {
+					self.analyze_capture();
+					self.state = FreezeState::Frozen;
+				}
+				return Some(input);
+			},
+			FreezeState::Frozen => {
+				if self.output_position >= self.output.len() {
+					self.synthesize_frame();
+				}
+				let sample = self.output[self.output_position];
+				self.output_position += 1;
+				return Some(sample);
+			},
+		}
+	}
+}
+
+//frame length of the noise reduction, frames overlap by half
+const NOISE_REDUCTION_FFT_SIZE: usize = 1024;
+//lowest gain a bin is pulled down to, deeper cuts leave musical noise
+const NOISE_REDUCTION_FLOOR: f32 = 0.05;
+//how much of the previous frame's gain a bin keeps, smooths the cuts over time
+const NOISE_REDUCTION_SMOOTHING: f32 = 0.6;
+
+// spectral subtraction: the first profile_duration of the source is taken to be
+// nothing but the background noise and its average spectrum is learned, after
+// that every bin is turned down by how much of it that noise accounts for.
+// strength 1.0 takes out the noise as learned, more cuts deeper. frames are
+// windowed with a square root hann on the way in and out and overlap by half, so
+// the output runs NOISE_REDUCTION_FFT_SIZE samples behind. meant for mono sources
+pub struct NoiseReduction<S: Source<Item = f32>> {
+	source: S,
+	strength: f32,
+	profile_frames: usize,
+	frames_seen: usize,
+	noise: Vec<f32>,
+	gains: Vec<f32>,
+	window: Vec<f32>,
+	//the last fft_size input samples, oldest first from input_position
+	input: Vec<f32>,
+	input_position: usize,
+	output: Vec<f32>,
+	tail: Vec<f32>,
+	output_position: usize,
+	//samples of silence still to feed in once the source ends, to flush the frames
+	flush: usize,
+	finished: bool,
+}
+
+impl<S: Source<Item = f32>> NoiseReduction<S> {
+	pub fn new(source: S, profile_duration: Duration, strength: f32) -> NoiseReduction<S> {
+		let hop = NOISE_REDUCTION_FFT_SIZE / 2;
+		let profile_samples = profile_duration.as_secs_f32() * source.sample_rate() as f32;
+		return NoiseReduction {
+			source,
+			strength: strength.max(0.0),
+			profile_frames: (profile_samples / hop as f32).ceil() as usize,
+			frames_seen: 0,
+			noise: vec![0.0; hop + 1],
+			gains: vec![1.0; hop + 1],
+			window: fft::hann_window(NOISE_REDUCTION_FFT_SIZE).iter().map(|w| w.sqrt()).collect(),
+			input: vec![0.0; NOISE_REDUCTION_FFT_SIZE],
+			input_position: 0,
+			output: vec![0.0; hop],
+			tail: vec![0.0; hop],
+			output_position: 0,
+			flush: NOISE_REDUCTION_FFT_SIZE,
+			finished: false,
+		}
+	}
+
+	fn process_frame(&mut self) {
+		let size = NOISE_REDUCTION_FFT_SIZE;
+		let hop = size / 2;
+		let frame: Vec<f32> = (0..size)
+			.map(|n| self.input[(self.input_position + n) % size] * self.window[n])
+			.collect();
+		let mut spectrum = fft::real_fft(&frame, size);
+
+		if self.frames_seen < self.profile_frames {
+			//running mean of the noise, the frames are passed through as they are
+			let count = self.frames_seen as f32;
+			for (noise, bin) in self.noise.iter_mut().zip(spectrum.iter()) {
+				*noise = (*noise * count + bin.norm()) / (count + 1.0);
+			}
+		} else {
+			for bin in 0..=hop {
+				let magnitude = spectrum[bin].norm();
+				let target = if magnitude > 0.0 {
+					((magnitude - self.strength * self.noise[bin]) / magnitude).max(NOISE_REDUCTION_FLOOR)
+				} else {
+					NOISE_REDUCTION_FLOOR
+				};
+				self.gains[bin] = NOISE_REDUCTION_SMOOTHING * self.gains[bin] + (1.0 - NOISE_REDUCTION_SMOOTHING) * target;
+				let gain = self.gains[bin];
+				spectrum[bin].re *= gain;
+				spectrum[bin].im *= gain;
+				if bin > 0 && bin < hop {
+					spectrum[size - bin] = spectrum[bin].conj();
+				}
+			}
+		}
+		self.frames_seen += 1;
+		fft::ifft(&mut spectrum);
+
+		for n in 0..hop {
+			self.output[n] = self.tail[n] + spectrum[n].re * self.window[n];
+			self.tail[n] = spectrum[n + hop].re * self.window[n + hop];
+		}
+		self.output_position = 0;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for NoiseReduction<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		let latency = NOISE_REDUCTION_FFT_SIZE as f32 / self.source.sample_rate() as f32;
+		return self.source.total_duration().map(|duration| duration + Duration::from_secs_f32(latency));
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for NoiseReduction<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let next = if self.finished { None } else { self.source.next() };
+		let input = match next {
+			Some(sample) => sample,
+			None => {
+				self.finished = true;
+				if self.flush == 0 {
+					return None;
+				}
+				self.flush -= 1;
+				0.0
+			},
+		};
+
+		self.input[self.input_position] = input;
+		self.input_position = (self.input_position + 1) % self.input.len();
+
+		let sample = self.output[self.output_position];
+		self.output_position += 1;
+		if self.output_position >= self.output.len() {
+			self.process_frame();
+		}
+		return Some(sample);
+	}
+}
+
+pub(crate) fn db_to_gain(db: f32) -> f32 {
+	return 10.0_f32.powf(db / 20.0);
+}
+
+pub(crate) fn gain_to_db(gain: f32) -> f32 {
+	return 20.0 * gain.max(1e-9).log10();
+}
+
+// one pole smoothing coefficient for a time constant given in seconds
+pub(crate) fn time_coefficient(seconds: f32, sample_rate: u32) -> f32 {
+	return (-1.0 / (seconds.max(1e-6) * sample_rate as f32)).exp();
+}
+
+// follows the signal with a fast and a slow envelope: while the fast one is above
+// the slow one we're in the attack of a note and attack_gain_db is used, otherwise
+// sustain_gain_db. the gain is blended in decibels so the handover is smooth
+pub struct TransientShaper<S: Source<Item = f32>> {
+	source: S,
+	attack_gain_db: f32,
+	sustain_gain_db: f32,
+	fast_envelope: f32,
+	slow_envelope: f32,
+	transient_amount: f32,
+	fast_coefficient: f32,
+	slow_coefficient: f32,
+	smoothing_coefficient: f32,
+}
+
+impl<S: Source<Item = f32>> TransientShaper<S> {
+	pub fn new(source: S, attack_gain_db: f32, sustain_gain_db: f32) -> TransientShaper<S> {
+		let sample_rate = source.sample_rate();
+		return TransientShaper {
+			source,
+			attack_gain_db,
+			sustain_gain_db,
+			fast_envelope: 0.0,
+			slow_envelope: 0.0,
+			transient_amount: 0.0,
+			fast_coefficient: time_coefficient(0.001, sample_rate),
+			slow_coefficient: time_coefficient(0.03, sample_rate),
+			smoothing_coefficient: time_coefficient(0.005, sample_rate),
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for TransientShaper<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for TransientShaper<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let level = sample.abs();
+		self.fast_envelope = self.fast_coefficient * self.fast_envelope + (1.0 - self.fast_coefficient) * level;
+		self.slow_envelope = self.slow_coefficient * self.slow_envelope + (1.0 - self.slow_coefficient) * level;
+
+		let target = if self.fast_envelope > self.slow_envelope {
+			((self.fast_envelope - self.slow_envelope) / self.fast_envelope).min(1.0)
+		} else {
+			0.0
+		};
+		self.transient_amount = self.smoothing_coefficient * self.transient_amount + (1.0 - self.smoothing_coefficient) * target;
+
+		let gain_db = self.attack_gain_db * self.transient_amount + self.sustain_gain_db * (1.0 - self.transient_amount);
+		return Some(sample * db_to_gain(gain_db));
+	}
+}
+
+// catmull-rom interpolation between p1 and p2, t goes from 0.0 to 1.0
+fn interpolate_cubic(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+	return 0.5 * (2.0 * p1
+		+ (p2 - p0) * t
+		+ (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+		+ (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+}
+
+// limits the reconstructed waveform instead of just the samples: the signal is
+// upsampled 4x with a 4 tap interpolator and the highest point between samples
+// decides the gain. that needs two samples of lookahead, so the output is
+// delayed by two samples. gain drops instantly and recovers over `release`
+pub struct TruePeakLimiter<S: Source<Item = f32>> {
+	source: S,
+	threshold: f32,
+	history: Vec<[f32; 4]>,
+	previous_peak: Vec<f32>,
+	channel: usize,
+	gain: f32,
+	release_coefficient: f32,
+	primed: usize,
+	finished: usize,
+}
+
+impl<S: Source<Item = f32>> TruePeakLimiter<S> {
+	pub fn new(source: S, threshold_db: f32, release: Duration) -> TruePeakLimiter<S> {
+		let channels = source.channels() as usize;
+		let release_coefficient = time_coefficient(release.as_secs_f32(), source.sample_rate());
+		return TruePeakLimiter {
+			source,
+			threshold: db_to_gain(threshold_db),
+			history: vec![[0.0; 4]; channels],
+			previous_peak: vec![0.0; channels],
+			channel: 0,
+			gain: 1.0,
+			release_coefficient,
+			primed: 0,
+			finished: 0,
+		}
+	}
+
+	fn true_peak(&self, channel: usize) -> f32 {
+		let [p0, p1, p2, p3] = self.history[channel];
+		let mut peak = p1.abs().max(p2.abs());
+		for step in 1..4 {
+			peak = peak.max(interpolate_cubic(p0, p1, p2, p3, step as f32 / 4.0).abs());
+		}
+		return peak;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for TruePeakLimiter<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for TruePeakLimiter<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let channels = self.history.len();
+		//once the source runs out the two delayed samples per channel still have to come out
+		let input = match self.source.next() {
+			Some(sample) => sample,
+			None => {
+				if self.finished >= 2 * channels {
+					return None;
+				}
+				self.finished += 1;
+				0.0
+			},
+		};
+
+		let channel = self.channel;
+		self.channel = (self.channel + 1) % channels;
+		let history = &mut self.history[channel];
+		history.rotate_left(1);
+		history[3] = input;
+
+		if self.primed < 2 * channels {
+			self.primed += 1;
+			return self.next();
+		}
+
+		let segment_peak = self.true_peak(channel);
+		let peak = segment_peak.max(self.previous_peak[channel]);
+		self.previous_peak[channel] = segment_peak;
+
+		let target = if peak > self.threshold { self.threshold / peak } else { 1.0 };
+		if target < self.gain {
+			self.gain = target;
+		} else {
+			self.gain = self.release_coefficient * self.gain + (1.0 - self.release_coefficient) * target;
+		}
+
+		let output = self.history[channel][1] * self.gain;
+		return Some(output.clamp(-self.threshold, self.threshold));
+	}
+}
+
+// plain direct form 1 biquad, coefficients are normalized so a0 == 1
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Biquad {
+	b0: f32,
+	b1: f32,
+	b2: f32,
+	a1: f32,
+	a2: f32,
+	x1: f32,
+	x2: f32,
+	y1: f32,
+	y2: f32,
+}
+
+impl Biquad {
+	pub(crate) fn new(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Biquad {
+		return Biquad {
+			b0: b0 / a0,
+			b1: b1 / a0,
+			b2: b2 / a0,
+			a1: a1 / a0,
+			a2: a2 / a0,
+			..Default::default()
+		}
+	}
+
+	// swaps the coefficients but keeps the filter's history, so the response can be
+	// moved while the filter runs without clicks
+	pub(crate) fn set_coefficients(&mut self, b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) {
+		self.b0 = b0 / a0;
+		self.b1 = b1 / a0;
+		self.b2 = b2 / a0;
+		self.a1 = a1 / a0;
+		self.a2 = a2 / a0;
+	}
+
+	pub(crate) fn process(&mut self, x: f32) -> f32 {
+		let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+		self.x2 = self.x1;
+		self.x1 = x;
+		self.y2 = self.y1;
+		self.y1 = y;
+		return y;
+	}
+}
+
+impl AudioEffect for Biquad {
+	fn process(&mut self, sample: f32) -> f32 {
+		return Biquad::process(self, sample);
+	}
+}
+
+// resonant 12 db/octave low pass. q of 0.707 is flat, higher values add a peak
+// at the cutoff
+#[derive(Copy, Clone, Debug)]
+pub struct LowPassFilter {
+	filter: Biquad,
+	cutoff_hz: f32,
+	q: f32,
+	sample_rate: u32,
+}
+
+impl LowPassFilter {
+	pub fn new(cutoff_hz: f32, q: f32, sample_rate: u32) -> LowPassFilter {
+		let mut filter = LowPassFilter {
+			filter: Biquad::default(),
+			cutoff_hz,
+			q: q.max(0.1),
+			sample_rate,
+		};
+		filter.set_cutoff(cutoff_hz);
+		return filter;
+	}
+
+	pub fn cutoff(&self) -> f32 {
+		return self.cutoff_hz;
+	}
+
+	// recomputes the coefficients, the filter's history is kept so it can be moved
+	// while it runs
+	pub fn set_cutoff(&mut self, cutoff_hz: f32) {
+		let sample_rate = self.sample_rate as f32;
+		self.cutoff_hz = cutoff_hz.clamp(20.0, 0.45 * sample_rate);
+		//rbj low pass
+		let omega = 2.0 * std::f32::consts::PI * self.cutoff_hz / sample_rate;
+		let (sin, cos) = omega.sin_cos();
+		let alpha = sin / (2.0 * self.q);
+		self.filter.set_coefficients((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+	}
+}
+
+impl AudioEffect for LowPassFilter {
+	fn process(&mut self, sample: f32) -> f32 {
+		return self.filter.process(sample);
+	}
+
+	fn as_low_pass_mut(&mut self) -> Option<&mut LowPassFilter> {
+		return Some(self);
+	}
+}
+
+// shared readings of a Meter, clone it freely and read it from any thread.
+// the f32 values are stored as their bit patterns in atomics
+#[derive(Clone)]
+pub struct MeterHandle {
+	peak: Arc<AtomicU32>,
+	rms: Arc<AtomicU32>,
+}
+
+impl MeterHandle {
+	pub fn peak(&self) -> f32 {
+		return f32::from_bits(self.peak.load(Ordering::Relaxed));
+	}
+
+	pub fn peak_db(&self) -> f32 {
+		return gain_to_db(self.peak());
+	}
+
+	pub fn rms(&self) -> f32 {
+		return f32::from_bits(self.rms.load(Ordering::Relaxed));
+	}
+
+	pub fn rms_db(&self) -> f32 {
+		return gain_to_db(self.rms());
+	}
+
+	pub fn reset_peak(&self) {
+		self.peak.store(0.0_f32.to_bits(), Ordering::Relaxed);
+	}
+}
+
+// passes the signal through untouched while keeping track of the held peak and
+// the rms over the last `window`. grab a handle() before appending it to a sink
+pub struct Meter<S: Source<Item = f32>> {
+	source: S,
+	handle: MeterHandle,
+	squares: Vec<f32>,
+	position: usize,
+	sum_of_squares: f32,
+}
+
+impl<S: Source<Item = f32>> Meter<S> {
+	pub fn new(source: S, window: Duration) -> Meter<S> {
+		let window_samples = (window.as_secs_f32() * source.sample_rate() as f32 * source.channels() as f32) as usize;
+		return Meter {
+			source,
+			handle: MeterHandle {
+				peak: Arc::new(AtomicU32::new(0.0_f32.to_bits())),
+				rms: Arc::new(AtomicU32::new(0.0_f32.to_bits())),
+			},
+			squares: vec![0.0; window_samples.max(1)],
+			position: 0,
+			sum_of_squares: 0.0,
+		}
+	}
+
+	pub fn handle(&self) -> MeterHandle {
+		return self.handle.clone();
+	}
+
+	pub fn peak(&self) -> f32 {
+		return self.handle.peak();
+	}
+
+	pub fn rms_db(&self) -> f32 {
+		return self.handle.rms_db();
+	}
+
+	pub fn reset_peak(&self) {
+		self.handle.reset_peak();
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Meter<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Meter<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+
+		let level = sample.abs();
+		if level > self.handle.peak() {
+			self.handle.peak.store(level.to_bits(), Ordering::Relaxed);
+		}
+
+		let square = sample * sample;
+		self.sum_of_squares += square - self.squares[self.position];
+		self.squares[self.position] = square;
+		self.position = (self.position + 1) % self.squares.len();
+		let rms = (self.sum_of_squares.max(0.0) / self.squares.len() as f32).sqrt();
+		self.handle.rms.store(rms.to_bits(), Ordering::Relaxed);
+
+		return Some(sample);
+	}
+}
+
+// error feedback curves for noise shaped dither, they push the quantization noise
+// out of the 2 to 5 khz range the ear is most sensitive to and up towards nyquist.
+// the coefficients are for 44.1 khz
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoiseShapingFilter {
+	//wannamaker's 9 tap f-weighted curve
+	Fweighted,
+	//the 20 tap curve sox uses for 44.1 khz
+	Shibata,
+	//wannamaker's 3 tap curve, the mildest
+	Wannamaker3,
+}
+
+impl NoiseShapingFilter {
+	fn coefficients(&self) -> &'static [f32] {
+		return match self {
+			NoiseShapingFilter::Fweighted => &[2.412, -3.370, 3.937, -4.174, 3.353, -2.205, 1.281, -0.569, 0.0847],
+			NoiseShapingFilter::Shibata => &[
+				2.677_32, -4.830_914, 6.570_535, -7.457_295, 6.726_304, -4.848_129, 2.041_243, 0.700_623, -2.953_757, 4.080_004,
+				-4.184_511, 3.331_118, -2.117_926, 0.879_303, -0.031_759, -0.423_828, 0.478_821, -0.354_908, 0.174_968, -0.060_908,
+			],
+			NoiseShapingFilter::Wannamaker3 => &[1.623, -0.982, 0.109],
+		};
+	}
+}
+
+// what is added before quantizing. RectangularPdf is a single uniform value of one
+// lsb, Tpdf the sum of two which also keeps the noise level from following the
+// signal, and NoiseShaped is tpdf with the error filtered by the curve
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DitherMode {
+	None,
+	Tpdf,
+	RectangularPdf,
+	NoiseShaped(NoiseShapingFilter),
+}
+
+// quantizes to `bits` wide integers, the output is a whole number of lsbs so
+// multiplying by the integer full scale and rounding is exact. dither turns the
+// truncation distortion into a quiet noise floor
+pub struct Dither<S: Source<Item = f32>> {
+	source: S,
+	lsb: f32,
+	mode: DitherMode,
+	rng: StdRng,
+	//last quantization errors of each channel, newest first
+	errors: Vec<Vec<f32>>,
+	channel: usize,
+}
+
+impl<S: Source<Item = f32>> Dither<S> {
+	pub fn new(source: S, bits: u16, mode: DitherMode) -> Dither<S> {
+		let taps = match mode {
+			DitherMode::NoiseShaped(filter) => filter.coefficients().len(),
+			_ => 0,
+		};
+		let channels = source.channels() as usize;
+		return Dither {
+			source,
+			lsb: 1.0 / ((1_u64 << (bits.clamp(2, 32) - 1)) - 1) as f32,
+			mode,
+			rng: StdRng::from_entropy(),
+			errors: vec![vec![0.0; taps]; channels.max(1)],
+			channel: 0,
+		}
+	}
+
+	fn tpdf(&mut self) -> f32 {
+		return (self.rng.gen::<f32>() - 0.5) * self.lsb + (self.rng.gen::<f32>() - 0.5) * self.lsb;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Dither<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Dither<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let channel = self.channel;
+		self.channel = (channel + 1) % self.errors.len();
+
+		let (wanted, noise) = match self.mode {
+			DitherMode::None => (sample, 0.0),
+			DitherMode::Tpdf => (sample, self.tpdf()),
+			DitherMode::RectangularPdf => (sample, (self.rng.gen::<f32>() - 0.5) * self.lsb),
+			DitherMode::NoiseShaped(filter) => {
+				let feedback: f32 = filter.coefficients().iter()
+					.zip(self.errors[channel].iter())
+					.map(|(coefficient, error)| coefficient * error)
+					.sum();
+				(sample - feedback, self.tpdf())
+			},
+		};
+		let quantized = ((wanted + noise) / self.lsb).round() * self.lsb;
+		if let DitherMode::NoiseShaped(_) = self.mode {
+			let errors = &mut self.errors[channel];
+			errors.rotate_right(1);
+			errors[0] = quantized - wanted;
+		}
+		return Some(quantized.clamp(-1.0, 1.0));
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Polarity {
+	Positive,
+	Negative,
+}
+
+// y[n] = x[n] + feedback * y[n - delay_samples], the echo is flipped with negative
+// polarity. short delays ring at sample_rate / delay_samples hz: positive feedback
+// brings out all harmonics of that pitch, negative only the odd ones an octave down
+pub struct CombFilter<S: Source<Item = f32>> {
+	source: S,
+	delay_line: DelayLine,
+	feedback: f32,
+	polarity: Polarity,
+}
+
+impl<S: Source<Item = f32>> CombFilter<S> {
+	pub fn new(source: S, delay_samples: usize, feedback: f32, polarity: Polarity) -> CombFilter<S> {
+		return CombFilter {
+			source,
+			delay_line: DelayLine::new(delay_samples),
+			feedback,
+			polarity,
+		}
+	}
+
+	pub fn set_delay_samples(&mut self, delay_samples: usize) {
+		self.delay_line = DelayLine::new(delay_samples);
+	}
+
+	pub fn set_delay_hz(&mut self, freq: f32) {
+		let delay_samples = (self.source.sample_rate() as f32 / freq).round() as usize;
+		self.set_delay_samples(delay_samples);
+	}
+}
+
+impl<S: Source<Item = f32>> Source for CombFilter<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for CombFilter<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let input = self.source.next()?;
+		let sign = match self.polarity {
+			Polarity::Positive => 1.0,
+			Polarity::Negative => -1.0,
+		};
+		let output = input + sign * self.feedback * self.delay_line.read();
+		self.delay_line.write(output);
+		return Some(output);
+	}
+}
+
+// y[n] = -g * x[n] + x[n - M] + g * y[n - M]. every frequency comes out at the same
+// level but smeared in time, chain a few with unrelated delays for a diffuse tail.
+// computed in the single buffer form: v[n] = x[n] + g * v[n - M], y[n] = v[n - M] - g * v[n]
+pub struct AllPassFilter<S: Source<Item = f32>> {
+	source: S,
+	delay_line: DelayLine,
+	gain: f32,
+}
+
+impl<S: Source<Item = f32>> AllPassFilter<S> {
+	pub fn new(source: S, delay_samples: usize, gain: f32) -> AllPassFilter<S> {
+		return AllPassFilter {
+			source,
+			delay_line: DelayLine::new(delay_samples),
+			gain,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for AllPassFilter<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for AllPassFilter<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let input = self.source.next()?;
+		let delayed = self.delay_line.read();
+		let v = input + self.gain * delayed;
+		self.delay_line.write(v);
+		return Some(delayed - self.gain * v);
+	}
+}
+
+//comb and all pass lengths of the reverb at 44.1 khz, from freeverb
+const REVERB_COMB_LENGTHS: [usize; 4] = [1557, 1617, 1491, 1422];
+const REVERB_ALLPASS_LENGTHS: [usize; 2] = [556, 225];
+const REVERB_ALLPASS_GAIN: f32 = 0.5;
+
+// schroeder reverb: four damped feedback combs in parallel into two all passes.
+// decay is the time the tail takes to fall by 60 db, damping from 0.0 to 1.0 how
+// much faster the highs die away than the lows. the wet signal starts after the
+// pre delay, 0 by default. once the source ends the tail rings out. mono sources
+pub struct Reverb<S: Source<Item = f32>> {
+	source: S,
+	mix: f32,
+	damping: f32,
+	pre_delay: Option<DelayLine>,
+	//(delay line, feedback, low pass state)
+	combs: Vec<(DelayLine, f32, f32)>,
+	allpasses: Vec<DelayLine>,
+	//samples of the tail still to play after the source ends
+	tail: usize,
+	finished: bool,
+}
+
+impl<S: Source<Item = f32>> Reverb<S> {
+	pub fn new(source: S, decay: Duration, damping: f32, mix: f32) -> Reverb<S> {
+		let sample_rate = source.sample_rate() as f32;
+		let scale = sample_rate / 44_100.0;
+		let decay_samples = decay.as_secs_f32().max(1e-3) * sample_rate;
+		let combs = REVERB_COMB_LENGTHS.iter().map(|len| {
+			let len = ((*len as f32 * scale) as usize).max(1);
+			//each trip round the comb takes it len samples closer to -60 db
+			(DelayLine::new(len), 10.0_f32.powf(-3.0 * len as f32 / decay_samples), 0.0)
+		}).collect();
+		let allpasses = REVERB_ALLPASS_LENGTHS.iter()
+			.map(|len| DelayLine::new(((*len as f32 * scale) as usize).max(1)))
+			.collect();
+		return Reverb {
+			source,
+			mix: mix.clamp(0.0, 1.0),
+			damping: damping.clamp(0.0, 1.0),
+			pre_delay: None,
+			combs,
+			allpasses,
+			tail: decay_samples as usize,
+			finished: false,
+		}
+	}
+
+	// the gap between the dry sound and the start of the reverb. whatever was
+	// waiting in the old pre delay is dropped
+	pub fn set_pre_delay(&mut self, delay: Duration) {
+		let samples = (delay.as_secs_f32() * self.source.sample_rate() as f32) as usize;
+		let old = self.pre_delay.as_ref().map_or(0, |line| line.buffer.len());
+		self.tail = (self.tail + samples).saturating_sub(old);
+		self.pre_delay = if samples > 0 { Some(DelayLine::new(samples)) } else { None };
+	}
+
+	fn wet(&mut self, input: f32) -> f32 {
+		let input = match self.pre_delay.as_mut() {
+			Some(line) => {
+				let delayed = line.read();
+				line.write(input);
+				delayed
+			},
+			None => input,
+		};
+
+		let mut output = 0.0;
+		for (line, feedback, lowpass) in self.combs.iter_mut() {
+			let delayed = line.read();
+			*lowpass = delayed * (1.0 - self.damping) + *lowpass * self.damping;
+			line.write(input + *lowpass * *feedback);
+			output += delayed;
+		}
+		output /= self.combs.len() as f32;
+
+		for line in self.allpasses.iter_mut() {
+			let delayed = line.read();
+			let v = output + REVERB_ALLPASS_GAIN * delayed;
+			line.write(v);
+			output = delayed - REVERB_ALLPASS_GAIN * v;
+		}
+		return output;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Reverb<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		let tail = self.tail as f32 / self.source.sample_rate() as f32;
+		return self.source.total_duration().map(|duration| duration + Duration::from_secs_f32(tail));
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Reverb<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let next = if self.finished { None } else { self.source.next() };
+		let input = match next {
+			Some(sample) => sample,
+			None => {
+				self.finished = true;
+				if self.tail == 0 {
+					return None;
+				}
+				self.tail -= 1;
+				0.0
+			},
+		};
+		let wet = self.wet(input);
+		return Some(input * (1.0 - self.mix) + wet * self.mix);
+	}
+}
+
+// direct form fir convolution with a fixed impulse response, for mono sources
+pub struct FirFilter<S: Source<Item = f32>> {
+	source: S,
+	coefficients: Vec<f32>,
+	history: Vec<f32>,
+	position: usize,
+}
+
+impl<S: Source<Item = f32>> FirFilter<S> {
+	pub fn new(source: S, coefficients: Vec<f32>) -> FirFilter<S> {
+		let len = coefficients.len().max(1);
+		return FirFilter {
+			source,
+			coefficients,
+			history: vec![0.0; len],
+			position: 0,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for FirFilter<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for FirFilter<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let input = self.source.next()?;
+		let len = self.history.len();
+		self.history[self.position] = input;
+
+		//coefficient k meets the input from k samples ago
+		let mut output = 0.0;
+		for (k, coefficient) in self.coefficients.iter().enumerate() {
+			output += coefficient * self.history[(self.position + len - k) % len];
+		}
+		self.position = (self.position + 1) % len;
+		return Some(output);
+	}
+}
+
+// an impulse response recorded at one sample rate, resampled to the same length in
+// time at another. each new tap is the linearly interpolated response averaged
+// over the span of time it covers, scaled by the rate ratio, so the response keeps
+// its gain and going down in rate doesn't just skip the taps in between
+pub(crate) fn resample_impulse_response(response: &[f32], from: u32, to: u32) -> Vec<f32> {
+	if from == to {
+		return response.to_vec();
+	}
+	let step = from as f32 / to as f32;
+	let len = ((response.len() as f32 / step).round() as usize).max(1);
+	//points averaged per tap, at least two for every tap of the original it spans
+	let points = step.ceil() as usize * 2;
+	let interpolate = |position: f32| -> f32 {
+		let index = position as usize;
+		let current = response.get(index).copied().unwrap_or(0.0);
+		let next = response.get(index + 1).copied().unwrap_or(0.0);
+		return current + (next - current) * position.fract();
+	};
+	return (0..len).map(|n| {
+		let sum: f32 = (0..points).map(|point| {
+			return interpolate((n as f32 + (point as f32 + 0.5) / points as f32 - 0.5).max(0.0) * step);
+		}).sum();
+		return sum / points as f32 * step;
+	}).collect();
+}
+
+//the cepstrum is computed this many times finer than the filter is long so its
+//tail doesn't wrap around onto the start
+const CEPSTRUM_OVERSAMPLING: usize = 4;
+
+// fir coefficients for any magnitude response, to use with FirFilter. the response
+// is linear gain at evenly spaced frequencies from 0 hz to nyquist, so the bins
+// are sample_rate / 2 / (len - 1) hz apart and the coefficients are only right at
+// that sample rate. the filter is minimum phase: all of its delay is as early as it
+// can be, so there's no pre-ringing and no latency like a linear phase fir would
+// have. a response of n points gives 2 * (n - 1) taps
+pub fn design_fir_from_spectrum(desired_response: &[f32], _sample_rate: u32) -> Vec<f32> {
+	if desired_response.len() < 2 {
+		return vec![desired_response.first().copied().unwrap_or(1.0)];
+	}
+	let taps = 2 * (desired_response.len() - 1);
+	let size = taps.next_power_of_two() * CEPSTRUM_OVERSAMPLING;
+	let half = size / 2;
+
+	//log magnitude on the finer grid, mirrored around nyquist
+	let last = (desired_response.len() - 1) as f32;
+	let mut spectrum: Vec<Complex> = vec![Complex::default(); size];
+	for bin in 0..=half {
+		let position = bin as f32 * last / half as f32;
+		let index = (position as usize).min(desired_response.len() - 2);
+		let weight = position - index as f32;
+		let magnitude = desired_response[index] * (1.0 - weight) + desired_response[index + 1] * weight;
+		let log_magnitude = magnitude.abs().max(1e-6).ln();
+		spectrum[bin] = Complex::new(log_magnitude, 0.0);
+		if bin > 0 && bin < half {
+			spectrum[size - bin] = Complex::new(log_magnitude, 0.0);
+		}
+	}
+
+	//real cepstrum, folded so everything anticausal lands on the causal side
+	fft::ifft(&mut spectrum);
+	for value in spectrum.iter_mut().take(half).skip(1) {
+		value.re *= 2.0;
+		value.im = 0.0;
+	}
+	spectrum[0].im = 0.0;
+	spectrum[half].im = 0.0;
+	for value in spectrum.iter_mut().skip(half + 1) {
+		*value = Complex::default();
+	}
+
+	//back to a spectrum, now with the minimum phase, and out to the impulse response
+	fft::fft(&mut spectrum);
+	for value in spectrum.iter_mut() {
+		*value = Complex::from_polar(value.re.exp(), value.im);
+	}
+	fft::ifft(&mut spectrum);
+	return spectrum.iter().take(taps).map(|value| value.re).collect();
+}
+
+// the cabinets' impulse responses, each under 50 ms. see the header of the file
+// for where the responses in this build came from, and
+// examples/cabinet_tables.rs for regenerating it from measured ones
+mod cabinets;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CabinetType {
+	BritishStack4x12,
+	AmericanCombo1x12,
+	BassReflex,
+}
+
+impl CabinetType {
+	// the cabinet's impulse response, resampled when the sample rate isn't the one
+	// it was recorded at
+	pub fn impulse_response(&self, sample_rate: u32) -> Vec<f32> {
+		let (recorded_rate, response) = match self {
+			CabinetType::BritishStack4x12 => cabinets::BRITISH_STACK_4X12,
+			CabinetType::AmericanCombo1x12 => cabinets::AMERICAN_COMBO_1X12,
+			CabinetType::BassReflex => cabinets::BASS_REFLEX,
+		};
+		return resample_impulse_response(response, recorded_rate, sample_rate);
+	}
+}
+
+// runs the signal through a speaker cabinet's impulse response
+pub struct CabinetSim<S: Source<Item = f32>> {
+	filter: FirFilter<S>,
+}
+
+impl<S: Source<Item = f32>> CabinetSim<S> {
+	pub fn new(source: S, cabinet: CabinetType) -> CabinetSim<S> {
+		let response = cabinet.impulse_response(source.sample_rate());
+		return CabinetSim {
+			filter: FirFilter::new(source, response),
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for CabinetSim<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.filter.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.filter.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.filter.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.filter.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for CabinetSim<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return self.filter.next();
+	}
+}
+
+// swings a sound between the speakers. the pan position follows an internal lfo
+// and is turned into channel gains with equal power panning so the loudness stays
+// even. depth 0.0 keeps it centered, 1.0 swings all the way. the output is stereo
+pub struct AutoPan<S: Source<Item = f32>> {
+	source: S,
+	lfo: Lfo,
+	pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> AutoPan<S> {
+	pub fn new(source: S, rate_hz: f32, depth: f32, waveform: LfoShape) -> AutoPan<S> {
+		let mut lfo = Lfo::new(rate_hz, depth.clamp(0.0, 1.0));
+		lfo.shape = waveform;
+		return AutoPan {
+			source,
+			lfo,
+			pending_right: None,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for AutoPan<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len().map(|len| len / self.source.channels() as usize * 2);
+	}
+
+	fn channels(&self) -> u16 {
+		return 2;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for AutoPan<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let channels = self.source.channels();
+		let mut input = 0.0;
+		for _ in 0..channels {
+			input += self.source.next()?;
+		}
+		input /= channels as f32;
+
+		let (left_gain, right_gain) = pan_gains(self.lfo.next_value(self.source.sample_rate()));
+		self.pending_right = Some(input * right_gain);
+		return Some(input * left_gain);
+	}
+}
+
+// turns a detector level into gain: a peak follower with separate attack and
+// release times, and everything above the threshold is scaled down by the ratio
+#[derive(Clone, Debug)]
+struct GainComputer {
+	threshold_db: f32,
+	ratio: f32,
+	attack_coefficient: f32,
+	release_coefficient: f32,
+	envelope: f32,
+}
+
+impl GainComputer {
+	fn new(threshold_db: f32, ratio: f32, attack: Duration, release: Duration, sample_rate: u32) -> GainComputer {
+		return GainComputer {
+			threshold_db,
+			ratio: ratio.max(1.0),
+			attack_coefficient: time_coefficient(attack.as_secs_f32(), sample_rate),
+			release_coefficient: time_coefficient(release.as_secs_f32(), sample_rate),
+			envelope: 0.0,
+		}
+	}
+
+	fn gain(&mut self, detector: f32) -> f32 {
+		let level = detector.abs();
+		let coefficient = if level > self.envelope { self.attack_coefficient } else { self.release_coefficient };
+		self.envelope = coefficient * self.envelope + (1.0 - coefficient) * level;
+
+		let over_db = gain_to_db(self.envelope) - self.threshold_db;
+		if over_db <= 0.0 {
+			return 1.0;
+		}
+		return db_to_gain(-over_db * (1.0 - 1.0 / self.ratio));
+	}
+}
+
+// feed forward compressor, the signal's own level decides the gain reduction.
+// with a lookahead the audio is delayed by that much while the gain follows the
+// undelayed signal, so the gain is already coming down when a transient gets to
+// the output instead of letting its start through. 64 to 2048 samples is typical,
+// the output is longer than the source by the lookahead
+pub struct Compressor<S: Source<Item = f32>> {
+	source: S,
+	computer: GainComputer,
+	lookahead: Option<DelayLine>,
+	//delayed samples still to come out after the source has ended
+	tail: usize,
+}
+
+impl<S: Source<Item = f32>> Compressor<S> {
+	pub fn new(source: S, threshold_db: f32, ratio: f32, attack: Duration, release: Duration, lookahead: Option<Duration>) -> Compressor<S> {
+		let sample_rate = source.sample_rate();
+		let lookahead_samples = lookahead
+			.map(|time| (time.as_secs_f32() * sample_rate as f32) as usize * source.channels() as usize)
+			.filter(|samples| *samples > 0);
+		return Compressor {
+			source,
+			computer: GainComputer::new(threshold_db, ratio, attack, release, sample_rate),
+			lookahead: lookahead_samples.map(DelayLine::new),
+			tail: lookahead_samples.unwrap_or(0),
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Compressor<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		if self.lookahead.is_some() {
+			return None;
+		}
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		let delay_samples = self.lookahead.as_ref().map(|delay| delay.buffer.len()).unwrap_or(0);
+		let frames = delay_samples / self.source.channels().max(1) as usize;
+		let lookahead = Duration::from_secs_f32(frames as f32 / self.source.sample_rate() as f32);
+		return self.source.total_duration().map(|duration| duration + lookahead);
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Compressor<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let delay = match self.lookahead.as_mut() {
+			Some(delay) => delay,
+			None => {
+				let sample = self.source.next()?;
+				return Some(sample * self.computer.gain(sample));
+			},
+		};
+
+		let input = match self.source.next() {
+			Some(sample) => sample,
+			None if self.tail > 0 => {
+				self.tail -= 1;
+				0.0
+			},
+			None => return None,
+		};
+		let delayed = delay.read();
+		delay.write(input);
+		return Some(delayed * self.computer.gain(input));
+	}
+}
+
+// same as Compressor but the gain reduction follows `key` instead of the signal
+// itself, the kick ducking the bass. both sources have to run at the same sample
+// rate and channel count, they're read one sample each in lockstep. once the key
+// runs out it counts as silence
+pub struct SideChainCompressor<Main: Source<Item = f32>, Key: Source<Item = f32>> {
+	source: Main,
+	key: Key,
+	computer: GainComputer,
+}
+
+impl<Main: Source<Item = f32>, Key: Source<Item = f32>> SideChainCompressor<Main, Key> {
+	pub fn new(source: Main, key: Key, threshold_db: f32, ratio: f32, attack: Duration, release: Duration) -> SideChainCompressor<Main, Key> {
+		let sample_rate = source.sample_rate();
+		return SideChainCompressor {
+			source,
+			key,
+			computer: GainComputer::new(threshold_db, ratio, attack, release, sample_rate),
+		}
+	}
+}
+
+impl<Main: Source<Item = f32>, Key: Source<Item = f32>> Source for SideChainCompressor<Main, Key> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<Main: Source<Item = f32>, Key: Source<Item = f32>> Iterator for SideChainCompressor<Main, Key> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let detector = self.key.next().unwrap_or(0.0);
+		return Some(sample * self.computer.gain(detector));
+	}
+}
+
+// an effect that works on one sample at a time instead of wrapping a Source, for
+// signals that never exist as a source of their own like the mid and side channels
+// of MsProcessor. any FnMut(f32) -> f32 closure is an effect too
+pub trait AudioEffect: Send {
+	fn process(&mut self, sample: f32) -> f32;
+
+	// lets a track reach the low pass filter in a chain of boxed effects, see
+	// VelocityFilterRouting
+	fn as_low_pass_mut(&mut self) -> Option<&mut LowPassFilter> {
+		return None;
+	}
+}
+
+impl<F: FnMut(f32) -> f32 + Send> AudioEffect for F {
+	fn process(&mut self, sample: f32) -> f32 {
+		return self(sample);
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, PartialEq)]
+pub enum GraphError {
+	UnknownNode(NodeId),
+	//the connection would feed a node's output back into itself
+	Cycle(NodeId, NodeId),
+}
+
+impl fmt::Display for GraphError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			GraphError::UnknownNode(node) => write!(f, "no node {} in the graph", node.0),
+			GraphError::Cycle(from, to) => write!(f, "connecting node {} to {} would make a cycle", from.0, to.0),
+		};
+	}
+}
+
+impl std::error::Error for GraphError {}
+
+// effects wired together freely instead of one after another. a node hears the
+// sum of everything connected into it, nodes with nothing connected into them hear
+// the graph's input, and the output is the sum of the nodes that don't lead
+// anywhere. a send bus is two nodes off the input, one dry and one reverb.
+// an empty graph passes the input through
+#[derive(Default)]
+pub struct EffectGraph {
+	nodes: Vec<Box<dyn AudioEffect>>,
+	inputs: Vec<Vec<usize>>,
+	//node indices in topological order, every node comes after all its inputs
+	order: Vec<usize>,
+	//whether a node is connected into another one, the rest make up the output
+	feeds_another: Vec<bool>,
+	//what each node put out for the current sample
+	outputs: Vec<f32>,
+}
+
+impl EffectGraph {
+	pub fn new() -> EffectGraph {
+		return EffectGraph::default();
+	}
+
+	// each effect feeding the next, the same as a plain list of effects
+	pub fn chain(effects: Vec<Box<dyn AudioEffect>>) -> EffectGraph {
+		let mut graph = EffectGraph::new();
+		let mut previous: Option<NodeId> = None;
+		for effect in effects {
+			let node = graph.add_node(effect);
+			if let Some(previous) = previous {
+				graph.inputs[node.0].push(previous.0);
+				graph.feeds_another[previous.0] = true;
+			}
+			previous = Some(node);
+		}
+		graph.order = (0..graph.nodes.len()).collect();
+		return graph;
+	}
+
+	pub fn add_node(&mut self, effect: Box<dyn AudioEffect>) -> NodeId {
+		self.nodes.push(effect);
+		self.inputs.push(Vec::new());
+		self.outputs.push(0.0);
+		self.feeds_another.push(false);
+		self.order.push(self.nodes.len() - 1);
+		return NodeId(self.nodes.len() - 1);
+	}
+
+	pub fn connect(&mut self, from: NodeId, to: NodeId) -> Result<(), GraphError> {
+		for node in [from, to] {
+			if node.0 >= self.nodes.len() {
+				return Err(GraphError::UnknownNode(node));
+			}
+		}
+		if self.inputs[to.0].contains(&from.0) {
+			return Ok(());
+		}
+		self.inputs[to.0].push(from.0);
+		match self.topological_order() {
+			Some(order) => {
+				self.order = order;
+				self.feeds_another[from.0] = true;
+				return Ok(());
+			},
+			None => {
+				self.inputs[to.0].pop();
+				return Err(GraphError::Cycle(from, to));
+			},
+		}
+	}
+
+	pub fn process(&mut self, sample: f32) -> f32 {
+		if self.nodes.is_empty() {
+			return sample;
+		}
+		for &node in self.order.iter() {
+			let input = if self.inputs[node].is_empty() {
+				sample
+			} else {
+				self.inputs[node].iter().map(|&input| self.outputs[input]).sum()
+			};
+			self.outputs[node] = self.nodes[node].process(input);
+		}
+		return self.outputs.iter()
+			.zip(self.feeds_another.iter())
+			.filter(|(_, feeds_another)| !**feeds_another)
+			.map(|(output, _)| output)
+			.sum();
+	}
+
+	// kahn's algorithm, None if there's a cycle
+	fn topological_order(&self) -> Option<Vec<usize>> {
+		let mut waiting: Vec<usize> = self.inputs.iter().map(|inputs| inputs.len()).collect();
+		let mut ready: Vec<usize> = (0..self.nodes.len()).filter(|node| waiting[*node] == 0).collect();
+		let mut order: Vec<usize> = Vec::with_capacity(self.nodes.len());
+		while let Some(node) = ready.pop() {
+			order.push(node);
+			for (next, inputs) in self.inputs.iter().enumerate() {
+				for _ in inputs.iter().filter(|input| **input == node) {
+					waiting[next] -= 1;
+					if waiting[next] == 0 {
+						ready.push(next);
+					}
+				}
+			}
+		}
+		if order.len() < self.nodes.len() {
+			return None;
+		}
+		return Some(order);
+	}
+}
+
+impl AudioEffect for EffectGraph {
+	fn process(&mut self, sample: f32) -> f32 {
+		return EffectGraph::process(self, sample);
+	}
+}
+
+impl From<Vec<Box<dyn AudioEffect>>> for EffectGraph {
+	fn from(effects: Vec<Box<dyn AudioEffect>>) -> EffectGraph {
+		return EffectGraph::chain(effects);
+	}
+}
+
+// runs every sample of a source through a list or graph of effects. the effects
+// see the channels interleaved, so effects with state are meant for mono sources
+pub struct EffectChain<S: Source<Item = f32>> {
+	source: S,
+	effects: EffectGraph,
+}
+
+impl<S: Source<Item = f32>> EffectChain<S> {
+	pub fn new(source: S, effects: impl Into<EffectGraph>) -> EffectChain<S> {
+		return EffectChain {
+			source,
+			effects: effects.into(),
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for EffectChain<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for EffectChain<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		return Some(self.effects.process(sample));
+	}
+}
+
+// a fixed gain in decibels, on the side channel this is the stereo width control
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gain {
+	pub gain_db: f32,
+}
+
+impl Gain {
+	pub const fn new(gain_db: f32) -> Gain {
+		return Gain {
+			gain_db,
+		}
+	}
+}
+
+impl AudioEffect for Gain {
+	fn process(&mut self, sample: f32) -> f32 {
+		return sample * db_to_gain(self.gain_db);
+	}
+}
+
+// mid is what both channels share, side is what differs between them.
+// ms_decode(ms_encode(l, r)) gives back l and r exactly
+pub fn ms_encode(left: f32, right: f32) -> (f32, f32) {
+	return ((left + right) * 0.5, (left - right) * 0.5);
+}
+
+pub fn ms_decode(mid: f32, side: f32) -> (f32, f32) {
+	return (mid + side, mid - side);
+}
+
+// splits a stereo source into mid and side, runs each through its own chain or
+// graph of effects and turns the result back into stereo. a mono source is all
+// mid with no side. the output is stereo
+pub struct MsProcessor<S: Source<Item = f32>> {
+	source: S,
+	mid_chain: EffectGraph,
+	side_chain: EffectGraph,
+	pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> MsProcessor<S> {
+	pub fn new(source: S, mid_chain: impl Into<EffectGraph>, side_chain: impl Into<EffectGraph>) -> MsProcessor<S> {
+		return MsProcessor {
+			source,
+			mid_chain: mid_chain.into(),
+			side_chain: side_chain.into(),
+			pending_right: None,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for MsProcessor<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len().map(|len| len / self.source.channels() as usize * 2);
+	}
+
+	fn channels(&self) -> u16 {
+		return 2;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for MsProcessor<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let (mid, side) = match self.source.channels() {
+			1 => (self.source.next()?, 0.0),
+			channels => {
+				let left = self.source.next()?;
+				let right = self.source.next()?;
+				//anything past the first two channels is dropped
+				for _ in 2..channels {
+					self.source.next()?;
+				}
+				ms_encode(left, right)
+			},
+		};
+
+		let mid = self.mid_chain.process(mid);
+		let side = self.side_chain.process(side);
+
+		let (left, right) = ms_decode(mid, side);
+		self.pending_right = Some(right);
+		return Some(left);
+	}
+}
+
+// an f32 that can be shared between threads, stored as its bit pattern
+#[derive(Debug, Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+	pub fn new(value: f32) -> AtomicF32 {
+		return AtomicF32(AtomicU32::new(value.to_bits()));
+	}
+
+	pub fn load(&self) -> f32 {
+		return f32::from_bits(self.0.load(Ordering::Relaxed));
+	}
+
+	pub fn store(&self, value: f32) {
+		self.0.store(value.to_bits(), Ordering::Relaxed);
+	}
+}
+
+// a fixed size queue between exactly one writer thread (a ui) and one reader
+// thread (the audio), neither of which ever waits on a lock. param_ring_buffer
+// hands out the two ends, and since neither can be cloned and both need &mut to
+// be used there's never more than one of each. the capacity is rounded up to a
+// power of two. the indices only ever grow and wrap around usize, a slot is read
+// or written only while the other side can't touch it
+struct ParamRingBuffer<T: Copy> {
+	slots: Vec<UnsafeCell<T>>,
+	mask: usize,
+	read: AtomicUsize,
+	write: AtomicUsize,
+}
+
+// only a ParamProducer writes slots and only a ParamConsumer reads them, and there's
+// one of each. the writer only touches slots between write and read + capacity and
+// the reader only those between read and write, the acquire and release on the
+// indices hand each slot over
+unsafe impl<T: Copy + Send> Sync for ParamRingBuffer<T> {}
+
+pub struct ParamProducer<T: Copy> {
+	buffer: Arc<ParamRingBuffer<T>>,
+}
+
+pub struct ParamConsumer<T: Copy> {
+	buffer: Arc<ParamRingBuffer<T>>,
+}
+
+pub fn param_ring_buffer<T: Copy + Default>(capacity: usize) -> (ParamProducer<T>, ParamConsumer<T>) {
+	let capacity = capacity.max(1).next_power_of_two();
+	let buffer = Arc::new(ParamRingBuffer {
+		slots: (0..capacity).map(|_| UnsafeCell::new(T::default())).collect(),
+		mask: capacity - 1,
+		read: AtomicUsize::new(0),
+		write: AtomicUsize::new(0),
+	});
+	return (ParamProducer { buffer: buffer.clone() }, ParamConsumer { buffer });
+}
+
+impl<T: Copy> ParamProducer<T> {
+	pub fn capacity(&self) -> usize {
+		return self.buffer.slots.len();
+	}
+
+	// false if the queue is full and the value was dropped
+	pub fn push(&mut self, value: T) -> bool {
+		let buffer = &self.buffer;
+		let write = buffer.write.load(Ordering::Relaxed);
+		if write.wrapping_sub(buffer.read.load(Ordering::Acquire)) >= buffer.slots.len() {
+			return false;
+		}
+		unsafe { *buffer.slots[write & buffer.mask].get() = value };
+		buffer.write.store(write.wrapping_add(1), Ordering::Release);
+		return true;
+	}
+}
+
+impl<T: Copy> ParamConsumer<T> {
+	pub fn capacity(&self) -> usize {
+		return self.buffer.slots.len();
+	}
+
+	// oldest value first
+	pub fn pop(&mut self) -> Option<T> {
+		let buffer = &self.buffer;
+		let read = buffer.read.load(Ordering::Relaxed);
+		if read == buffer.write.load(Ordering::Acquire) {
+			return None;
+		}
+		let value = unsafe { *buffer.slots[read & buffer.mask].get() };
+		buffer.read.store(read.wrapping_add(1), Ordering::Release);
+		return Some(value);
+	}
+}
+
+// what moves the wah. Auto sweeps with a sine lfo at the given rate, Manual reads
+// a pedal position from 0.0 (heel, low_hz) to 1.0 (toe, high_hz) that can be
+// changed from another thread while playing
+#[derive(Clone, Debug)]
+pub enum WahMode {
+	Auto(f32),
+	Manual(Arc<AtomicF32>),
+}
+
+const WAH_Q: f32 = 4.0;
+//coefficients are refreshed this often instead of every sample, ~0.7 ms at 44.1 khz
+const WAH_UPDATE_INTERVAL: u32 = 32;
+
+// a band pass filter swept between low_hz and high_hz. the sweep is exponential so
+// equal pedal travel moves the same number of octaves. recomputing a biquad needs
+// a sin and a cos, so the coefficients are only updated every WAH_UPDATE_INTERVAL
+// frames and only when the center actually moved
+pub struct WahWah<S: Source<Item = f32>> {
+	source: S,
+	mode: WahMode,
+	lfo: Lfo,
+	low_hz: f32,
+	high_hz: f32,
+	center_hz: f32,
+	filters: Vec<Biquad>,
+	channel: usize,
+	frames_until_update: u32,
+}
+
+impl<S: Source<Item = f32>> WahWah<S> {
+	pub fn new(source: S, low_hz: f32, high_hz: f32, mode: WahMode) -> WahWah<S> {
+		let rate = match mode {
+			WahMode::Auto(rate_hz) => rate_hz,
+			WahMode::Manual(_) => 0.0,
+		};
+		let channels = source.channels() as usize;
+		let mut wah = WahWah {
+			source,
+			mode,
+			lfo: Lfo::new(rate, 0.5),
+			low_hz: low_hz.max(1.0),
+			high_hz: high_hz.max(low_hz.max(1.0)),
+			center_hz: 0.0,
+			filters: vec![Biquad::default(); channels],
+			channel: 0,
+			frames_until_update: 0,
+		};
+		wah.update_filters();
+		return wah;
+	}
+
+	pub fn center_hz(&self) -> f32 {
+		return self.center_hz;
+	}
+
+	fn position(&mut self) -> f32 {
+		let position = match &self.mode {
+			//the lfo is only read once per update, so it runs at the update rate
+			WahMode::Auto(_) => 0.5 + self.lfo.next_value(self.source.sample_rate() / WAH_UPDATE_INTERVAL),
+			WahMode::Manual(pedal) => pedal.load(),
+		};
+		return position.clamp(0.0, 1.0);
+	}
+
+	fn update_filters(&mut self) {
+		let position = self.position();
+		let center_hz = self.low_hz * (self.high_hz / self.low_hz).powf(position);
+		if (center_hz - self.center_hz).abs() < 0.01 {
+			return;
+		}
+		self.center_hz = center_hz;
+
+		//rbj band pass with 0 db peak gain
+		let omega = 2.0 * std::f32::consts::PI * center_hz / self.source.sample_rate() as f32;
+		let (sin, cos) = omega.sin_cos();
+		let alpha = sin / (2.0 * WAH_Q);
+		for filter in self.filters.iter_mut() {
+			filter.set_coefficients(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for WahWah<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for WahWah<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		if self.channel == 0 {
+			if self.frames_until_update == 0 {
+				self.update_filters();
+				self.frames_until_update = WAH_UPDATE_INTERVAL;
+			}
+			self.frames_until_update -= 1;
+		}
+
+		let output = self.filters[self.channel].process(sample);
+		self.channel = (self.channel + 1) % self.filters.len();
+		return Some(output);
+	}
+}
+
+// one pole high pass that removes dc the oscillators didn't have in their tables,
+// from modulation or asymmetric distortion. y = x - x[n-1] + 0.995 * y[n-1], which
+// puts the corner at about 35 hz at 44.1 khz
+pub struct DcBlocker<S: Source<Item = f32>> {
+	source: S,
+	previous_input: Vec<f32>,
+	previous_output: Vec<f32>,
+	channel: usize,
+}
+
+const DC_BLOCKER_POLE: f32 = 0.995;
+
+impl<S: Source<Item = f32>> DcBlocker<S> {
+	pub fn new(source: S) -> DcBlocker<S> {
+		let channels = source.channels() as usize;
+		return DcBlocker {
+			source,
+			previous_input: vec![0.0; channels],
+			previous_output: vec![0.0; channels],
+			channel: 0,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for DcBlocker<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for DcBlocker<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let input = self.source.next()?;
+		let channel = self.channel;
+		let output = input - self.previous_input[channel] + DC_BLOCKER_POLE * self.previous_output[channel];
+		self.previous_input[channel] = input;
+		self.previous_output[channel] = output;
+		self.channel = (channel + 1) % self.previous_input.len();
+		return Some(output);
+	}
+}
+
+// keeps the peak level near target_level_db without a threshold or ratio: a peak
+// follower with a fast attack and slow release sets the gain that would put the
+// current peak on target. quiet passages are brought up by at most AUTO_GAIN_MAX_DB
+// so silence and noise floors aren't blown up
+pub struct AutoGain<S: Source<Item = f32>> {
+	source: S,
+	target_level: f32,
+	peak: f32,
+	gain: f32,
+	attack_coefficient: f32,
+	release_coefficient: f32,
+}
+
+const AUTO_GAIN_MAX_DB: f32 = 24.0;
+const AUTO_GAIN_ATTACK: f32 = 0.005;
+const AUTO_GAIN_RELEASE: f32 = 1.0;
+
+impl<S: Source<Item = f32>> AutoGain<S> {
+	pub fn new(source: S, target_level_db: f32) -> AutoGain<S> {
+		let sample_rate = source.sample_rate();
+		return AutoGain {
+			source,
+			target_level: db_to_gain(target_level_db),
+			peak: 0.0,
+			gain: 1.0,
+			attack_coefficient: time_coefficient(AUTO_GAIN_ATTACK, sample_rate),
+			release_coefficient: time_coefficient(AUTO_GAIN_RELEASE, sample_rate),
+		}
+	}
+
+	pub fn current_gain(&self) -> f32 {
+		return self.gain;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for AutoGain<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for AutoGain<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let level = sample.abs();
+		let coefficient = if level > self.peak { self.attack_coefficient } else { self.release_coefficient };
+		self.peak = coefficient * self.peak + (1.0 - coefficient) * level;
+
+		self.gain = (self.target_level / self.peak.max(1e-9)).min(db_to_gain(AUTO_GAIN_MAX_DB));
+		return Some(sample * self.gain);
+	}
+}
+
+// plays a source faster or slower by a factor that can change while it plays, like
+// a turntable: pitch and tempo move together. the factor is shared so another thread
+// can turn the knob, 1.0 plays the source as it is. meant for mono sources
+pub struct Varispeed<S: Source<Item = f32>> {
+	source: S,
+	speed: Arc<AtomicF32>,
+	current: f32,
+	next: f32,
+	position: f32,
+	started: bool,
+	finished: bool,
+}
+
+const VARISPEED_MIN_SPEED: f32 = 0.01;
+
+impl<S: Source<Item = f32>> Varispeed<S> {
+	pub fn new(source: S, speed: Arc<AtomicF32>) -> Varispeed<S> {
+		return Varispeed {
+			source,
+			speed,
+			current: 0.0,
+			next: 0.0,
+			position: 0.0,
+			started: false,
+			finished: false,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Varispeed<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		//depends on how the speed is changed while playing
+		return None;
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Varispeed<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if !self.started {
+			self.current = self.source.next()?;
+			self.next = match self.source.next() {
+				Some(sample) => sample,
+				None => {
+					self.finished = true;
+					self.current
+				},
+			};
+			self.started = true;
+		}
+		while self.position >= 1.0 {
+			if self.finished {
+				return None;
+			}
+			self.current = self.next;
+			match self.source.next() {
+				Some(sample) => self.next = sample,
+				//the last sample is still played out before stopping
+				None => self.finished = true,
+			}
+			self.position -= 1.0;
+		}
+		let sample = self.current + (self.next - self.current) * self.position;
+		self.position += self.speed.load().max(VARISPEED_MIN_SPEED);
+		return Some(sample);
+	}
+}
+
+// curves that bend loud samples down towards +-1.0 without the hard corner of
+// clipping. all of them are close to a straight line for quiet signals
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SoftClipMode {
+	//x / (1 + |x|), the gentlest of the three
+	Algebraic,
+	Tanh,
+	//atan scaled so it still levels off at 1.0
+	Arctangent,
+}
+
+impl SoftClipMode {
+	pub fn shape(&self, sample: f32) -> f32 {
+		return match self {
+			SoftClipMode::Algebraic => sample / (1.0 + sample.abs()),
+			SoftClipMode::Tanh => sample.tanh(),
+			SoftClipMode::Arctangent => sample.atan() * 2.0 / std::f32::consts::PI,
+		};
+	}
+}
+
+// a bit of analog style saturation: drive is the gain going into the curve, more
+// drive bends the signal harder, and output_gain brings the level back down after
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SoftClip {
+	pub mode: SoftClipMode,
+	pub drive: f32,
+	pub output_gain: f32,
+}
+
+impl SoftClip {
+	pub const fn new(mode: SoftClipMode, drive: f32, output_gain: f32) -> SoftClip {
+		return SoftClip {
+			mode,
+			drive,
+			output_gain,
+		}
+	}
+}
+
+impl AudioEffect for SoftClip {
+	fn process(&mut self, sample: f32) -> f32 {
+		return self.mode.shape(sample * self.drive) * self.output_gain;
+	}
+}
+
+pub struct SoftClipper<S: Source<Item = f32>> {
+	source: S,
+	clip: SoftClip,
+}
+
+impl<S: Source<Item = f32>> SoftClipper<S> {
+	pub fn new(source: S, mode: SoftClipMode, drive: f32, output_gain: f32) -> SoftClipper<S> {
+		return SoftClipper {
+			source,
+			clip: SoftClip::new(mode, drive, output_gain),
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for SoftClipper<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for SoftClipper<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		return Some(self.clip.process(sample));
+	}
+}
+
+//length of the grains the harmonizer's pitch shifters crossfade between, in samples
+const HARMONIZER_WINDOW: usize = 2048;
+//samples of the input the pitch detection looks at, and how often it runs
+const PITCH_DETECTION_WINDOW: usize = 1024;
+const PITCH_DETECTION_HOP: usize = 512;
+//lowest and highest fundamental the detection looks for. the lowest is also held
+//to what half the window can fit
+const PITCH_DETECTION_MIN_HZ: f32 = 60.0;
+const PITCH_DETECTION_MAX_HZ: f32 = 1000.0;
+//how far the normalized difference has to dip for a lag to count as the period,
+//0.0 is a perfect repeat
+const PITCH_DETECTION_THRESHOLD: f32 = 0.15;
+
+// delay line pitch shifter: two read heads sweep through the last
+// HARMONIZER_WINDOW samples at `ratio` times the speed they were written, half a
+// window apart, and each fades out as it wraps around while the other is loudest
+#[derive(Clone)]
+struct PitchShifter {
+	buffer: Vec<f32>,
+	position: usize,
+	phase: f32,
+}
+
+impl PitchShifter {
+	fn new() -> PitchShifter {
+		return PitchShifter {
+			buffer: vec![0.0; HARMONIZER_WINDOW],
+			position: 0,
+			phase: 0.0,
+		}
+	}
+
+	fn process(&mut self, input: f32, ratio: f32) -> f32 {
+		let len = self.buffer.len();
+		self.buffer[self.position] = input;
+		self.position = (self.position + 1) % len;
+
+		self.phase = (self.phase + (1.0 - ratio) / len as f32).rem_euclid(1.0);
+		let mut output = 0.0;
+		for head in [self.phase, (self.phase + 0.5) % 1.0] {
+			//the newest sample sits just behind position
+			let index = (self.position + len) as f32 - 1.0 - head * (len - 1) as f32;
+			let gain = 1.0 - (2.0 * head - 1.0).abs();
+			output += gain * interpolate(&self.buffer, index);
+		}
+		return output;
+	}
+}
+
+// adds voices at fixed intervals above or below the input. without a key every
+// voice is shifted by its interval exactly. with a key the input's pitch is tracked and
+// each voice lands on the note of the scale nearest to the shifted pitch, so a
+// third above stays a major or minor third depending on where the melody is. when
+// no clear pitch is found the voices fall back to the plain interval. wet_mix 0.0
+// is the dry input only, 1.0 the voices only
+pub struct Harmonizer<S: Source<Item = f32>> {
+	source: S,
+	intervals: Vec<(Interval, Direction)>,
+	wet_mix: f32,
+	key: Option<Scale>,
+	//one shifter per interval per channel
+	shifters: Vec<PitchShifter>,
+	ratios: Vec<f32>,
+	//the first channel's recent samples, for the pitch detection
+	history: Vec<f32>,
+	history_position: usize,
+	detector: PitchDetector,
+	frames_since_detection: usize,
+	channel: usize,
+}
+
+impl<S: Source<Item = f32>> Harmonizer<S> {
+	pub fn new(source: S, intervals: Vec<(Interval, Direction)>, wet_mix: f32, key: Option<Scale>) -> Harmonizer<S> {
+		let channels = source.channels().max(1) as usize;
+		let ratios = intervals.iter().map(|interval| chromatic_ratio(*interval)).collect();
+		return Harmonizer {
+			shifters: vec![PitchShifter::new(); intervals.len() * channels],
+			ratios,
+			detector: PitchDetector::new(PITCH_DETECTION_WINDOW, source.sample_rate()),
+			source,
+			intervals,
+			wet_mix: wet_mix.clamp(0.0, 1.0),
+			key,
+			history: vec![0.0; PITCH_DETECTION_WINDOW],
+			history_position: 0,
+			frames_since_detection: 0,
+			channel: 0,
+		}
+	}
+
+	fn update_ratios(&mut self, key: Scale) {
+		let detected = self.detector.detect(&self.history, self.history_position);
+		for (ratio, interval) in self.ratios.iter_mut().zip(self.intervals.iter()) {
+			let chromatic = chromatic_ratio(*interval);
+			*ratio = match detected {
+				Some(frequency) => nearest_scale_frequency(key, frequency * chromatic) / frequency,
+				None => chromatic,
+			};
+		}
+	}
+}
+
+// the note of the scale, in any octave, closest in pitch to frequency
+fn nearest_scale_frequency(key: Scale, frequency: f32) -> f32 {
+	let semitones = 12.0 * (frequency / key.tonic).log2();
+	let octave = (semitones / 12.0).floor();
+	let within = semitones - 12.0 * octave;
+	let intervals = key.mode.intervals();
+	let nearest = intervals.iter().map(|interval| *interval as f32).chain(std::iter::once(12.0))
+		.min_by(|a, b| (a - within).abs().partial_cmp(&(b - within).abs()).unwrap_or(std::cmp::Ordering::Equal))
+		.unwrap_or(within);
+	return key.tonic * 2.0_f32.powf((12.0 * octave + nearest) / 12.0);
+}
+
+// the frequency ratio of an interval played exactly
+fn chromatic_ratio((interval, direction): (Interval, Direction)) -> f32 {
+	let semitones = match direction {
+		Direction::Up => interval.semitones() as f32,
+		Direction::Down => -(interval.semitones() as f32),
+	};
+	return 2.0_f32.powf(semitones / 12.0);
+}
+
+// yin pitch detection with its buffers made up front, it runs on the audio thread
+struct PitchDetector {
+	sample_rate: u32,
+	shortest: usize,
+	longest: usize,
+	//the window unrolled from the ring buffer
+	samples: Vec<f32>,
+	//cumulative mean normalized difference for every lag up to longest
+	difference: Vec<f32>,
+}
+
+impl PitchDetector {
+	fn new(window: usize, sample_rate: u32) -> PitchDetector {
+		let longest = ((sample_rate as f32 / PITCH_DETECTION_MIN_HZ) as usize).min(window / 2).max(2);
+		let shortest = ((sample_rate as f32 / PITCH_DETECTION_MAX_HZ) as usize).clamp(1, longest - 1);
+		return PitchDetector {
+			sample_rate,
+			shortest,
+			longest,
+			samples: vec![0.0; window],
+			difference: vec![1.0; longest + 2],
+		}
+	}
+
+	// fundamental of the samples in a ring buffer that starts at `start`. the
+	// period is the first lag whose difference dips under the threshold, taken at
+	// the bottom of that dip, so the shorter of two similar periods wins and a
+	// note isn't heard an octave down. None for silence or noise
+	fn detect(&mut self, history: &[f32], start: usize) -> Option<f32> {
+		let (newer, older) = history.split_at(start);
+		self.samples[..older.len()].copy_from_slice(older);
+		self.samples[older.len()..].copy_from_slice(newer);
+		let width = self.samples.len() - self.longest - 1;
+
+		let mut running_sum = 0.0;
+		let mut found: Option<usize> = None;
+		for lag in 1..=self.longest + 1 {
+			let mut squared = 0.0;
+			for n in 0..width {
+				let delta = self.samples[n] - self.samples[n + lag];
+				squared += delta * delta;
+			}
+			running_sum += squared;
+			self.difference[lag] = if running_sum > 0.0 { squared * lag as f32 / running_sum } else { 1.0 };
+
+			//one lag past the bottom of the first dip is enough to place it
+			match found {
+				Some(dip) if self.difference[lag] >= self.difference[dip] => break,
+				Some(_) => found = Some(lag),
+				None if lag >= self.shortest && lag <= self.longest && self.difference[lag] < PITCH_DETECTION_THRESHOLD => found = Some(lag),
+				None => {},
+			}
+		}
+		let lag = found.filter(|lag| *lag <= self.longest)?;
+
+		//a parabola through the dip and its neighbours puts the period between lags
+		let (before, at, after) = (self.difference[lag - 1], self.difference[lag], self.difference[lag + 1]);
+		let curvature = before - 2.0 * at + after;
+		let offset = if curvature > 0.0 { (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+		return Some(self.sample_rate as f32 / (lag as f32 + offset));
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Harmonizer<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for Harmonizer<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let input = self.source.next()?;
+		let channels = self.source.channels().max(1) as usize;
+		let channel = self.channel;
+		self.channel = (self.channel + 1) % channels;
+
+		if channel == 0 {
+			self.history[self.history_position] = input;
+			self.history_position = (self.history_position + 1) % self.history.len();
+			self.frames_since_detection += 1;
+			if let Some(key) = self.key {
+				if self.frames_since_detection >= PITCH_DETECTION_HOP {
+					self.frames_since_detection = 0;
+					self.update_ratios(key);
+				}
+			}
+		}
+
+		if self.intervals.is_empty() {
+			return Some(input);
+		}
+		let mut wet = 0.0;
+		for (voice, ratio) in self.ratios.iter().enumerate() {
+			wet += self.shifters[voice * channels + channel].process(input, *ratio);
+		}
+		wet /= self.intervals.len() as f32;
+		return Some(input * (1.0 - self.wet_mix) + wet * self.wet_mix);
+	}
+}
@@ -0,0 +1,201 @@
+// generated by examples/cabinet_tables.rs from sketches of how each kind of cabinet tends to measure, stand-ins for measured responses, don't edit by hand
+
+// (sample rate, impulse response) of each cabinet
+pub(super) static BRITISH_STACK_4X12: (u32, &[f32]) = (44100, &[
+	2.3502984e-11, -2.2149855e-8, -6.2426054e-8, -4.2835772e-8, 8.5665675e-8, 2.5060325e-7, 2.503131e-7, -1.3164046e-7,
+	-9.437524e-7, -1.9396734e-6, -2.6248701e-6, -2.5033326e-6, -1.4174877e-6, 2.1842004e-7, 1.4776972e-6, 1.3198674e-6,
+	-8.231957e-7, -4.578389e-6, -8.587255e-6, -1.101567e-5, -1.0455019e-5, -6.7957153e-6, -1.5809628e-6, 2.4603692e-6,
+	2.6126763e-6, -2.3987259e-6, -1.146878e-5, -2.1246433e-5, -2.7458249e-5, -2.6934476e-5, -1.9405472e-5, -8.109117e-6,
+	1.2862929e-6, 3.1493382e-6, -5.298505e-6, -2.2191305e-5, -4.129061e-5, -5.4468146e-5, -5.545842e-5, -4.31825e-5,
+	-2.2943692e-5, -4.638234e-6, 1.5047335e-6, -1.0140955e-5, -3.732474e-5, -7.009991e-5, -9.4763454e-5, -0.00010005814,
+	-8.287135e-5, -5.0615043e-5, -1.8780353e-5, -4.344838e-6, -1.7786107e-5, -5.7408204e-5, -0.00010894156, -0.00015112803,
+	-0.00016519331, -0.00014403762, -9.672086e-5, -4.565579e-5, -1.7180428e-5, -2.9404895e-5, -8.288179e-5, -0.00015879376,
+	-0.00022618115, -0.00025555113, -0.000232969, -0.00016795672, -9.09572e-5, -4.0690036e-5, -4.6555695e-5, -0.00011402169,
+	-0.00022012957, -0.0003220557, -0.00037572446, -0.0003564495, -0.0002720418, -0.00016161871, -7.959195e-5, -7.126124e-5,
+	-0.00015087532, -0.00029266477, -0.0004400126, -0.00052977016, -0.0005213837, -0.0004174998, -0.00026577455, -0.0001397159,
+	-0.00010608591, -0.00019321307, -0.0003751224, -0.00058000255, -0.00072067766, -0.0007343021, -0.0006133189, -0.0004126224,
+	-0.00022802313, -0.00015420455, -0.00024049784, -0.0004649875, -0.0007402074, -0.0009497674, -0.0010007604, -0.00086849154,
+	-0.0006121828, -0.00035257213, -0.00021946063, -0.0002918915, -0.0005583305, -0.00091661647, -0.001216069, -0.0013246564,
+	-0.0011914377, -0.0008749614, -0.00052242, -0.00030641223, -0.00034627237, -0.0006496457, -0.0011026149, -0.0015157035,
+	-0.0017075364, -0.0015894029, -0.0012115355, -0.00074746826, -0.000420366, -0.00040230935, -0.0007317923, -0.0012886801,
+	-0.0018413326, -0.0021479176, -0.0020678365, -0.0016321721, -0.0010383357, -0.0005674274, -0.00045853097, -0.00079592114,
+	-0.0014620848, -0.002181651, -0.002640685, -0.002629837, -0.0021464452, -0.001406221, -0.0007545601, -0.0005134406,
+	-0.0008314839, -0.0016066565, -0.0025209559, -0.0031765993, -0.0032757833, -0.002763105, -0.001862995, -0.0009897682,
+	-0.00056561106, -0.0008261176, -0.0017025166, -0.0028387522, -0.0037419351, -0.004003163, -0.00349016, -0.0024214743,
+	-0.0012823897, -0.0006137977, -0.0007654162, -0.0017254951, -0.0031091552, -0.004318194, -0.004806813, -0.004335626,
+	-0.0030963807, -0.0016438122, -0.0006570162, -0.00063234847, -0.0016461723, -0.0032999916, -0.0048818476, -0.005679626,
+	-0.0053090807, -0.0039061597, -0.0020887419, -0.0006946074, -0.0004058967, -0.001427632, -0.0033708583, -0.005403819,
+	-0.006614089, -0.006424931, -0.0048767477, -0.0026377812, -0.00072625955, -5.828147e-5, -0.0010210329, -0.0032691192,
+	-0.005848241, -0.007605099, -0.0077087595, -0.0060490165, -0.003322546, -0.0007520204, 0.0004508784, -0.00035605812,
+	-0.0029211226, -0.0061692977, -0.008655317, -0.009210266, -0.0074942927, -0.0041965987, -0.00077222567, 0.0011874065,
+	0.00068035955, -0.0022120802, -0.0063032284, -0.009786244, -0.011031939, -0.00934981, -0.0053606536, -0.000787447,
+	0.002273433, 0.002296229, -0.000935443, -0.0061467523, -0.011064698, -0.013401845, -0.011910771, -0.0070300587,
+	-0.00079838745, 0.0039732964, 0.004943433, 0.0013557016, -0.005490964, -0.012681154, -0.016898818, -0.015925134,
+	-0.009757172, -0.0008057747, 0.0069954493, 0.009860264, 0.0059109856, -0.003758497, -0.015265812, -0.02341164,
+	-0.02391278, -0.015481474, -0.0008102907, 0.014098327, 0.022162527, 0.018241223, 0.0017591617, -0.022205219,
+	-0.04399647, -0.052439112, -0.038887616, -0.00081240525, 0.056469508, 0.120703615, 0.17609489, 0.20811874,
+	0.20814595, 0.176149, 0.1207843, 0.056576442, -0.000679543, -0.038729154, -0.05225538, -0.043787807,
+	-0.021971965, 0.0020166675, 0.018522633, 0.022467498, 0.014426507, -0.0004592511, -0.015107932, -0.02351709,
+	-0.022994166, -0.014826908, -0.0032985322, 0.0063916496, 0.01036126, 0.0075164107, -0.0002652168, -0.009197389,
+	-0.015346494, -0.016301699, -0.012065924, -0.0048579993, 0.0020060262, 0.0056107426, 0.0046572145, -9.8236545e-5,
+	-0.00631405, -0.011179282, -0.012655252, -0.010303379, -0.0053710835, -0.00014579942, 0.0030994706, 0.0030898997,
+	4.1869003e-5, -0.004518862, -0.008495917, -0.010166315, -0.008909259, -0.0054152566, -0.0013134901, 0.0015892003,
+	0.0021061327, 0.00015601999, -0.0032591964, -0.006548097, -0.008255636, -0.0076926136, -0.005198876, -0.0019433387,
+	0.0006287354, 0.0014423305, 0.0002457418, -0.0023188205, -0.0050396733, -0.006694139, -0.006585542, -0.004824084,
+	-0.0022406974, 1.1321157e-5, 0.0009776757, 0.0003129742, -0.0015955949, -0.0038319295, -0.0053777974, -0.00556496,
+	-0.004353006, -0.0023186675, -0.00037437014, 0.0006481318, 0.0003598889, -0.0010340742, -0.002851613, -0.004254945,
+	-0.0046261908, -0.0038293917, -0.002248798, -0.00059651746, 0.0004154964, 0.00038875, -0.00060039025, -0.0020555644,
+	-0.0032976735, -0.003771977, -0.0032867286, -0.0020813076, -0.00070150464, 0.00025447516, 0.00040176, -0.00027139753,
+	-0.0014152752, -0.00248898, -0.0030072185, -0.0027514435, -0.0018539255, -0.0007235635, 0.00014675804, 0.0004009874,
+	-2.9643823e-5, -0.00090953644, -0.0018164995, -0.0023362164, -0.0022442504, -0.0015960017, -0.00068927, 7.816788e-5,
+	0.00038831215, 0.00013913383, -0.00052074145, -0.0012693321, -0.0017612395, -0.0017807445, -0.0013305095, -0.00061987794,
+	3.7220074e-5, 0.00036541582, 0.00024720188, -0.00023314427, -0.0008365343, -0.0012818652, -0.0013717203, -0.001075014,
+	-0.00053233904, 1.4473568e-5, 0.00033378898, 0.0003054766, -3.1966832e-5, -0.000506455, -0.00089482166, -0.0010235282,
+	-0.00084233686, -0.0004399572, 2.2410532e-6, 0.0002947849, 0.00032376085, 9.681744e-5, -0.00026670686, -0.000594211,
+	-0.0007384968, -0.00064101524, -0.00035270152, -5.5840937e-6, 0.0002496588, 0.0003108706, 0.00016652039, -0.000104345905,
+	-0.00037192373, -0.00051547214, -0.0004757652, -0.00027749455, -1.3565237e-5, 0.0001996367, 0.00027464822, 0.00018955654,
+	-6.30421e-6, -0.00021824683, -0.00035045933, -0.0003480436, -0.00021855306, -2.4831737e-5, 0.000145948, 0.00022197718,
+	0.00017728373, 4.01992e-5, -0.00012251257, -0.00023734043, -0.0002566255, -0.00017771992, -4.117386e-5, 8.986659e-5,
+	0.00015878586, 0.00013981754, 4.7212437e-5, -7.372716e-5, -0.0001685879, -0.00019825005, -0.00015490029, -6.322251e-5,
+	3.27088e-5, 9.007244e-5, 8.591189e-5, 2.5696587e-5, -6.1170795e-5, -0.00013596943, -0.0001682546, -0.00014850189,
+	-9.0645124e-5, -2.4175373e-5, 1.9949683e-5, 2.2914286e-5, -1.4727753e-5, -7.4864525e-5, -0.00013115694, -0.00016119042,
+	-0.00015590411, -0.00012239629, -7.944011e-5, -4.8292426e-5, -4.3221586e-5, -6.592239e-5, -0.00010591591, -0.00014622406,
+	-0.00017135452, -0.00017389428, -0.00015695445, -0.00013179243, -0.00011210985, -0.00010785258, -0.00012127028, -0.00014673645,
+	-0.00017402094, -0.00019322717, -0.00019905099, -0.00019257044, -0.00018005117, -0.00016962063, -0.00016751679, -0.00017560509,
+	-0.00019110682, -0.00020838992, -0.00022178615, -0.00022805721, -0.00022747237, -0.00022320138, -0.00021951736, -0.00021977139,
+	-0.00022506827, -0.0002341334, -0.0002442511, -0.00025269247, -0.00025791425, -0.00026002637, -0.0002604374, -0.0002609816,
+	-0.0002630176, -0.00026691862, -0.00027211694, -0.00027757228, -0.00028235823, -0.00028606076, -0.000288838, -0.00029118633,
+	-0.00029360052, -0.00029632746, -0.00029931898, -0.00030235943, -0.0003052474, -0.0003079112, -0.0003104001, -0.00031279298,
+]);
+pub(super) static AMERICAN_COMBO_1X12: (u32, &[f32]) = (44100, &[
+	3.2544703e-11, -1.6948448e-8, -1.2671578e-8, 3.8406114e-9, -1.5037315e-7, -6.2816673e-7, -1.2402226e-6, -1.4737841e-6,
+	-1.0004222e-6, -2.3035955e-7, -2.2352481e-7, -1.775315e-6, -4.402415e-6, -6.3339558e-6, -5.882611e-6, -3.2103997e-6,
+	-7.8087896e-7, -1.6427512e-6, -6.6401803e-6, -1.3002128e-5, -1.6031117e-5, -1.3033353e-5, -6.288797e-6, -2.0656835e-6,
+	-5.748002e-6, -1.6773147e-5, -2.7974518e-5, -3.0762276e-5, -2.2531305e-5, -1.0028179e-5, -4.978001e-6, -1.4576779e-5,
+	-3.445567e-5, -5.071573e-5, -5.0617542e-5, -3.3834363e-5, -1.4508503e-5, -1.1027361e-5, -3.073127e-5, -6.212885e-5,
+	-8.234279e-5, -7.5298725e-5, -4.63396e-5, -2.026041e-5, -2.2456792e-5, -5.7354955e-5, -0.00010224475, -0.00012354007,
+	-0.00010409011, -5.9508653e-5, -2.8451614e-5, -4.233503e-5, -9.804508e-5, -0.00015705949, -0.00017439309, -0.00013586726,
+	-7.3047195e-5, -4.10847e-5, -7.459693e-5, -0.00015668367, -0.00022836859, -0.00023422955, -0.00016916572, -8.71357e-5,
+	-6.118664e-5, -0.00012401227, -0.00023717509, -0.0003172008, -0.00030149746, -0.00020232632, -0.00010270638, -9.2961855e-5,
+	-0.00019604887, -0.00034308966, -0.00042349187, -0.0003737094, -0.00023373109, -0.00012174768, -0.00014187496, -0.00029661378,
+	-0.0004772212, -0.00054577994, -0.0004474865, -0.00026212822, -0.00014760799, -0.00021462771, -0.00043166013, -0.0006410857,
+	-0.00068095495, -0.00051872863, -0.00028705323, -0.0001852588, -0.00031899614, -0.0006066602, -0.0008343968, -0.00082411536,
+	-0.00058292516, -0.00030927864, -0.00024147538, -0.00046350862, -0.0008259626, -0.0010545676, -0.0009685706, -0.0006356135,
+	-0.00033131096, -0.00032488597, -0.00065692, -0.0010920665, -0.0012962954, -0.0011060277, -0.0006729679, -0.00035784452,
+	-0.00044585098, -0.00090755936, -0.0014048645, -0.0015512854, -0.0012269793, -0.0006924904, -0.00039613235, -0.0006161429,
+	-0.0012224747, -0.0017609119, -0.0018081781, -0.0013212984, -0.0006937509, -0.00045621814, -0.00084839214, -0.0016065198,
+	-0.0021527954, -0.0020526862, -0.0013790233, -0.00067914557, -0.0005509785, -0.0011553532, -0.0020613105, -0.0025686598,
+	-0.0022680147, -0.0013912885, -0.0006545412, -0.00069594395, -0.0015489592, -0.0025842988, -0.002991952, -0.0024355066,
+	-0.0013513518, -0.00062983367, -0.0009088884, -0.0020392626, -0.0031678681, -0.0034014261, -0.0025355485, -0.001255625,
+	-0.0006193052, -0.001209206, -0.0026333164, -0.0037986182, -0.0037713973, -0.0025485605, -0.0011047004, -0.00064178824,
+	-0.0016171329, -0.0033340957, -0.0044568535, -0.004072216, -0.0024561128, -0.0009041511, -0.000720639, -0.0021529277,
+	-0.0041395463, -0.005116298, -0.0042708684, -0.002241944, -0.000665247, -0.0008836223, -0.0028360765, -0.0050418894,
+	-0.0057439744, -0.004331531, -0.001892754, -0.0004054429, -0.0011628582, -0.0036849612, -0.006027177, -0.0063001085,
+	-0.0042157806, -0.001398566, -0.00014874968, -0.0015951642, -0.004717054, -0.0070753833, -0.0067377994, -0.0038819804,
+	-0.00075236685, 7.3811505e-5, -0.0022233692, -0.0059503894, -0.008160779, -0.007001693, -0.0032830455, 5.137408e-5,
+	0.00022327807, -0.0030997363, -0.007407232, -0.009252834, -0.0070247483, -0.002360701, 0.0010202704, 0.00025036,
+	-0.004293709, -0.009122036, -0.010317501, -0.0067200772, -0.0010329171, 0.00217129, 8.9508016e-5, -0.0059095826,
+	-0.011158438, -0.011318836, -0.0059617376, 0.0008350569, 0.003546489, -0.0003560786, -0.008127876, -0.013648571,
+	-0.012220916, -0.0045363447, 0.003501866, 0.005250879, -0.0012548328, -0.011314167, -0.016896069, -0.012989777,
+	-0.0020050795, 0.007554933, 0.0075617637, -0.0029735619, -0.016364034, -0.021710802, -0.013595371, 0.0027893041,
+	0.014670153, 0.011350382, -0.006603424, -0.026206963, -0.030960567, -0.014013454, 0.014399374, 0.03212084,
+	0.020854197, -0.017891692, -0.05879242, -0.06558836, -0.014226883, 0.088425815, 0.20350339, 0.27917513,
+	0.27918673, 0.20352645, 0.08846021, -0.014181299, -0.065531716, -0.058724858, -0.017813344, 0.02094319,
+	0.032220338, 0.01450924, -0.013893362, -0.030830387, -0.02606684, -0.006453501, 0.011509964, 0.014839251,
+	0.0029677744, -0.01340767, -0.021514019, -0.01615831, -0.0027590436, 0.0077849305, 0.0077866055, -0.0017650479,
+	-0.012741531, -0.01663976, -0.011049932, -0.0009828223, 0.00553052, 0.003788991, -0.004241881, -0.011919259,
+	-0.013339867, -0.007812272, -3.371926e-5, 0.0038754595, 0.0011704933, -0.00561998, -0.0109709, -0.01080447,
+	-0.0055497275, 0.00045510902, 0.002542493, -0.0006562546, -0.0063380953, -0.009930344, -0.008729845, -0.0038966262,
+	0.0006521972, 0.0014267205, -0.0019497753, -0.0066094855, -0.008833373, -0.0069837105, -0.0026722893, 0.0006545138,
+	0.00048626392, -0.0028446359, -0.006559896, -0.00771573, -0.0055022184, -0.0017722081, 0.00052783266, -0.0002956149,
+	-0.0034226258, -0.0062759696, -0.006611207, -0.0042506563, -0.0011266703, 0.00032171697, -0.00092624954, -0.0037417363,
+	-0.0058244583, -0.005550039, -0.0032064542, -0.0006831022, 7.544682e-5, -0.0014108465, -0.0038487194, -0.005260373,
+	-0.0045576133, -0.0023512335, -0.00039832512, -0.00017960396, -0.0017560627, -0.0037848554, -0.004630259, -0.0036535843,
+	-0.0016671481, -0.0002351425, -0.00041903867, -0.0019714849, -0.0035881675, -0.003973485, -0.002851503, -0.001135409,
+	-0.000161028, -0.0006249958, -0.002070001, -0.003294073, -0.003322617, -0.0021587266, -0.0007361123, -0.00014779187,
+	-0.0007857756, -0.0020674446, -0.0029351476, -0.0027034958, -0.0015768739, -0.00044855976, -0.0001716408, -0.0008953684,
+	-0.0019818055, -0.0025406047, -0.0021353746, -0.0011025276, -0.0002520731, -0.00021329604, -0.0009527345, -0.0018322156,
+	-0.0021356766, -0.0016312056, -0.00072818506, -0.00012680705, -0.000258027, -0.0009610135, -0.0016378394, -0.0017411658,
+	-0.0011981573, -0.0004433486, -5.4542703e-5, -0.00029549142, -0.0009265017, -0.0014168883, -0.0013731753, -0.0008383341,
+	-0.0002356396, -1.9307015e-5, -0.000319382, -0.000857626, -0.0011857243, -0.0010431304, -0.00054968643, -9.18727e-5,
+	-7.7925215e-6, -0.00032687315, -0.0007639199, -0.0009582015, -0.0007580153, -0.00032693724, 1.0041695e-6, -9.537413e-6,
+	-0.0003179788, -0.0006550964, -0.00074527407, -0.0005208682, -0.00016263223, 5.5166398e-5, -1.6887003e-5, -0.00029479832,
+	-0.0005402671, -0.00055483915, -0.0003314057, -4.8054877e-5, 8.1360165e-5, -2.4765002e-5, -0.00026077795, -0.0004273494,
+	-0.00039183008, -0.00018675835, 2.5916452e-5, 8.857639e-5, -3.0304902e-5, -0.00022002497, -0.00032267976, -0.00025848043,
+	-8.223082e-5, 6.815372e-5, 8.393076e-5, -3.239776e-5, -0.00017671545, -0.00023083063, -0.00015475343, -1.2028948e-5,
+	8.6750675e-5, 7.270898e-5, -3.119718e-5, -0.00013464308, -0.00015460815, -7.8831625e-5, 3.0096096e-5, 8.8701585e-5,
+	5.8547965e-5, -2.7670641e-5, -9.690609e-5, -9.519921e-5, -2.764992e-5, 5.0320363e-5, 7.97356e-5, 4.3696527e-5,
+	-2.3179251e-5, -6.573911e-5, -5.2417374e-5, 2.6079215e-6, 5.4352266e-5, 6.4294065e-5, 2.9320057e-5, -1.9153289e-5,
+	-4.2469605e-5, -2.5014393e-5, 1.6062411e-5, 4.717455e-5, 4.5612705e-5, 1.5803613e-5, -1.6856618e-5, -2.7572733e-5,
+	-1.1000957e-5, 1.6784306e-5, 3.290655e-5, 2.5874826e-5, 3.029676e-6, -1.7240909e-5, -2.079456e-5, -7.957284e-6,
+	8.531784e-6, 1.477395e-5, 6.4007763e-6, -9.391409e-6, -2.0877062e-5, -2.1309534e-5, -1.3293457e-5, -5.4185252e-6,
+	-4.8508728e-6, -1.2153002e-5, -2.1940567e-5, -2.7945149e-5, -2.7886555e-5, -2.4458315e-5, -2.2357668e-5, -2.4355511e-5,
+	-2.9607492e-5, -3.5064604e-5, -3.826325e-5, -3.90412e-5, -3.908563e-5, -4.016725e-5, -4.276928e-5, -4.609618e-5,
+	-4.9087892e-5, -5.1334035e-5, -5.3163094e-5, -5.5087563e-5, -5.729681e-5, -5.963787e-5, -6.192768e-5, -6.4157255e-5,
+]);
+pub(super) static BASS_REFLEX: (u32, &[f32]) = (44100, &[
+	3.0802534e-11, -2.8783068e-8, -1.3398184e-7, -3.6934847e-7, -7.950735e-7, -1.4345393e-6, -2.241681e-6, -3.0951544e-6,
+	-3.8315734e-6, -4.3076384e-6, -4.475083e-6, -4.436946e-6, -4.4585363e-6, -4.9181826e-6, -6.2003555e-6, -8.559551e-6,
+	-1.1994818e-5, -1.6183321e-5, -2.0506835e-5, -2.4182944e-5, -2.6477508e-5, -2.694729e-5, -2.5639001e-5, -2.3176288e-5,
+	-2.0685708e-5, -1.9557914e-5, -2.1086813e-5, -2.6075737e-5, -3.4521516e-5, -4.5481767e-5, -5.7191995e-5, -6.743629e-5,
+	-7.410205e-5, -7.578592e-5, -7.228731e-5, -6.4834814e-5, -5.5945715e-5, -4.8910093e-5, -4.699627e-5, -5.255965e-5,
+	-6.629048e-5, -8.682283e-5, -0.00011085431, -0.00013380323, -0.00015088715, -0.00015837702, -0.00015470965, -0.00014114323,
+	-0.000121729616, -0.00010254211, -9.028474e-5, -9.0591355e-5, -0.00010643546, -0.00013708342, -0.00017792138, -0.00022128565,
+	-0.0002581754, -0.0002804739, -0.00028315224, -0.00026586195, -0.00023345412, -0.00019518835, -0.00016272289, -0.00014730418,
+	-0.00015681988, -0.00019347077, -0.00025271997, -0.00032389993, -0.00039245212, -0.00044336705, -0.00046500895, -0.00045238493,
+	-0.000408959, -0.00034642944, -0.0002823705, -0.00023617726, -0.00022423326, -0.0002554835, -0.0003285855, -0.00043148163,
+	-0.0005436978, -0.0006409578, -0.00070111133, -0.00070994475, -0.0006653763, -0.00057884684, -0.0004733553, -0.00037840186,
+	-0.00032292365, -0.00032788966, -0.00040042022, -0.0005310405, -0.00069497863, -0.0008574435, -0.0009818042, -0.0010387704,
+	-0.0010143103, -0.0009141955, -0.00076380937, -0.0006029594, -0.00047669926, -0.00042424622, -0.00046867135, -0.00061006425,
+	-0.00082409166, -0.0010666612, -0.0012838733, -0.0014250649, -0.0014558071, -0.0013675822, -0.0011814687, -0.0009445589,
+	-0.00071960257, -0.0005700546, -0.0005441123, -0.00066167215, -0.00090769096, -0.0012339917, -0.0015695373, -0.00183705,
+	-0.0019721398, -0.0019402415, -0.0017469686, -0.0014389227, -0.0010941861, -0.00080438744, -0.0006523742, -0.0006908951,
+	-0.0009277134, -0.0013212555, -0.0017884433, -0.0022233895, -0.0025227978, -0.0026120758, -0.0024655543, -0.0021154762,
+	-0.0016468817, -0.0011789836, -0.0008369656, -0.00072087464, -0.00087923673, -0.0012942748, -0.0018830348, -0.0025149044,
+	-0.003042014, -0.003335434, -0.0033184395, -0.002988312, -0.0024206766, -0.001754377, -0.001159785, -0.00079765986,
+	-0.0007784171, -0.0011320399, -0.0017966762, -0.0026297155, -0.0034395927, -0.004031392, -0.004255456, -0.004046979,
+	-0.0034462647, -0.0025936165, -0.001698988, -0.0009927653, -0.0006691285, -0.00083580345, -0.0014830534, -0.002480605,
+	-0.0036045467, -0.004588891, -0.005190054, -0.0052487506, -0.0047337376, -0.0037555762, -0.0025456483, -0.0014040149,
+	-0.00062773493, -0.00043650437, -0.00091423746, -0.001981757, -0.0034089207, -0.004864985, -0.005996366, -0.006513861,
+	-0.006267762, -0.005291803, -0.0038033882, -0.00215813, -0.00076764636, 3.6414713e-7, -8.408609e-5, -0.0010493223,
+	-0.0027052495, -0.0046801483, -0.006504521, -0.0077233417, -0.008010139, -0.007254684, -0.005601174, -0.0034249877,
+	-0.0012508014, 0.00037060073, 0.0009981445, 0.00041595055, -0.0013012525, -0.0037915993, -0.006481018, -0.008714922,
+	-0.009916749, -0.009735733, -0.008146513, -0.0054735327, -0.0023299106, 0.00051861396, 0.0023352965, 0.0025973083,
+	0.0011383244, -0.001788642, -0.0055485037, -0.0092580095, -0.011989432, -0.012997925, -0.011918359, -0.008881351,
+	-0.0045148046, 0.00017681345, 0.0040342286, 0.006018826, 0.0054784976, 0.0023385729, -0.002833879, -0.008917281,
+	-0.014464819, -0.018031448, -0.018528344, -0.015522376, -0.009405071, -0.0013803067, 0.0067422125, 0.012916845,
+	0.015324727, 0.012832857, 0.0053461576, -0.0060346154, -0.019100256, -0.030867267, -0.038107835, -0.037979864,
+	-0.028634712, -0.009677604, 0.017620016, 0.050412294, 0.08467183, 0.11582097, 0.13948958, 0.15226012,
+	0.15226938, 0.13950798, 0.11584841, 0.08470821, 0.0504575, 0.017673953, -0.009615043, -0.028563632,
+	-0.037900366, -0.038020022, -0.030771244, -0.018996127, -0.005922481, 0.0054661953, 0.012960697, 0.015460267,
+	0.013059985, 0.006892851, -0.0012222693, -0.009239735, -0.015349841, -0.018348707, -0.017844813, -0.01427128,
+	-0.008716938, -0.00262683, 0.0025522325, 0.0056986697, 0.0062454143, 0.0042671375, 0.00041594676, -0.0042695412,
+	-0.008630054, -0.011661121, -0.01273484, -0.011720594, -0.008983512, -0.005268439, -0.0015031016, 0.0014292477,
+	0.0028935247, 0.0026367144, 0.0008251443, -0.002018358, -0.0051570456, -0.007825183, -0.009409646, -0.009585996,
+	-0.008379587, -0.0061411886, -0.0034473618, -0.0009526933, 0.0007687472, 0.0013550937, 0.0007316177, -0.0008857993,
+	-0.003056084, -0.0052284515, -0.006878223, -0.0076300236, -0.007339651, -0.0061173365, -0.0042895507, -0.0023113173,
+	-0.0006521343, 0.00031628011, 0.0004038299, -0.00036115758, -0.001748695, -0.0033910822, -0.004876702, -0.005849942,
+	-0.006093397, -0.0055733295, -0.004439449, -0.0029809603, -0.0015514432, -0.0004816413, -1.6972772e-6, -0.00019078728,
+	-0.00096499675, -0.002104627, -0.0033126224, -0.0042889207, -0.0048021376, -0.00474171, -0.004138883, -0.0031529404,
+	-0.002027467, -0.0010284467, -0.00037979402, -0.0002117785, -0.00053413806, -0.0012391459, -0.0021326211, -0.002984176,
+	-0.0035838594, -0.0037913686, -0.003566391, -0.0029737393, -0.002163067, -0.001329292, -0.00066397624, -0.00030973053,
+	-0.0003284065, -0.0006900204, -0.0012841556, -0.0019500533, -0.00251734, -0.0028471719, -0.002863925, -0.0025703148,
+	-0.002043067, -0.0014111097, -0.0008223115, -0.00040728613, -0.0002489861, -0.00036518683, -0.00070736324, -0.0011754673,
+	-0.0016443137, -0.0019946913, -0.0021415567, -0.0020526699, -0.0017536973, -0.0013192315, -0.000852567, -0.0004595914,
+	-0.00022338165, -0.00018551195, -0.0003382178, -0.0006287498, -0.0009742605, -0.0012831186, -0.0014772452, -0.0015100357,
+	-0.0013758752, -0.001109331, -0.0007747914, -0.00044953276, -0.00020459239, -8.814628e-5, -0.0001152372, -0.00026596896,
+	-0.0004921423, -0.00073030405, -0.00091770175, -0.0010072428, -0.0009778499, -0.00083803973, -0.0006222384, -0.0003811116,
+	-0.00016855675, -2.8665992e-5, 1.4228077e-5, -3.9784412e-5, -0.00016763262, -0.000330098, -0.0004821262, -0.0005839105,
+	-0.00061006634, -0.0005548206, -0.00043220705, -0.0002715137, -0.000109370856, 1.9429834e-5, 9.011765e-5, 9.3217444e-5,
+	3.585068e-5, -6.1012892e-5, -0.00016843664, -0.0002569193, -0.0003034613, -0.0002966221, -0.00023848284, -0.00014325572,
+	-3.309225e-5, 6.773314e-5, 0.00013874602, 0.00016788945, 0.00015382134, 0.00010548718, 3.9270904e-5, -2.5431626e-5,
+	-7.104673e-5, -8.58749e-5, -6.645161e-5, -1.7770479e-5, 4.853474e-5, 0.000117453426, 0.00017442819, 0.00020879222,
+	0.00021600835, 0.00019827776, 0.00016348888, 0.00012288828, 8.813472e-5, 6.848844e-5, 6.8800175e-5, 8.872265e-5,
+	0.00012322635, 0.00016419568, 0.00020262979, 0.00023086548, 0.00024428917, 0.00024216782, 0.00022747503, 0.00020584496,
+	0.00018398195, 0.00016795966, 0.00016183087, 0.0001668532, 0.00018146404, 0.00020193506, 0.00022348377, 0.00024152876,
+	0.00025276822, 0.0002558362, 0.0002514235, 0.00024188592, 0.00023049169, 0.00022053167, 0.00021452276, 0.00021369437,
+	0.00021784642, 0.00022557528, 0.00023476878, 0.00024321137, 0.00024913898, 0.00025160913, 0.00025061925, 0.00024697275,
+	0.00024196392, 0.00023698351, 0.00023315851, 0.00023111273, 0.00023089307, 0.0002320566, 0.00023386958, 0.00023554961,
+	0.00023647878, 0.00023633456, 0.00023511617, 0.00023307829, 0.0002306057, 0.00022807682, 0.00022575735, 0.00022375038,
+	0.00022200795, 0.00022038716, 0.0002187244, 0.00021689896, 0.00021486463, 0.00021264529, 0.00021030116, 0.00020788626,
+]);
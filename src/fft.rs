@@ -0,0 +1,171 @@
+// a small radix-2 fft kept in the crate rather than pulling in rustfft: the
+// effects only ever need power of two sizes, and this keeps the dependency list
+// to what playback itself needs
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Complex {
+	pub re: f32,
+	pub im: f32,
+}
+
+impl Complex {
+	pub const fn new(re: f32, im: f32) -> Complex {
+		return Complex {
+			re,
+			im,
+		}
+	}
+
+	pub fn from_polar(magnitude: f32, phase: f32) -> Complex {
+		return Complex::new(magnitude * phase.cos(), magnitude * phase.sin());
+	}
+
+	pub fn norm(&self) -> f32 {
+		return (self.re * self.re + self.im * self.im).sqrt();
+	}
+
+	pub fn arg(&self) -> f32 {
+		return self.im.atan2(self.re);
+	}
+
+	pub fn conj(&self) -> Complex {
+		return Complex::new(self.re, -self.im);
+	}
+}
+
+impl Add for Complex {
+	type Output = Complex;
+
+	fn add(self, other: Complex) -> Complex {
+		return Complex::new(self.re + other.re, self.im + other.im);
+	}
+}
+
+impl Sub for Complex {
+	type Output = Complex;
+
+	fn sub(self, other: Complex) -> Complex {
+		return Complex::new(self.re - other.re, self.im - other.im);
+	}
+}
+
+impl Mul for Complex {
+	type Output = Complex;
+
+	fn mul(self, other: Complex) -> Complex {
+		return Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re);
+	}
+}
+
+// in-place radix-2 fft, the buffer length has to be a power of two
+pub fn fft(buffer: &mut [Complex]) {
+	FftPlan::new(buffer.len()).fft(buffer);
+}
+
+// inverse of fft, including the 1/n scaling so ifft(fft(x)) == x
+pub fn ifft(buffer: &mut [Complex]) {
+	FftPlan::new(buffer.len()).ifft(buffer);
+}
+
+// the twiddle factors for one fft size, worked out once. anything transforming
+// frame after frame on the audio thread keeps one of these instead of calling
+// fft and ifft, which build a plan every time
+pub struct FftPlan {
+	len: usize,
+	twiddles: Vec<Complex>,
+}
+
+impl FftPlan {
+	pub fn new(len: usize) -> FftPlan {
+		let twiddles = (0..len / 2)
+			.map(|k| Complex::from_polar(1.0, -2.0 * std::f32::consts::PI * k as f32 / len as f32))
+			.collect();
+		return FftPlan {
+			len,
+			twiddles,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		return self.len;
+	}
+
+	pub fn is_empty(&self) -> bool {
+		return self.len == 0;
+	}
+
+	// the buffer has to be exactly len long
+	pub fn fft(&self, buffer: &mut [Complex]) {
+		self.transform(buffer, false);
+	}
+
+	pub fn ifft(&self, buffer: &mut [Complex]) {
+		self.transform(buffer, true);
+		let scale = 1.0 / buffer.len() as f32;
+		for value in buffer.iter_mut() {
+			value.re *= scale;
+			value.im *= scale;
+		}
+	}
+
+	fn transform(&self, buffer: &mut [Complex], inverse: bool) {
+		let len = buffer.len();
+		if len < 2 {
+			return;
+		}
+		debug_assert!(len.is_power_of_two(), "fft size has to be a power of two");
+		debug_assert_eq!(len, self.len, "buffer length doesn't match the plan");
+
+		//bit reversal permutation
+		let mut j = 0;
+		for i in 1..len {
+			let mut bit = len >> 1;
+			while j & bit != 0 {
+				j ^= bit;
+				bit >>= 1;
+			}
+			j |= bit;
+			if i < j {
+				buffer.swap(i, j);
+			}
+		}
+
+		//butterflies. a stage of size `size` uses every (len / size)th twiddle of
+		//the full length ones, conjugated for the inverse
+		let mut size = 2;
+		while size <= len {
+			let stride = len / size;
+			for start in (0..len).step_by(size) {
+				for k in 0..size / 2 {
+					let twiddle = self.twiddles[k * stride];
+					let twiddle = if inverse { twiddle.conj() } else { twiddle };
+					let even = buffer[start + k];
+					let odd = buffer[start + k + size / 2] * twiddle;
+					buffer[start + k] = even + odd;
+					buffer[start + k + size / 2] = even - odd;
+				}
+			}
+			size <<= 1;
+		}
+	}
+}
+
+// real input, complex output. shorter inputs are zero padded up to `size`
+pub fn real_fft(samples: &[f32], size: usize) -> Vec<Complex> {
+	let mut buffer: Vec<Complex> = vec![Complex::default(); size];
+	for (value, sample) in buffer.iter_mut().zip(samples.iter()) {
+		value.re = *sample;
+	}
+	fft(&mut buffer);
+	return buffer;
+}
+
+// periodic hann window, overlapping copies at half the length sum to one
+pub fn hann_window(len: usize) -> Vec<f32> {
+	let mut window: Vec<f32> = Vec::with_capacity(len);
+	for n in 0..len {
+		window.push(0.5 - 0.5 * (2.0 * std::f32::consts::PI * n as f32 / len as f32).cos());
+	}
+	return window;
+}
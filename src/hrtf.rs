@@ -0,0 +1,166 @@
+use core::time::Duration;
+use rodio::source::Source;
+use crate::effects::resample_impulse_response;
+
+// the responses, in the layout of the MIT KEMAR compact set: rings of elevations
+// from -40 to 90 degrees, each holding azimuths from 0 to 180 to the right. the
+// other side is the same responses with the ears swapped. see the header of the
+// file for where the responses in this build came from, and
+// examples/hrtf_tables.rs for regenerating it from the measured set
+mod kemar;
+
+use kemar::{AZIMUTHS, HRIR_LENGTH, HRIR_SAMPLE_RATE, HRIRS, RINGS};
+
+//the lowest and highest ring of the set, positions past them use the nearest ring
+const LOWEST_ELEVATION: f32 = -40.0;
+const HIGHEST_ELEVATION: f32 = 90.0;
+
+// left and right ear impulse responses for a source at azimuth degrees clockwise
+// from straight ahead and elevation degrees above the horizon. positions between
+// the measured ones interpolate the two nearest azimuths on the two nearest rings,
+// and the responses are resampled from 44.1 khz when the sample rate differs
+pub fn head_related_impulse_response(azimuth: f32, elevation: f32, sample_rate: u32) -> (Vec<f32>, Vec<f32>) {
+	let azimuth = azimuth.rem_euclid(360.0);
+	let elevation = elevation.clamp(LOWEST_ELEVATION, HIGHEST_ELEVATION);
+
+	let upper = RINGS.iter().position(|ring| ring.0 as f32 >= elevation).unwrap_or(RINGS.len() - 1);
+	let lower = upper.saturating_sub(1);
+	let upper_weight = if upper == lower {
+		1.0
+	} else {
+		(elevation - RINGS[lower].0 as f32) / (RINGS[upper].0 - RINGS[lower].0) as f32
+	};
+
+	let mut left = [0.0; HRIR_LENGTH];
+	let mut right = [0.0; HRIR_LENGTH];
+	add_ring(upper, azimuth, upper_weight, &mut left, &mut right);
+	if upper != lower {
+		add_ring(lower, azimuth, 1.0 - upper_weight, &mut left, &mut right);
+	}
+	return (
+		resample_impulse_response(&left, HRIR_SAMPLE_RATE, sample_rate),
+		resample_impulse_response(&right, HRIR_SAMPLE_RATE, sample_rate),
+	);
+}
+
+// adds weight times the ring's response at azimuth, interpolated between the two
+// nearest measured azimuths
+fn add_ring(ring: usize, azimuth: f32, weight: f32, left: &mut [f32], right: &mut [f32]) {
+	let (_, start, count) = RINGS[ring];
+	let azimuths = &AZIMUTHS[start..start + count];
+	//sources to the left are the right side's responses with the ears swapped
+	let (azimuth, swapped) = if azimuth > 180.0 { (360.0 - azimuth, true) } else { (azimuth, false) };
+
+	let after = azimuths.iter().position(|measured| *measured as f32 > azimuth);
+	let (before, after, after_swapped, after_azimuth) = match after {
+		Some(0) => (0, 0, swapped, azimuths[0] as f32),
+		None if count == 1 => (0, 0, swapped, 0.0),
+		Some(after) => (after - 1, after, swapped, azimuths[after] as f32),
+		//past the last azimuth of a ring that stops short of 180 the nearest one
+		//on the far side is the last one mirrored
+		None => (count - 1, count - 1, !swapped, 360.0 - azimuths[count - 1] as f32),
+	};
+	let before_azimuth = azimuths[before] as f32;
+	let after_weight = if after_azimuth > before_azimuth {
+		(azimuth - before_azimuth) / (after_azimuth - before_azimuth)
+	} else {
+		0.0
+	};
+
+	add_response(start + before, swapped, weight * (1.0 - after_weight), left, right);
+	add_response(start + after, after_swapped, weight * after_weight, left, right);
+}
+
+fn add_response(index: usize, swapped: bool, weight: f32, left: &mut [f32], right: &mut [f32]) {
+	let [measured_left, measured_right] = &HRIRS[index];
+	let (to_left, to_right) = if swapped { (measured_right, measured_left) } else { (measured_left, measured_right) };
+	for k in 0..HRIR_LENGTH {
+		left[k] += weight * to_left[k] as f32 / 32768.0;
+		right[k] += weight * to_right[k] as f32 / 32768.0;
+	}
+}
+
+// places a sound around the listener for headphones by running it through the
+// ear responses of head_related_impulse_response. azimuth is in degrees
+// clockwise from straight ahead, so 90 is the right ear, and elevation in degrees
+// from -40 below to 90 overhead. the input is mixed down to mono and the output
+// is stereo
+pub struct HrtfPanner<S: Source<Item = f32>> {
+	source: S,
+	left: Vec<f32>,
+	right: Vec<f32>,
+	history: Vec<f32>,
+	position: usize,
+	pending_right: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> HrtfPanner<S> {
+	pub fn new(source: S, azimuth: f32, elevation: f32) -> HrtfPanner<S> {
+		let (left, right) = head_related_impulse_response(azimuth, elevation, source.sample_rate());
+		return HrtfPanner {
+			history: vec![0.0; left.len()],
+			source,
+			left,
+			right,
+			position: 0,
+			pending_right: None,
+		}
+	}
+
+	// jumps to the new position, the sound already in the filters keeps playing
+	// through the new responses
+	pub fn set_position(&mut self, azimuth: f32, elevation: f32) {
+		let (left, right) = head_related_impulse_response(azimuth, elevation, self.source.sample_rate());
+		self.left = left;
+		self.right = right;
+	}
+}
+
+impl<S: Source<Item = f32>> Source for HrtfPanner<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len().map(|len| len / self.source.channels() as usize * 2);
+	}
+
+	fn channels(&self) -> u16 {
+		return 2;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for HrtfPanner<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let channels = self.source.channels();
+		let mut input = 0.0;
+		for _ in 0..channels {
+			input += self.source.next()?;
+		}
+		input /= channels as f32;
+
+		let len = self.history.len();
+		self.history[self.position] = input;
+		let (mut left, mut right) = (0.0, 0.0);
+		//tap k meets the input from k samples ago
+		for k in 0..len {
+			let sample = self.history[(self.position + len - k) % len];
+			left += self.left[k] * sample;
+			right += self.right[k] * sample;
+		}
+		self.position = (self.position + 1) % len;
+
+		self.pending_right = Some(right);
+		return Some(left);
+	}
+}
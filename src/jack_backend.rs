@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use rodio::Sink;
+
+use crate::composer::{self, NoteQueue, Playback, PlaybackHandle, PlaybackOutput, PlayError, SAMPLE_RATE, Song, SynthConfig};
+use crate::effects::{AtomicF32, Varispeed, pan_gains};
+
+// one track as the process callback plays it: its notes one after the other,
+// read at the rate the server runs at. every note source is built before the
+// client is activated and the queue keeps the finished ones, so pulling samples
+// here neither allocates nor frees. they're dropped with the client
+struct JackTrack {
+	stream: Varispeed<NoteQueue>,
+	//shared with the track, set from the PlaybackHandle
+	level: Arc<AtomicF32>,
+	finished: Arc<AtomicBool>,
+	left_gain: f32,
+	right_gain: f32,
+}
+
+impl JackTrack {
+	// adds the track's next left.len() samples to both buffers, panned. once the
+	// track runs out or is stopped it adds nothing
+	fn fill_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+		if self.finished.load(Ordering::Relaxed) {
+			return;
+		}
+		let level = self.level.load();
+		let (left_gain, right_gain) = (level * self.left_gain, level * self.right_gain);
+		for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+			let sample = match self.stream.next() {
+				Some(sample) => sample,
+				None => {
+					self.finished.store(true, Ordering::Relaxed);
+					return;
+				},
+			};
+			*left += sample * left_gain;
+			*right += sample * right_gain;
+		}
+	}
+}
+
+// every period both output ports are cleared and each track adds its block to
+// them. nothing here waits on a lock, the tracks read their controls from atomics,
+// and nothing is allocated or freed
+pub(crate) struct Process {
+	left: jack::Port<jack::AudioOut>,
+	right: jack::Port<jack::AudioOut>,
+	tracks: Vec<JackTrack>,
+}
+
+impl jack::ProcessHandler for Process {
+	fn process(&mut self, _: &jack::Client, process_scope: &jack::ProcessScope) -> jack::Control {
+		let left = self.left.as_mut_slice(process_scope);
+		let right = self.right.as_mut_slice(process_scope);
+		left.fill(0.0);
+		right.fill(0.0);
+		for track in self.tracks.iter_mut() {
+			track.fill_block(left, right);
+		}
+		return jack::Control::Continue;
+	}
+}
+
+// starts playing the song as a jack client called client_name and returns right
+// away. the output ports are called out_l and out_r and aren't connected anywhere,
+// that's left to the user's patchbay. the handle controls the song like on any
+// other backend and PlaybackHandle::jack_client_name tells what jack called it
+pub fn play_song_jack(song: Song, config: &SynthConfig, client_name: &str) -> Result<Playback, PlayError> {
+	let (client, _status) = jack::Client::new(client_name, jack::ClientOptions::NO_START_SERVER)?;
+	let left = client.register_port("out_l", jack::AudioOut)?;
+	let right = client.register_port("out_r", jack::AudioOut)?;
+
+	let base_tempo = song.sections.first()
+		.and_then(|section| section.tracks.first())
+		.map(|track| track.tempo as f32)
+		.unwrap_or(0.0);
+	let prototracks = song.flatten();
+	//the sinks are never played, the notes go straight to the process callback
+	let sinks = prototracks.iter().map(|_| Sink::new_idle().0).collect();
+	let (mut tracks, queues) = composer::build_tracks(&prototracks, sinks, config);
+
+	//the notes are made at SAMPLE_RATE, played faster or slower to match the server
+	let rate_ratio = SAMPLE_RATE as f32 / client.sample_rate() as f32;
+	let mut jack_tracks: Vec<JackTrack> = Vec::with_capacity(tracks.len());
+	for (track, queue) in tracks.iter_mut().zip(queues) {
+		let finished = Arc::new(AtomicBool::new(false));
+		track.finished = Some(finished.clone());
+		let (left_gain, right_gain) = pan_gains(track.pan);
+		jack_tracks.push(JackTrack {
+			stream: Varispeed::new(NoteQueue::new(queue), Arc::new(AtomicF32::new(rate_ratio))),
+			level: track.level.clone(),
+			finished,
+			left_gain,
+			right_gain,
+		});
+	}
+
+	let name = client.name().to_string();
+	let client = client.activate_async((), Process {
+		left,
+		right,
+		tracks: jack_tracks,
+	})?;
+
+	let handle = PlaybackHandle::with_jack_client(tracks, base_tempo, &name);
+	if config.print_playback {
+		handle.print_while_playing();
+	}
+	return Ok(Playback::new(PlaybackOutput::Jack { _client: client }, handle));
+}
+
+// every physical playback port of the running jack server, these are what the
+// client's outputs usually get connected to
+pub fn list_playback_ports() -> Vec<String> {
+	return match jack::Client::new("rodio-synth-probe", jack::ClientOptions::NO_START_SERVER) {
+		Ok((client, _status)) => client.ports(None, Some(jack::jack_sys::FLOAT_MONO_AUDIO), jack::PortFlags::IS_INPUT | jack::PortFlags::IS_PHYSICAL),
+		Err(_) => Vec::new(),
+	};
+}
@@ -0,0 +1,22 @@
+#![allow(clippy::needless_return)]
+
+pub mod analysis;
+pub mod composer;
+pub mod composition;
+pub mod effects;
+pub mod fft;
+#[cfg(feature = "hrtf")]
+pub mod hrtf;
+#[cfg(feature = "jack")]
+pub mod jack_backend;
+pub mod midi;
+pub mod modulation;
+pub mod osc;
+pub mod oscillators;
+pub mod patch;
+pub mod patch_library;
+pub mod plugin;
+pub mod render;
+pub mod synthdef;
+pub mod theory;
+pub mod wavetable;
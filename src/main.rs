@@ -1,59 +1,34 @@
-pub mod composer;
+use rodio_synth::composer;
+use rodio_synth::composition::melody_from_scale_degrees;
+use rodio_synth::theory::{Mode, Scale};
 
 use composer::*;
 
 fn main() {
 
-	let triangle_notes: Vec<Note> = vec![
-		Note {
-			pitch: 261.63,
-			duration: 0.5,
-		},
-		Note {
-			pitch: 293.66,
-			duration: 0.5,
-		},
-		Note {
-			pitch: 329.63,
-			duration: 0.5,
-		},
-		Note {
-			pitch: 349.23,
-			duration: 0.5,
-		},
-		Note {
-			pitch: 329.63,
-			duration: 0.5,
-		},
-		Note {
-			pitch: 293.66,
-			duration: 0.5,
-		},
-	];
+	let c_major = Scale::new(261.63, Mode::Major);
+	let triangle_notes: Vec<Note> = melody_from_scale_degrees(&c_major, &[1, 2, 3, 4, 3, 2], 0.5);
 
 	let sine_notes: Vec<Note> = vec![
-		Note {
-			pitch: 261.63,
-			duration: 1.5,
-		},
-		Note {
-			pitch: 293.66,
-			duration: 1.5,
-		},	
+		Note::new(261.63, 1.5),
+		Note::new(293.66, 1.5),	
 	];
 
 	let prototracks: Vec<ProtoTrack> = vec![
 		ProtoTrack {
-			instrument: Instruments::Triangle,
 			notes: triangle_notes,
 			tempo: 40,
+			..ProtoTrack::new(Instruments::Triangle)
 		},
 		ProtoTrack {
-			instrument: Instruments::Sine,
 			notes: sine_notes,
 			tempo: 40,
+			..ProtoTrack::new(Instruments::Sine)
 		},
 	];
 
-	println!("{}", composer::play_song(prototracks).unwrap());
+	//this is the end of the line, there's nothing better to do without an audio device
+	//than to stop with the error
+	composer::play_song(Song::from_prototracks(prototracks), &SynthConfig::default()).unwrap();
+	println!("👍");
 }
\ No newline at end of file
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::composer::{Instruments, ProtoTrack};
+use crate::patch::Waveform;
+use crate::theory;
+
+//ticks per quarter note
+const TICKS_PER_BEAT: u16 = 480;
+//general midi puts drums on channel 10
+const DRUM_CHANNEL: u8 = 9;
+const DEFAULT_BPM: u32 = 120;
+
+#[derive(Debug)]
+pub enum MidiError {
+	Io(io::Error),
+}
+
+impl fmt::Display for MidiError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			MidiError::Io(error) => write!(f, "couldn't write midi file: {}", error),
+		};
+	}
+}
+
+impl std::error::Error for MidiError {}
+
+impl From<io::Error> for MidiError {
+	fn from(error: io::Error) -> MidiError {
+		return MidiError::Io(error);
+	}
+}
+
+// the general midi program (0 based) closest to each instrument, or None for drums
+fn program(instrument: &Instruments) -> Option<u8> {
+	return match instrument {
+		Instruments::Sine => Some(79),
+		Instruments::Saw => Some(81),
+		Instruments::Square => Some(80),
+		Instruments::Triangle => Some(74),
+		Instruments::Snare | Instruments::Kick => None,
+		Instruments::Custom(patch) => Some(waveform_program(patch.waveform)),
+		Instruments::Am { .. } => Some(88),
+		Instruments::SubSaw(_) => Some(38),
+		Instruments::DualOsc { osc1_type, .. } => Some(waveform_program(*osc1_type)),
+	};
+}
+
+fn waveform_program(waveform: Waveform) -> u8 {
+	return match waveform {
+		Waveform::Sine => 79,
+		Waveform::Saw => 81,
+		Waveform::Square => 80,
+		Waveform::Triangle => 74,
+		Waveform::Noise => 122,
+	};
+}
+
+// drums play one fixed general midi drum instead of the note's pitch
+fn drum_note(instrument: &Instruments) -> Option<u8> {
+	return match instrument {
+		Instruments::Snare => Some(38),
+		Instruments::Kick => Some(36),
+		_ => None,
+	};
+}
+
+fn write_variable_length(bytes: &mut Vec<u8>, value: u32) {
+	let mut groups = vec![(value & 0x7f) as u8];
+	let mut rest = value >> 7;
+	while rest > 0 {
+		groups.push((rest & 0x7f) as u8 | 0x80);
+		rest >>= 7;
+	}
+	bytes.extend(groups.iter().rev());
+}
+
+fn write_chunk(file: &mut File, id: &[u8; 4], data: &[u8]) -> io::Result<()> {
+	file.write_all(id)?;
+	file.write_all(&(data.len() as u32).to_be_bytes())?;
+	return file.write_all(data);
+}
+
+// a type 1 file: a conductor track holding the tempo, then one track per prototrack.
+// midi has a single tempo for the whole file, so the first track's tempo is used
+// and tracks at other tempos are converted so they keep their timing in seconds.
+// notes go to the nearest midi note, rests and notes that might not play
+// (play_probability) are kept as written
+pub fn save_midi(prototracks: &[ProtoTrack], path: &Path) -> Result<(), MidiError> {
+	let bpm = prototracks.iter().map(|proto| proto.tempo).find(|tempo| *tempo > 0).unwrap_or(DEFAULT_BPM);
+	let ticks_per_second = bpm as f32 / 60.0 * TICKS_PER_BEAT as f32;
+
+	let mut file = File::create(path)?;
+	let mut header: Vec<u8> = Vec::new();
+	header.extend_from_slice(&1_u16.to_be_bytes());
+	header.extend_from_slice(&(prototracks.len() as u16 + 1).to_be_bytes());
+	header.extend_from_slice(&TICKS_PER_BEAT.to_be_bytes());
+	write_chunk(&mut file, b"MThd", &header)?;
+
+	let mut conductor: Vec<u8> = Vec::new();
+	let microseconds_per_beat = 60_000_000 / bpm;
+	write_variable_length(&mut conductor, 0);
+	conductor.extend_from_slice(&[0xff, 0x51, 0x03]);
+	conductor.extend_from_slice(&microseconds_per_beat.to_be_bytes()[1..]);
+	write_variable_length(&mut conductor, 0);
+	conductor.extend_from_slice(&[0xff, 0x2f, 0x00]);
+	write_chunk(&mut file, b"MTrk", &conductor)?;
+
+	let mut melodic_channel = 0;
+	for proto in prototracks.iter() {
+		let channel = match program(&proto.instrument) {
+			Some(_) => {
+				let channel = melodic_channel;
+				melodic_channel = (melodic_channel + 1) % 16;
+				if melodic_channel == DRUM_CHANNEL {
+					melodic_channel += 1;
+				}
+				channel
+			},
+			None => DRUM_CHANNEL,
+		};
+
+		let mut track: Vec<u8> = Vec::new();
+		if let Some(program) = program(&proto.instrument) {
+			write_variable_length(&mut track, 0);
+			track.extend_from_slice(&[0xc0 | channel, program]);
+		}
+
+		//times are kept in seconds and rounded to ticks only when written so rounding
+		//errors don't add up over a long track
+		let seconds_per_beat = if proto.tempo > 0 { 60.0 / proto.tempo as f32 } else { 0.0 };
+		let mut time = 0.0;
+		let mut last_tick = 0;
+		for note in proto.notes.iter() {
+			let start = (time * ticks_per_second).round() as u32;
+			time += note.duration * seconds_per_beat;
+			let end = (time * ticks_per_second).round() as u32;
+			if note.is_rest() || end <= start {
+				continue;
+			}
+
+			//every pitch of a chord starts and stops together
+			let keys: Vec<u8> = match drum_note(&proto.instrument) {
+				Some(key) => vec![key],
+				None => note.pitches().map(theory::freq_to_midi_note).collect(),
+			};
+			let velocity = (note.velocity.clamp(0.0, 1.0) * 127.0).round().max(1.0) as u8;
+			for (n, key) in keys.iter().enumerate() {
+				write_variable_length(&mut track, if n == 0 { start - last_tick } else { 0 });
+				track.extend_from_slice(&[0x90 | channel, *key, velocity]);
+			}
+			for (n, key) in keys.iter().enumerate() {
+				write_variable_length(&mut track, if n == 0 { end - start } else { 0 });
+				track.extend_from_slice(&[0x80 | channel, *key, 0]);
+			}
+			last_tick = end;
+		}
+		write_variable_length(&mut track, 0);
+		track.extend_from_slice(&[0xff, 0x2f, 0x00]);
+		write_chunk(&mut file, b"MTrk", &track)?;
+	}
+
+	return Ok(());
+}
@@ -0,0 +1,670 @@
+use core::time::Duration;
+use std::sync::Arc;
+use rodio::source::Source;
+
+use crate::effects::AtomicF32;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LfoShape {
+	Sine,
+	Triangle,
+	Square,
+	Saw,
+}
+
+impl LfoShape {
+	// value of the shape from -1.0 to 1.0, phase in radians. every shape starts at
+	// zero or its top and rises first so they line up with the sine
+	pub fn value(&self, phase: f32) -> f32 {
+		let position = (phase / (2.0 * std::f32::consts::PI)).rem_euclid(1.0);
+		return match self {
+			LfoShape::Sine => phase.sin(),
+			LfoShape::Triangle => {
+				if position < 0.25 {
+					4.0 * position
+				} else if position < 0.75 {
+					2.0 - 4.0 * position
+				} else {
+					4.0 * position - 4.0
+				}
+			},
+			LfoShape::Square => if position < 0.5 { 1.0 } else { -1.0 },
+			LfoShape::Saw => 2.0 * (position + 0.5).fract() - 1.0,
+		};
+	}
+}
+
+//every measure is taken to be in 4/4
+const BEATS_PER_MEASURE: f32 = 4.0;
+
+// a note length to lock an lfo cycle to. Triplet fits three cycles in the time of
+// two of the division it wraps
+#[derive(Clone, Debug, PartialEq)]
+pub enum BeatDiv {
+	WholeNote,
+	Half,
+	Quarter,
+	Eighth,
+	Sixteenth,
+	Triplet(Box<BeatDiv>),
+}
+
+impl BeatDiv {
+	// length of the division in quarter note beats
+	pub fn beats(&self) -> f32 {
+		return match self {
+			BeatDiv::WholeNote => 4.0,
+			BeatDiv::Half => 2.0,
+			BeatDiv::Quarter => 1.0,
+			BeatDiv::Eighth => 0.5,
+			BeatDiv::Sixteenth => 0.25,
+			BeatDiv::Triplet(division) => division.beats() * 2.0 / 3.0,
+		};
+	}
+}
+
+// how fast an lfo runs, either free in hz or one cycle per note length at the
+// song's tempo
+#[derive(Clone, Debug, PartialEq)]
+pub enum LfoRate {
+	Hz(f32),
+	BeatDivision(BeatDiv),
+}
+
+impl LfoRate {
+	pub fn hz(&self, bpm: u32) -> f32 {
+		return match self {
+			LfoRate::Hz(rate) => *rate,
+			LfoRate::BeatDivision(division) => bpm as f32 / 60.0 / division.beats(),
+		};
+	}
+}
+
+// a low frequency oscillator used to modulate other parameters over time.
+// rate is in hz, depth is the peak amount of modulation and phase is in radians.
+// the lfo doesn't know what it's modulating, the caller decides how to interpret
+// the value (for oscillators it's the pitch offset in semitones).
+// with beats_per_cycle set the rate follows bpm instead, and sync starts the cycle
+// over at every measure. both need bpm, which play_song fills in from the track.
+// retrigger starts the cycle over at retrigger_phase (radians) on every note, like
+// key sync on an analog synth
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lfo {
+	pub rate: f32,
+	pub depth: f32,
+	pub phase: f32,
+	pub shape: LfoShape,
+	pub beats_per_cycle: Option<f32>,
+	pub bpm: u32,
+	pub sync: bool,
+	pub retrigger: bool,
+	pub retrigger_phase: f32,
+	//samples since the last measure started
+	measure_position: f32,
+}
+
+impl Lfo {
+	pub const fn new(rate: f32, depth: f32) -> Lfo {
+		return Lfo {
+			rate,
+			depth,
+			phase: 0.0,
+			shape: LfoShape::Sine,
+			beats_per_cycle: None,
+			bpm: 0,
+			sync: false,
+			retrigger: false,
+			retrigger_phase: 0.0,
+			measure_position: 0.0,
+		}
+	}
+
+	// called when a note starts, only does anything with retrigger on
+	pub fn note_on(&mut self) {
+		if self.retrigger {
+			self.phase = self.retrigger_phase;
+		}
+	}
+
+	// an lfo running at `rate`. tempo locked rates are kept in beats so they follow
+	// bpm if it changes later
+	pub fn with_rate(rate: &LfoRate, bpm: u32, depth: f32) -> Lfo {
+		let mut lfo = Lfo::new(rate.hz(bpm), depth);
+		lfo.bpm = bpm;
+		lfo.set_rate(rate);
+		return lfo;
+	}
+
+	pub fn set_rate(&mut self, rate: &LfoRate) {
+		match rate {
+			LfoRate::Hz(hz) => {
+				self.rate = *hz;
+				self.beats_per_cycle = None;
+			},
+			LfoRate::BeatDivision(division) => {
+				self.rate = rate.hz(self.bpm);
+				self.beats_per_cycle = Some(division.beats());
+			},
+		}
+	}
+
+	// the rate in hz the lfo is running at right now
+	pub fn rate_hz(&self) -> f32 {
+		return match self.beats_per_cycle {
+			Some(beats) if self.bpm > 0 => self.bpm as f32 / 60.0 / beats,
+			_ => self.rate,
+		};
+	}
+
+	pub fn next_value(&mut self, sample_rate: u32) -> f32 {
+		if self.sync && self.bpm > 0 {
+			let measure_length = sample_rate as f32 * BEATS_PER_MEASURE * 60.0 / self.bpm as f32;
+			if self.measure_position >= measure_length {
+				self.measure_position -= measure_length;
+				self.phase = 0.0;
+			}
+			self.measure_position += 1.0;
+		}
+		let value = self.shape.value(self.phase) * self.depth;
+		self.phase += 2.0 * std::f32::consts::PI * self.rate_hz() / sample_rate as f32;
+		self.phase %= 2.0 * std::f32::consts::PI;
+		return value;
+	}
+}
+
+// follows the level of a signal, e.g. to open a filter the harder a part is
+// played. attack and release are how long the level takes to move most of the
+// way up or down. with retrigger every note starts again from silence
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EnvelopeFollower {
+	pub attack: Duration,
+	pub release: Duration,
+	pub retrigger: bool,
+	level: f32,
+}
+
+impl EnvelopeFollower {
+	pub fn new(attack: Duration, release: Duration) -> EnvelopeFollower {
+		return EnvelopeFollower {
+			attack,
+			release,
+			retrigger: false,
+			level: 0.0,
+		}
+	}
+
+	pub fn note_on(&mut self) {
+		if self.retrigger {
+			self.level = 0.0;
+		}
+	}
+
+	pub fn level(&self) -> f32 {
+		return self.level;
+	}
+
+	// feeds one input sample in and returns the level after it
+	pub fn next_value(&mut self, input: f32, sample_rate: u32) -> f32 {
+		let input = input.abs();
+		let time = if input > self.level { self.attack } else { self.release };
+		let samples = time.as_secs_f32() * sample_rate as f32;
+		let coefficient = if samples > 0.0 { (-1.0 / samples).exp() } else { 0.0 };
+		self.level = input + coefficient * (self.level - input);
+		return self.level;
+	}
+}
+
+// a new random value from -depth to depth rate times a second, held in between.
+// with retrigger every note picks a new value and starts its hold time over
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SampleHold {
+	pub rate: f32,
+	pub depth: f32,
+	pub retrigger: bool,
+	//position in the hold time, a new value is picked when it reaches 1.0
+	position: f32,
+	value: f32,
+	//xorshift state, the rng lives in the struct so it can stay Copy like Lfo
+	seed: u32,
+}
+
+impl SampleHold {
+	pub fn new(rate: f32, depth: f32, seed: u32) -> SampleHold {
+		let mut sample_hold = SampleHold {
+			rate,
+			depth,
+			retrigger: false,
+			position: 0.0,
+			value: 0.0,
+			seed: seed.max(1),
+		};
+		sample_hold.pick();
+		return sample_hold;
+	}
+
+	pub fn note_on(&mut self) {
+		if self.retrigger {
+			self.position = 0.0;
+			self.pick();
+		}
+	}
+
+	pub fn next_value(&mut self, sample_rate: u32) -> f32 {
+		if self.position >= 1.0 {
+			self.position -= 1.0;
+			self.pick();
+		}
+		self.position += self.rate / sample_rate as f32;
+		return self.value * self.depth;
+	}
+
+	fn pick(&mut self) {
+		self.seed ^= self.seed << 13;
+		self.seed ^= self.seed >> 17;
+		self.seed ^= self.seed << 5;
+		self.value = self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0;
+	}
+}
+
+// anything a Vca can be modulated by. it's read from the audio thread, so it
+// has to be cheap and can't block
+pub trait ModSource: Send + Sync {
+	fn current_value(&self) -> f32;
+}
+
+// a value another thread sets, like a knob or an osc message
+impl ModSource for AtomicF32 {
+	fn current_value(&self) -> f32 {
+		return self.load();
+	}
+}
+
+// the level of an oscillator. with a modulation source the level swings around
+// base_level by mod_depth times the source's value, a value of 1.0 with depth
+// 1.0 doubles it. the result stays between 0.0 and 1.0
+#[derive(Clone)]
+pub struct Vca {
+	pub base_level: f32,
+	pub modulation: Option<Arc<dyn ModSource>>,
+	pub mod_depth: f32,
+}
+
+impl Vca {
+	pub fn new(base_level: f32) -> Vca {
+		return Vca {
+			base_level,
+			modulation: None,
+			mod_depth: 0.0,
+		}
+	}
+
+	pub fn amplitude(&self) -> f32 {
+		let modulation = match &self.modulation {
+			Some(source) => source.current_value() * self.mod_depth,
+			None => 0.0,
+		};
+		return (self.base_level * (1.0 + modulation)).clamp(0.0, 1.0);
+	}
+}
+
+impl Default for Vca {
+	fn default() -> Vca {
+		return Vca::new(1.0);
+	}
+}
+
+// where a performance control or other modulation source ends up. Pitch depth is
+// in semitones, Volume depth is the gain added on top of the note's own.
+// FilterCutoff puts a low pass at base_hz on the voice and its depth is in octaves
+// above that. VibratoDepth depth is in cents added to the note's vibrato, notes
+// without one get a vibrato at VIBRATO_TARGET_RATE_HZ that starts right away
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ModTarget {
+	Pitch,
+	Volume,
+	FilterCutoff { base_hz: f32 },
+	VibratoDepth,
+}
+
+pub const VIBRATO_TARGET_RATE_HZ: f32 = 5.5;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModSourceId {
+	Lfo1,
+	Lfo2,
+	Env1,
+	Env2,
+	Velocity,
+	Note,
+}
+
+// what a ModMatrix route can change. the depths are in the destination's own
+// units: semitones for OscFrequency, gain added to 1.0 for OscAmplitude, octaves for
+// FilterCutoff, q added for FilterResonance and -1.0 to 1.0 for PanPosition
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModDestId {
+	OscFrequency,
+	OscAmplitude,
+	FilterCutoff,
+	FilterResonance,
+	PanPosition,
+}
+
+// the value of every modulation source at one sample. lfos swing from -1.0 to 1.0,
+// envelopes and velocity go from 0.0 to 1.0 and note is the midi note number over
+// 127, for keyboard tracking
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ModSources {
+	pub lfo1: f32,
+	pub lfo2: f32,
+	pub env1: f32,
+	pub env2: f32,
+	pub velocity: f32,
+	pub note: f32,
+}
+
+impl ModSources {
+	pub fn value(&self, source: ModSourceId) -> f32 {
+		return match source {
+			ModSourceId::Lfo1 => self.lfo1,
+			ModSourceId::Lfo2 => self.lfo2,
+			ModSourceId::Env1 => self.env1,
+			ModSourceId::Env2 => self.env2,
+			ModSourceId::Velocity => self.velocity,
+			ModSourceId::Note => self.note,
+		};
+	}
+}
+
+// how much each destination is moved, the sum of every route into it
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ModDestinations {
+	pub osc_frequency: f32,
+	pub osc_amplitude: f32,
+	pub filter_cutoff: f32,
+	pub filter_resonance: f32,
+	pub pan_position: f32,
+}
+
+impl ModDestinations {
+	pub fn value(&self, destination: ModDestId) -> f32 {
+		return match destination {
+			ModDestId::OscFrequency => self.osc_frequency,
+			ModDestId::OscAmplitude => self.osc_amplitude,
+			ModDestId::FilterCutoff => self.filter_cutoff,
+			ModDestId::FilterResonance => self.filter_resonance,
+			ModDestId::PanPosition => self.pan_position,
+		};
+	}
+
+	fn add(&mut self, destination: ModDestId, amount: f32) {
+		match destination {
+			ModDestId::OscFrequency => self.osc_frequency += amount,
+			ModDestId::OscAmplitude => self.osc_amplitude += amount,
+			ModDestId::FilterCutoff => self.filter_cutoff += amount,
+			ModDestId::FilterResonance => self.filter_resonance += amount,
+			ModDestId::PanPosition => self.pan_position += amount,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ModRoute {
+	pub source: ModSourceId,
+	pub destination: ModDestId,
+	pub depth: f32,
+}
+
+// any source to any destination: every route adds source * depth to its
+// destination, so several sources can move one destination and one source can
+// move several. see oscillators::ModMatrixVoice for a voice wired up to it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModMatrix {
+	routes: Vec<ModRoute>,
+}
+
+impl ModMatrix {
+	pub fn new() -> ModMatrix {
+		return ModMatrix::default();
+	}
+
+	pub fn add_route(&mut self, source: ModSourceId, destination: ModDestId, depth: f32) {
+		self.routes.push(ModRoute {
+			source,
+			destination,
+			depth,
+		});
+	}
+
+	pub fn routes(&self) -> &[ModRoute] {
+		return &self.routes;
+	}
+
+	pub fn evaluate(&self, sources: &ModSources) -> ModDestinations {
+		let mut destinations = ModDestinations::default();
+		for route in self.routes.iter() {
+			destinations.add(route.destination, sources.value(route.source) * route.depth);
+		}
+		return destinations;
+	}
+}
+
+// pressure on a held note from 0.0 to 1.0, scaled by depth and sent to target
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AftertouchRouting {
+	pub target: ModTarget,
+	pub depth: f32,
+}
+
+impl AftertouchRouting {
+	pub const fn new(target: ModTarget, depth: f32) -> AftertouchRouting {
+		return AftertouchRouting {
+			target,
+			depth,
+		}
+	}
+}
+
+// pressure changing partway through a note: offset is in beats from the start of
+// the note at note_index. the pressure holds until the next event for that note
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AftertouchEvent {
+	pub note_index: usize,
+	pub offset: f32,
+	pub pressure: f32,
+}
+
+impl AftertouchEvent {
+	pub const fn new(note_index: usize, offset: f32, pressure: f32) -> AftertouchEvent {
+		return AftertouchEvent {
+			note_index,
+			offset,
+			pressure,
+		}
+	}
+}
+
+// how a wind player's breath pressure (0.0 to 1.0) changes the note: at full
+// pressure the pitch is pitch_deviation_cents sharp, and the gain is
+// pressure ^ volume_exponent so above 1.0 soft breaths fade faster
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BreathCurve {
+	pub pitch_deviation_cents: f32,
+	pub volume_exponent: f32,
+}
+
+impl BreathCurve {
+	pub const fn new(pitch_deviation_cents: f32, volume_exponent: f32) -> BreathCurve {
+		return BreathCurve {
+			pitch_deviation_cents,
+			volume_exponent,
+		}
+	}
+
+	// (pitch offset in cents, gain) for a pressure
+	pub fn response(&self, pressure: f32) -> (f32, f32) {
+		let pressure = pressure.clamp(0.0, 1.0);
+		return (self.pitch_deviation_cents * pressure, pressure.powf(self.volume_exponent));
+	}
+}
+
+impl Default for BreathCurve {
+	fn default() -> BreathCurve {
+		return BreathCurve::new(10.0, 1.5);
+	}
+}
+
+// breath pressure changing during a note, offset is in beats from the start of the
+// note at note_index. the pressure glides in a straight line between events
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BreathEvent {
+	pub note_index: usize,
+	pub offset: f32,
+	pub pressure: f32,
+}
+
+impl BreathEvent {
+	pub const fn new(note_index: usize, offset: f32, pressure: f32) -> BreathEvent {
+		return BreathEvent {
+			note_index,
+			offset,
+			pressure,
+		}
+	}
+}
+
+// the pitch wheel at `beat` of the track, from -1.0 (all the way down) to 1.0.
+// the wheel glides in a straight line from one event to the next
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PitchBendEvent {
+	pub beat: f32,
+	pub value: f32,
+}
+
+impl PitchBendEvent {
+	pub const fn new(beat: f32, value: f32) -> PitchBendEvent {
+		return PitchBendEvent {
+			beat,
+			value,
+		}
+	}
+}
+
+// a singer's vibrato on a single note: the pitch swings depth_cents either way
+// rate_hz times a second, starting only once the note has been held for `delay`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vibrato {
+	pub rate_hz: f32,
+	pub depth_cents: f32,
+	pub delay: Duration,
+}
+
+impl Vibrato {
+	pub const fn new(rate_hz: f32, depth_cents: f32, delay: Duration) -> Vibrato {
+		return Vibrato {
+			rate_hz,
+			depth_cents,
+			delay,
+		}
+	}
+
+	// pitch offset in semitones `time` seconds into the note. the swing starts from
+	// the note's own pitch so there's no jump when it kicks in
+	pub fn pitch_offset(&self, time: f32) -> f32 {
+		let vibrato_time = time - self.delay.as_secs_f32();
+		if vibrato_time <= 0.0 {
+			return 0.0;
+		}
+		return (2.0 * std::f32::consts::PI * self.rate_hz * vibrato_time).sin() * self.depth_cents / 100.0;
+	}
+}
+
+// attack, decay and release are in seconds, sustain is the level held
+// between the end of decay and the start of release
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Adsr {
+	pub attack: f32,
+	pub decay: f32,
+	pub sustain: f32,
+	pub release: f32,
+}
+
+impl Adsr {
+	pub const fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Adsr {
+		return Adsr {
+			attack,
+			decay,
+			sustain,
+			release,
+		}
+	}
+
+	// shapes a single note: the release phase is fitted inside the note's duration
+	// so the next note in the sink starts on time
+	pub fn apply<S: Source<Item = f32>>(self, source: S, note_duration: Duration) -> AdsrEnvelope<S> {
+		return AdsrEnvelope::new(source, self, note_duration);
+	}
+
+	pub(crate) fn level_at(&self, time: f32, note_length: f32) -> f32 {
+		let release_start = (note_length - self.release).max(0.0);
+		let held = if time < self.attack {
+			time / self.attack
+		} else if time < self.attack + self.decay {
+			1.0 - (1.0 - self.sustain) * (time - self.attack) / self.decay
+		} else {
+			self.sustain
+		};
+
+		if time >= release_start && self.release > 0.0 {
+			return held * ((note_length - time) / self.release).max(0.0);
+		}
+		return held;
+	}
+}
+
+pub struct AdsrEnvelope<S: Source<Item = f32>> {
+	source: S,
+	adsr: Adsr,
+	note_length: f32,
+	elapsed_samples: u64,
+}
+
+impl<S: Source<Item = f32>> AdsrEnvelope<S> {
+	fn new(source: S, adsr: Adsr, note_duration: Duration) -> AdsrEnvelope<S> {
+		return AdsrEnvelope {
+			source,
+			adsr,
+			note_length: note_duration.as_secs_f32(),
+			elapsed_samples: 0,
+		}
+	}
+}
+
+impl<S: Source<Item = f32>> Source for AdsrEnvelope<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		return self.source.current_frame_len();
+	}
+
+	fn channels(&self) -> u16 {
+		return self.source.channels();
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.source.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return self.source.total_duration();
+	}
+}
+
+impl<S: Source<Item = f32>> Iterator for AdsrEnvelope<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let sample = self.source.next()?;
+		let time = self.elapsed_samples as f32 / (self.source.sample_rate() as f32 * self.source.channels() as f32);
+		self.elapsed_samples += 1;
+		return Some(sample * self.adsr.level_at(time, self.note_length));
+	}
+}
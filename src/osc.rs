@@ -0,0 +1,152 @@
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rosc::{OscMessage, OscPacket, OscType};
+
+use crate::composer::PlaybackHandle;
+
+//how often the receiving thread wakes up to check whether it should stop
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(100);
+//largest udp datagram
+const MAX_PACKET_SIZE: usize = 65_536;
+
+#[derive(Debug)]
+pub enum OscError {
+	Io(io::Error),
+}
+
+impl fmt::Display for OscError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			OscError::Io(err) => write!(f, "osc socket error: {}", err),
+		};
+	}
+}
+
+impl std::error::Error for OscError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		return match self {
+			OscError::Io(err) => Some(err),
+		};
+	}
+}
+
+impl From<io::Error> for OscError {
+	fn from(err: io::Error) -> OscError {
+		return OscError::Io(err);
+	}
+}
+
+// listens for osc messages on a udp socket and passes them on to a playing song:
+//   /track/N/volume  float or int, 1.0 is the volume the track was written with
+//   /track/N/mute    bool, int or float, anything but zero or false mutes
+//   /synth/tempo     float or int, in bpm
+// anything else is ignored. the socket is closed when the controller is dropped
+pub struct OscController {
+	local_addr: SocketAddr,
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl OscController {
+	pub fn start(addr: SocketAddr, handle: PlaybackHandle) -> Result<OscController, OscError> {
+		let socket = UdpSocket::bind(addr)?;
+		socket.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+		let local_addr = socket.local_addr()?;
+		let stop = Arc::new(AtomicBool::new(false));
+
+		let thread_stop = stop.clone();
+		let thread = thread::spawn(move || {
+			let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+			while !thread_stop.load(Ordering::Relaxed) {
+				let size = match socket.recv_from(&mut buffer) {
+					Ok((size, _)) => size,
+					//timeouts and bad datagrams, keep listening
+					Err(_) => continue,
+				};
+				if let Ok((_, packet)) = rosc::decoder::decode_udp(&buffer[..size]) {
+					dispatch(&packet, &handle);
+				}
+			}
+		});
+
+		return Ok(OscController {
+			local_addr,
+			stop,
+			thread: Some(thread),
+		});
+	}
+
+	// the address the socket ended up on, useful when started on port 0
+	pub fn local_addr(&self) -> SocketAddr {
+		return self.local_addr;
+	}
+}
+
+impl Drop for OscController {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+fn dispatch(packet: &OscPacket, handle: &PlaybackHandle) {
+	match packet {
+		OscPacket::Message(message) => dispatch_message(message, handle),
+		//the timetag is ignored, bundled messages are applied as soon as they arrive
+		OscPacket::Bundle(bundle) => {
+			for packet in bundle.content.iter() {
+				dispatch(packet, handle);
+			}
+		},
+	}
+}
+
+fn dispatch_message(message: &OscMessage, handle: &PlaybackHandle) {
+	let parts: Vec<&str> = message.addr.trim_start_matches('/').split('/').collect();
+	let argument = message.args.first();
+	match parts.as_slice() {
+		["track", index, "volume"] => {
+			if let (Ok(index), Some(volume)) = (index.parse::<usize>(), argument.and_then(as_float)) {
+				handle.set_track_volume(index, volume);
+			}
+		},
+		["track", index, "mute"] => {
+			if let (Ok(index), Some(muted)) = (index.parse::<usize>(), argument.and_then(as_bool)) {
+				handle.set_track_muted(index, muted);
+			}
+		},
+		["synth", "tempo"] => {
+			if let Some(bpm) = argument.and_then(as_float) {
+				handle.set_tempo(bpm);
+			}
+		},
+		_ => {},
+	}
+}
+
+fn as_float(argument: &OscType) -> Option<f32> {
+	return match argument {
+		OscType::Float(value) => Some(*value),
+		OscType::Double(value) => Some(*value as f32),
+		OscType::Int(value) => Some(*value as f32),
+		_ => None,
+	};
+}
+
+//touchosc style toggles send 0.0 and 1.0, others send ints or osc booleans
+fn as_bool(argument: &OscType) -> Option<bool> {
+	return match argument {
+		OscType::Bool(value) => Some(*value),
+		OscType::Int(value) => Some(*value != 0),
+		OscType::Float(value) => Some(*value != 0.0),
+		_ => None,
+	};
+}
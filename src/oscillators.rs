@@ -0,0 +1,1121 @@
+use core::time::Duration;
+use std::sync::Arc;
+use rand::Rng;
+use rodio::source::Source;
+
+use crate::composer::WavetableOscillator;
+use crate::effects::{AtomicF32, AudioEffect, Biquad, LowPassFilter, pan_gains};
+use crate::modulation::{Adsr, AftertouchRouting, BreathCurve, Lfo, ModMatrix, ModSources, ModTarget, VIBRATO_TARGET_RATE_HZ, Vibrato};
+use crate::theory::freq_to_midi_note;
+
+// several copies of the same oscillator slightly detuned from each other.
+// every voice gets its own lfo so the detuning keeps moving, and the lfo phases
+// are spread out so the voices don't all swing the same way at the same time
+#[derive(Clone)]
+pub struct UnisonOscillator {
+	voices: Vec<WavetableOscillator>,
+	detune_cents: f32,
+}
+
+impl UnisonOscillator {
+	// phase_spread 0.0 keeps every lfo in phase, 1.0 spreads them evenly around the cycle
+	pub fn new(oscillator: &WavetableOscillator, voice_count: usize, detune_cents: f32, lfo: Option<Lfo>, phase_spread: f32) -> UnisonOscillator {
+		let voice_count = voice_count.max(1);
+		let mut voices: Vec<WavetableOscillator> = Vec::with_capacity(voice_count);
+
+		for voice_index in 0..voice_count {
+			let mut voice = oscillator.clone();
+			voice.lfo = lfo.map(|mut lfo| {
+				lfo.phase += phase_spread * voice_index as f32 * 2.0 * std::f32::consts::PI / voice_count as f32;
+				lfo
+			});
+			voices.push(voice);
+		}
+
+		return UnisonOscillator {
+			voices,
+			detune_cents,
+		}
+	}
+
+	// voices are detuned symmetrically around the frequency, from -detune_cents/2 to +detune_cents/2
+	pub fn set_frequency(&mut self, frequency: f32) {
+		let voice_count = self.voices.len();
+		for (voice_index, voice) in self.voices.iter_mut().enumerate() {
+			let offset = if voice_count > 1 {
+				self.detune_cents * (voice_index as f32 / (voice_count - 1) as f32 - 0.5)
+			} else {
+				0.0
+			};
+			voice.set_frequency(frequency * 2.0_f32.powf(offset / 1200.0));
+		}
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		let mut sample = 0.0;
+		for voice in self.voices.iter_mut() {
+			sample += voice.get_sample();
+		}
+		return sample / self.voices.len() as f32;
+	}
+}
+
+impl Source for UnisonOscillator {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.voices[0].sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for UnisonOscillator {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// dx style phase modulation: the modulator's output nudges where the carrier reads
+// its table instead of changing the carrier's frequency. mod_depth is in radians
+// of carrier phase, ratio sets the modulator's frequency relative to the carrier
+#[derive(Clone)]
+pub struct PhaseModOscillator {
+	pub carrier: WavetableOscillator,
+	pub modulator: WavetableOscillator,
+	pub mod_depth: f32,
+	pub ratio: f32,
+}
+
+impl PhaseModOscillator {
+	pub fn new(carrier: WavetableOscillator, modulator: WavetableOscillator, mod_depth: f32, ratio: f32) -> PhaseModOscillator {
+		return PhaseModOscillator {
+			carrier,
+			modulator,
+			mod_depth,
+			ratio,
+		}
+	}
+
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.carrier.set_frequency(frequency);
+		self.modulator.set_frequency(frequency * self.ratio);
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		let mod_sample = self.modulator.get_sample();
+		let table_len = self.carrier.wave_table.len() as f32;
+		let offset = mod_sample * self.mod_depth / (2.0 * std::f32::consts::PI) * table_len;
+		return self.carrier.get_sample_shifted(offset);
+	}
+}
+
+impl Source for PhaseModOscillator {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.carrier.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for PhaseModOscillator {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// out = carrier * (carrier_mix + mod_depth * modulator). with carrier_mix 0.0 this
+// is ring modulation and only the sum and difference frequencies are heard, with
+// 1.0 it's classic am where the carrier stays audible between the sidebands
+#[derive(Clone)]
+pub struct AmOscillator {
+	pub carrier: WavetableOscillator,
+	pub modulator: WavetableOscillator,
+	pub mod_depth: f32,
+	pub carrier_mix: f32,
+	pub mod_ratio: f32,
+}
+
+impl AmOscillator {
+	pub fn new(carrier: WavetableOscillator, modulator: WavetableOscillator, mod_depth: f32, carrier_mix: f32, mod_ratio: f32) -> AmOscillator {
+		return AmOscillator {
+			carrier,
+			modulator,
+			mod_depth,
+			carrier_mix,
+			mod_ratio,
+		}
+	}
+
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.carrier.set_frequency(frequency);
+		self.modulator.set_frequency(frequency * self.mod_ratio);
+	}
+
+	pub fn retune(&mut self, frequency: f32) {
+		self.carrier.retune(frequency);
+		self.modulator.retune(frequency * self.mod_ratio);
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		let carrier = self.carrier.get_sample();
+		let modulator = self.modulator.get_sample();
+		return carrier * (self.carrier_mix + self.mod_depth * modulator);
+	}
+}
+
+impl Source for AmOscillator {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.carrier.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for AmOscillator {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// hard sync: the slave restarts its cycle every time the master finishes one, so
+// the slave is heard cut off at the master's pitch. sync_ratio puts the slave
+// above the master, sweeping it between 1.0 and 4.0 or so gives the classic sound
+#[derive(Clone)]
+pub struct HardSyncOscillator {
+	pub master: WavetableOscillator,
+	pub slave: WavetableOscillator,
+	pub sync_ratio: f32,
+}
+
+impl HardSyncOscillator {
+	pub fn new(master: WavetableOscillator, slave: WavetableOscillator, sync_ratio: f32) -> HardSyncOscillator {
+		return HardSyncOscillator {
+			master,
+			slave,
+			sync_ratio,
+		}
+	}
+
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.master.set_frequency(frequency);
+		self.slave.set_frequency(frequency * self.sync_ratio);
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		//the master is never heard, it only keeps time
+		self.master.get_sample();
+		if self.master.index < self.master.index_increment {
+			self.slave.index = 0.0;
+		}
+		return self.slave.get_sample();
+	}
+}
+
+impl Source for HardSyncOscillator {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.master.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for HardSyncOscillator {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SubWaveform {
+	Square,
+	Sine,
+	Off,
+}
+
+impl SubWaveform {
+	fn table(&self, len: usize) -> Vec<f32> {
+		return (0..len).map(|n| {
+			let phase = 2.0 * std::f32::consts::PI * n as f32 / len as f32;
+			match self {
+				SubWaveform::Square => if phase.sin() >= 0.0 { 1.0 } else { -1.0 },
+				SubWaveform::Sine => phase.sin(),
+				SubWaveform::Off => 0.0,
+			}
+		}).collect();
+	}
+}
+
+// the primary oscillator plus a plain square or sine exactly an octave below it
+// for weight. out = primary + sub_level * sub
+#[derive(Clone)]
+pub struct SubOscillator {
+	pub primary: WavetableOscillator,
+	pub sub_level: f32,
+	sub: WavetableOscillator,
+	sub_waveform: SubWaveform,
+}
+
+impl SubOscillator {
+	pub fn new(primary: WavetableOscillator, sub_level: f32, sub_waveform: SubWaveform) -> SubOscillator {
+		let sub = WavetableOscillator::new(primary.sample_rate, sub_waveform.table(primary.wave_table.len()));
+		return SubOscillator {
+			primary,
+			sub_level,
+			sub,
+			sub_waveform,
+		}
+	}
+
+	pub fn sub_waveform(&self) -> SubWaveform {
+		return self.sub_waveform;
+	}
+
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.primary.set_frequency(frequency);
+		self.sub.set_frequency(frequency / 2.0);
+	}
+
+	pub fn retune(&mut self, frequency: f32) {
+		self.primary.retune(frequency);
+		self.sub.retune(frequency / 2.0);
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		let primary = self.primary.get_sample();
+		if self.sub_waveform == SubWaveform::Off {
+			return primary;
+		}
+		return primary + self.sub_level * self.sub.get_sample();
+	}
+}
+
+impl Source for SubOscillator {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.primary.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for SubOscillator {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// two oscillators with their own tables playing one note, osc2 detune_semitones
+// above osc1. mix 0.0 is only osc1 and 1.0 only osc2, in between they crossfade.
+// with ring_mod_mode the two are multiplied instead and mix does nothing
+#[derive(Clone)]
+pub struct MultiOscVoice {
+	pub osc1: WavetableOscillator,
+	pub osc2: WavetableOscillator,
+	pub mix: f32,
+	pub detune_semitones: f32,
+	pub ring_mod_mode: bool,
+}
+
+impl MultiOscVoice {
+	pub fn new(osc1: WavetableOscillator, osc2: WavetableOscillator, mix: f32, detune_semitones: f32) -> MultiOscVoice {
+		return MultiOscVoice {
+			osc1,
+			osc2,
+			mix: mix.clamp(0.0, 1.0),
+			detune_semitones,
+			ring_mod_mode: false,
+		}
+	}
+
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.osc1.set_frequency(frequency);
+		self.osc2.set_frequency(frequency * 2.0_f32.powf(self.detune_semitones / 12.0));
+	}
+
+	pub fn retune(&mut self, frequency: f32) {
+		self.osc1.retune(frequency);
+		self.osc2.retune(frequency * 2.0_f32.powf(self.detune_semitones / 12.0));
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		let first = self.osc1.get_sample();
+		let second = self.osc2.get_sample();
+		if self.ring_mod_mode {
+			return first * second;
+		}
+		return first * (1.0 - self.mix) + second * self.mix;
+	}
+}
+
+impl Source for MultiOscVoice {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.osc1.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for MultiOscVoice {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// plucked string: a delay line one period long is filled with noise and played
+// back over and over while each pass averages neighbouring samples, so the high
+// harmonics die first like on a real string. decay is how much of the level is
+// kept every period, 0.996 or so rings for a couple of seconds
+#[derive(Clone)]
+pub struct KarplusStrong {
+	buffer: Vec<f32>,
+	position: usize,
+	decay: f32,
+	sample_rate: u32,
+}
+
+impl KarplusStrong {
+	pub fn new(frequency: f32, decay: f32, sample_rate: u32) -> KarplusStrong {
+		let len = (sample_rate as f32 / frequency.max(1.0)).round().max(2.0) as usize;
+		let mut rng = rand::thread_rng();
+		return KarplusStrong {
+			buffer: (0..len).map(|_| rng.gen_range(-1.0, 1.0)).collect(),
+			position: 0,
+			decay,
+			sample_rate,
+		}
+	}
+
+	pub fn get_sample(&mut self) -> f32 {
+		let sample = self.buffer[self.position];
+		let next = self.buffer[(self.position + 1) % self.buffer.len()];
+		self.buffer[self.position] = 0.5 * (sample + next) * self.decay;
+		self.position = (self.position + 1) % self.buffer.len();
+		return sample;
+	}
+}
+
+impl Source for KarplusStrong {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for KarplusStrong {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// a strummed chord, one plucked string per frequency. the strings are picked one
+// after another strum_delay apart in the order given, 1 to 5 ms is a quick strum,
+// and the sum is divided by the number of strings
+#[derive(Clone)]
+pub struct KarplusStrongChord {
+	strings: Vec<KarplusStrong>,
+	//sample at which each string is picked
+	starts: Vec<u64>,
+	elapsed: u64,
+	sample_rate: u32,
+}
+
+impl KarplusStrongChord {
+	pub fn new(frequencies: &[f32], decay: f32, strum_delay: Duration, sample_rate: u32) -> KarplusStrongChord {
+		let strum_samples = (strum_delay.as_secs_f32() * sample_rate as f32) as u64;
+		return KarplusStrongChord {
+			strings: frequencies.iter().map(|frequency| KarplusStrong::new(*frequency, decay, sample_rate)).collect(),
+			starts: (0..frequencies.len() as u64).map(|n| n * strum_samples).collect(),
+			elapsed: 0,
+			sample_rate,
+		}
+	}
+
+	pub fn get_sample(&mut self) -> f32 {
+		if self.strings.is_empty() {
+			return 0.0;
+		}
+		let mut sum = 0.0;
+		for (string, start) in self.strings.iter_mut().zip(self.starts.iter()) {
+			if self.elapsed >= *start {
+				sum += string.get_sample();
+			}
+		}
+		self.elapsed += 1;
+		return sum / self.strings.len() as f32;
+	}
+}
+
+impl Source for KarplusStrongChord {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for KarplusStrongChord {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// the sound source of a track, whichever kind of oscillator its instrument needs
+#[derive(Clone)]
+pub enum Oscillator {
+	Wavetable(WavetableOscillator),
+	Am(AmOscillator),
+	Sub(SubOscillator),
+	Multi(MultiOscVoice),
+}
+
+impl Oscillator {
+	pub fn set_frequency(&mut self, frequency: f32) {
+		match self {
+			Oscillator::Wavetable(oscillator) => oscillator.set_frequency(frequency),
+			Oscillator::Am(oscillator) => oscillator.set_frequency(frequency),
+			Oscillator::Sub(oscillator) => oscillator.set_frequency(frequency),
+			Oscillator::Multi(oscillator) => oscillator.set_frequency(frequency),
+		}
+	}
+
+	// like set_frequency but without restarting anything that follows the notes
+	pub fn retune(&mut self, frequency: f32) {
+		match self {
+			Oscillator::Wavetable(oscillator) => oscillator.retune(frequency),
+			Oscillator::Am(oscillator) => oscillator.retune(frequency),
+			Oscillator::Sub(oscillator) => oscillator.retune(frequency),
+			Oscillator::Multi(oscillator) => oscillator.retune(frequency),
+		}
+	}
+}
+
+impl Source for Oscillator {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return match self {
+			Oscillator::Wavetable(oscillator) => oscillator.sample_rate,
+			Oscillator::Am(oscillator) => oscillator.carrier.sample_rate,
+			Oscillator::Sub(oscillator) => oscillator.primary.sample_rate,
+			Oscillator::Multi(oscillator) => oscillator.osc1.sample_rate,
+		};
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for Oscillator {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return match self {
+			Oscillator::Wavetable(oscillator) => Some(oscillator.get_sample()),
+			Oscillator::Am(oscillator) => Some(oscillator.get_sample()),
+			Oscillator::Sub(oscillator) => Some(oscillator.get_sample()),
+			Oscillator::Multi(oscillator) => Some(oscillator.get_sample()),
+		};
+	}
+}
+
+// one note of a track that reacts to performance controls while it plays.
+// aftertouch pressure is shared so it can be changed from another thread, and
+// pressure events scheduled ahead of time (sample offset, pressure) are written into
+// it when they come due. pitch bend follows (sample offset, value) points that are
+// joined with straight lines, the offsets count from the start of the note
+pub struct ExpressionVoice {
+	oscillator: Oscillator,
+	frequency: f32,
+	aftertouch: Option<AftertouchRouting>,
+	pressure: Arc<AtomicF32>,
+	scheduled: Vec<(u64, f32)>,
+	bend: Breakpoints,
+	bend_range: f32,
+	breath: Breakpoints,
+	breath_curve: Option<BreathCurve>,
+	vibrato: Option<Vibrato>,
+	//only there when aftertouch goes to ModTarget::FilterCutoff
+	filter: Option<LowPassFilter>,
+	filter_octaves: f32,
+	elapsed: u64,
+	pitch_offset: f32,
+}
+
+impl ExpressionVoice {
+	pub fn new(oscillator: Oscillator, frequency: f32) -> ExpressionVoice {
+		let mut voice = ExpressionVoice {
+			oscillator,
+			frequency,
+			aftertouch: None,
+			pressure: Arc::new(AtomicF32::new(0.0)),
+			scheduled: Vec::new(),
+			bend: Breakpoints::new(Vec::new()),
+			bend_range: 0.0,
+			breath: Breakpoints::new(Vec::new()),
+			breath_curve: None,
+			vibrato: None,
+			filter: None,
+			filter_octaves: 0.0,
+			elapsed: 0,
+			pitch_offset: 0.0,
+		};
+		voice.oscillator.set_frequency(frequency);
+		return voice;
+	}
+
+	pub fn with_aftertouch(mut self, routing: AftertouchRouting, pressure: Arc<AtomicF32>, mut scheduled: Vec<(u64, f32)>) -> ExpressionVoice {
+		scheduled.sort_by_key(|(offset, _)| *offset);
+		self.aftertouch = Some(routing);
+		self.filter = match routing.target {
+			ModTarget::FilterCutoff { base_hz } => Some(LowPassFilter::new(base_hz, std::f32::consts::FRAC_1_SQRT_2, self.oscillator.sample_rate())),
+			_ => None,
+		};
+		self.pressure = pressure;
+		self.scheduled = scheduled;
+		return self;
+	}
+
+	// bend values go from -1.0 to 1.0 and range is the bend at 1.0 in semitones.
+	// before the first point the pitch is unbent, after the last it holds
+	pub fn with_pitch_bend(self, points: Vec<(i64, f32)>, range: f32) -> ExpressionVoice {
+		return ExpressionVoice {
+			bend: Breakpoints::new(points),
+			bend_range: range,
+			..self
+		}
+	}
+
+	// breath pressure from 0.0 to 1.0 at each point, shaped by the curve. before the
+	// first point the pressure holds the first point's value
+	pub fn with_breath(self, points: Vec<(i64, f32)>, curve: BreathCurve) -> ExpressionVoice {
+		return ExpressionVoice {
+			breath: Breakpoints::new(points),
+			breath_curve: Some(curve),
+			..self
+		}
+	}
+
+	pub fn with_vibrato(self, vibrato: Vibrato) -> ExpressionVoice {
+		return ExpressionVoice {
+			vibrato: Some(vibrato),
+			..self
+		}
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		while let Some((offset, pressure)) = self.scheduled.first() {
+			if *offset > self.elapsed {
+				break;
+			}
+			self.pressure.store(*pressure);
+			self.scheduled.remove(0);
+		}
+
+		let now = self.elapsed as i64;
+		let mut pitch_offset = self.bend.value(now, Some(0.0)).clamp(-1.0, 1.0) * self.bend_range;
+		let mut gain = 1.0;
+		if let Some(curve) = self.breath_curve {
+			let (cents, breath_gain) = curve.response(self.breath.value(now, None));
+			pitch_offset += cents / 100.0;
+			gain *= breath_gain;
+		}
+		let mut vibrato_depth = 0.0;
+		let mut filter_octaves = 0.0;
+		if let Some(aftertouch) = self.aftertouch {
+			let pressure = self.pressure.load().clamp(0.0, 1.0);
+			match aftertouch.target {
+				ModTarget::Pitch => pitch_offset += aftertouch.depth * pressure,
+				ModTarget::Volume => gain += aftertouch.depth * pressure,
+				ModTarget::FilterCutoff { .. } => filter_octaves = aftertouch.depth * pressure,
+				ModTarget::VibratoDepth => vibrato_depth = aftertouch.depth * pressure,
+			}
+		}
+		let vibrato = match self.vibrato {
+			Some(vibrato) => Some(vibrato),
+			None if vibrato_depth != 0.0 => Some(Vibrato::new(VIBRATO_TARGET_RATE_HZ, 0.0, Duration::ZERO)),
+			None => None,
+		};
+		if let Some(mut vibrato) = vibrato {
+			vibrato.depth_cents += vibrato_depth;
+			pitch_offset += vibrato.pitch_offset(self.elapsed as f32 / self.oscillator.sample_rate() as f32);
+		}
+		self.elapsed += 1;
+
+		//only retune when the pitch actually moved
+		if pitch_offset != self.pitch_offset {
+			self.pitch_offset = pitch_offset;
+			self.oscillator.retune(self.frequency * 2.0_f32.powf(pitch_offset / 12.0));
+		}
+		let sample = self.oscillator.next().unwrap_or(0.0) * gain;
+		return match (self.filter.as_mut(), self.aftertouch.map(|aftertouch| aftertouch.target)) {
+			(Some(filter), Some(ModTarget::FilterCutoff { base_hz })) => {
+				//the same goes for the cutoff
+				if filter_octaves != self.filter_octaves {
+					self.filter_octaves = filter_octaves;
+					filter.set_cutoff(base_hz * 2.0_f32.powf(filter_octaves));
+				}
+				filter.process(sample)
+			},
+			_ => sample,
+		};
+	}
+}
+
+// (sample offset, value) points joined with straight lines, read in order
+struct Breakpoints {
+	points: Vec<(i64, f32)>,
+	cursor: usize,
+}
+
+impl Breakpoints {
+	fn new(mut points: Vec<(i64, f32)>) -> Breakpoints {
+		points.sort_by_key(|(offset, _)| *offset);
+		return Breakpoints {
+			points,
+			cursor: 0,
+		}
+	}
+
+	// ahead of the first point the value is `before`, or the first point's value if
+	// that's None. after the last point it holds. `now` can only move forward
+	fn value(&mut self, now: i64, before: Option<f32>) -> f32 {
+		while self.cursor + 1 < self.points.len() && self.points[self.cursor + 1].0 <= now {
+			self.cursor += 1;
+		}
+		let (start, value) = match self.points.get(self.cursor) {
+			Some(point) if point.0 <= now => *point,
+			Some(point) => return before.unwrap_or(point.1),
+			None => return before.unwrap_or(0.0),
+		};
+		return match self.points.get(self.cursor + 1) {
+			Some((end, target)) => value + (target - value) * (now - start) as f32 / (end - start) as f32,
+			None => value,
+		};
+	}
+}
+
+impl Source for ExpressionVoice {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.oscillator.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for ExpressionVoice {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}
+
+// a note's oscillator with its vibrato, for notes that don't need the rest of
+// ExpressionVoice. the lfo starts with the note and only lives as long as it
+pub struct VibratoSource {
+	oscillator: Oscillator,
+	frequency: f32,
+	vibrato: Vibrato,
+	elapsed: u64,
+}
+
+impl VibratoSource {
+	pub fn new(mut oscillator: Oscillator, frequency: f32, vibrato: Vibrato) -> VibratoSource {
+		oscillator.set_frequency(frequency);
+		return VibratoSource {
+			oscillator,
+			frequency,
+			vibrato,
+			elapsed: 0,
+		}
+	}
+}
+
+impl Source for VibratoSource {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.oscillator.sample_rate();
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for VibratoSource {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let time = self.elapsed as f32 / self.oscillator.sample_rate() as f32;
+		self.elapsed += 1;
+		let offset = self.vibrato.pitch_offset(time);
+		if offset != 0.0 {
+			self.oscillator.retune(self.frequency * 2.0_f32.powf(offset / 12.0));
+		}
+		return self.oscillator.next();
+	}
+}
+
+//the filter's coefficients need a sin and a cos, they're worked out this often
+const MOD_FILTER_UPDATE_INTERVAL: u64 = 16;
+const MOD_FILTER_MIN_Q: f32 = 0.5;
+
+// one note played through a ModMatrix: an oscillator, a resonant low pass and a
+// pan, with two lfos and two envelopes to move them. env1 always shapes the volume
+// like the amp envelope of a hardware synth, and can be routed on top of that.
+// the output is stereo
+pub struct ModMatrixVoice {
+	oscillator: WavetableOscillator,
+	matrix: ModMatrix,
+	frequency: f32,
+	note_length: f32,
+	pub lfo1: Lfo,
+	pub lfo2: Lfo,
+	pub env1: Adsr,
+	pub env2: Adsr,
+	//midi velocity and note over 127, as the matrix sees them
+	velocity: f32,
+	note: f32,
+	pub cutoff_hz: f32,
+	pub resonance: f32,
+	filter: Biquad,
+	elapsed: u64,
+	pending_right: Option<f32>,
+}
+
+impl ModMatrixVoice {
+	// velocity from 0.0 to 1.0, note_duration is how long the note is held for the
+	// envelopes
+	pub fn new(oscillator: WavetableOscillator, matrix: ModMatrix, frequency: f32, velocity: f32, note_duration: Duration) -> ModMatrixVoice {
+		return ModMatrixVoice {
+			oscillator,
+			matrix,
+			frequency,
+			note_length: note_duration.as_secs_f32(),
+			lfo1: Lfo::new(5.0, 1.0),
+			lfo2: Lfo::new(0.5, 1.0),
+			env1: Adsr::new(0.01, 0.1, 0.8, 0.1),
+			env2: Adsr::new(0.01, 0.3, 0.0, 0.1),
+			velocity: velocity.clamp(0.0, 1.0),
+			note: freq_to_midi_note(frequency) as f32 / 127.0,
+			cutoff_hz: 20_000.0,
+			resonance: std::f32::consts::FRAC_1_SQRT_2,
+			filter: Biquad::default(),
+			elapsed: 0,
+			pending_right: None,
+		}
+	}
+
+	fn update_filter(&mut self, cutoff_octaves: f32, resonance: f32) {
+		let sample_rate = self.oscillator.sample_rate as f32;
+		let cutoff = (self.cutoff_hz * 2.0_f32.powf(cutoff_octaves)).clamp(20.0, 0.45 * sample_rate);
+		//rbj low pass
+		let omega = 2.0 * std::f32::consts::PI * cutoff / sample_rate;
+		let (sin, cos) = omega.sin_cos();
+		let alpha = sin / (2.0 * (self.resonance + resonance).max(MOD_FILTER_MIN_Q));
+		self.filter.set_coefficients((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+	}
+}
+
+impl Source for ModMatrixVoice {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 2;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.oscillator.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for ModMatrixVoice {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(right) = self.pending_right.take() {
+			return Some(right);
+		}
+
+		let sample_rate = self.oscillator.sample_rate;
+		let time = self.elapsed as f32 / sample_rate as f32;
+		let sources = ModSources {
+			lfo1: self.lfo1.next_value(sample_rate),
+			lfo2: self.lfo2.next_value(sample_rate),
+			env1: self.env1.level_at(time, self.note_length),
+			env2: self.env2.level_at(time, self.note_length),
+			velocity: self.velocity,
+			note: self.note,
+		};
+		let modulation = self.matrix.evaluate(&sources);
+
+		if self.elapsed % MOD_FILTER_UPDATE_INTERVAL == 0 {
+			self.update_filter(modulation.filter_cutoff, modulation.filter_resonance);
+		}
+		self.elapsed += 1;
+
+		self.oscillator.retune(self.frequency * 2.0_f32.powf(modulation.osc_frequency / 12.0));
+		let gain = sources.env1 * (1.0 + modulation.osc_amplitude).max(0.0);
+		let sample = self.filter.process(self.oscillator.get_sample()) * gain;
+
+		let (left_gain, right_gain) = pan_gains(modulation.pan_position.clamp(-1.0, 1.0));
+		self.pending_right = Some(sample * right_gain);
+		return Some(sample * left_gain);
+	}
+}
+
+//length of the overlap-add grains in samples, about 23 ms at 44.1 khz
+const SAMPLE_PLAYER_GRAIN: usize = 1024;
+
+// plays a recorded mono sample with its pitch and speed set apart. with the two
+// linked (set_both) it simply reads the sample faster or slower like a record.
+// otherwise it overlap-adds hann windowed grains, half a grain apart: each grain
+// is read at the pitch ratio, and the point in the sample the next grain starts
+// from moves on at the speed. fine for a few semitones or a modest stretch, past
+// that the grains start to sound like an echo
+pub struct SamplePlayer {
+	samples: Arc<[f32]>,
+	sample_rate: u32,
+	pitch: f32,
+	speed: f32,
+	linked: bool,
+	//where in the sample playback is, the start of the next grain when they're apart
+	position: f32,
+	//sample positions the current and the previous grain started from
+	grain_starts: (f32, f32),
+	grain_offset: usize,
+	window: Vec<f32>,
+}
+
+impl SamplePlayer {
+	// takes a Vec<f32> or an Arc<[f32]> that's shared with other players
+	pub fn new(samples: impl Into<Arc<[f32]>>, sample_rate: u32) -> SamplePlayer {
+		return SamplePlayer {
+			samples: samples.into(),
+			sample_rate,
+			pitch: 1.0,
+			speed: 1.0,
+			linked: true,
+			position: 0.0,
+			grain_starts: (0.0, f32::NEG_INFINITY),
+			grain_offset: 0,
+			window: crate::fft::hann_window(SAMPLE_PLAYER_GRAIN),
+		}
+	}
+
+	// moves the pitch without changing how fast the sample plays through
+	pub fn set_pitch_semitones(&mut self, semitones: f32) {
+		self.pitch = 2.0_f32.powf(semitones / 12.0);
+		self.unlink();
+	}
+
+	// plays through the sample `rate` times as fast without changing the pitch
+	pub fn set_speed(&mut self, rate: f32) {
+		self.speed = rate.max(0.0);
+		self.unlink();
+	}
+
+	// pitch and speed move together, 2.0 is twice as fast and an octave up
+	pub fn set_both(&mut self, rate: f32) {
+		self.pitch = rate.max(0.0);
+		self.speed = rate.max(0.0);
+		self.linked = true;
+	}
+
+	//the first grain picks up from wherever linked playback got to
+	fn unlink(&mut self) {
+		if self.linked {
+			self.linked = false;
+			self.grain_starts = (self.position, f32::NEG_INFINITY);
+			self.grain_offset = 0;
+		}
+	}
+
+	//linear interpolation, silence outside the sample
+	fn read(&self, position: f32) -> f32 {
+		if position < 0.0 {
+			return 0.0;
+		}
+		let index = position as usize;
+		let current = self.samples.get(index).copied().unwrap_or(0.0);
+		let next = self.samples.get(index + 1).copied().unwrap_or(0.0);
+		return current + (next - current) * position.fract();
+	}
+}
+
+impl Source for SamplePlayer {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for SamplePlayer {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let len = self.samples.len() as f32;
+		if self.linked {
+			if self.position >= len {
+				return None;
+			}
+			let sample = self.read(self.position);
+			self.position += self.speed;
+			return Some(sample);
+		}
+
+		let hop = SAMPLE_PLAYER_GRAIN / 2;
+		if self.grain_offset == hop {
+			self.grain_offset = 0;
+			self.position += self.speed * hop as f32;
+			self.grain_starts = (self.position, self.grain_starts.0);
+		}
+		//done once neither grain has anything of the sample left to read
+		if self.grain_starts.0.min(self.grain_starts.1) >= len {
+			return None;
+		}
+
+		let offset = self.grain_offset;
+		let (current, previous) = self.grain_starts;
+		let sample = self.window[offset] * self.read(current + self.pitch * offset as f32)
+			+ self.window[offset + hop] * self.read(previous + self.pitch * (offset + hop) as f32);
+		self.grain_offset += 1;
+		return Some(sample);
+	}
+}
@@ -0,0 +1,341 @@
+use std::fmt;
+
+use crate::modulation::{Adsr, Lfo, LfoShape};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Waveform {
+	Sine,
+	Saw,
+	Square,
+	Triangle,
+	Noise,
+}
+
+impl Waveform {
+	fn name(&self) -> &'static str {
+		return match self {
+			Waveform::Sine => "sine",
+			Waveform::Saw => "saw",
+			Waveform::Square => "square",
+			Waveform::Triangle => "triangle",
+			Waveform::Noise => "noise",
+		};
+	}
+
+	fn from_name(name: &str) -> Option<Waveform> {
+		return match name {
+			"sine" => Some(Waveform::Sine),
+			"saw" => Some(Waveform::Saw),
+			"square" => Some(Waveform::Square),
+			"triangle" => Some(Waveform::Triangle),
+			"noise" => Some(Waveform::Noise),
+			_ => None,
+		};
+	}
+}
+
+// an [[effects]] table as from_toml reads it: its kind and its settings
+type EffectTable = (Option<String>, Vec<(String, f32)>);
+
+// one stage of a patch's effects chain. decay is in seconds, gain in decibels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PatchEffect {
+	LowPass { cutoff: f32, q: f32 },
+	SoftClip { drive: f32, output_gain: f32 },
+	Gain { db: f32 },
+	Reverb { decay: f32, damping: f32, mix: f32 },
+}
+
+impl PatchEffect {
+	fn kind(&self) -> &'static str {
+		return match self {
+			PatchEffect::LowPass { .. } => "low_pass",
+			PatchEffect::SoftClip { .. } => "soft_clip",
+			PatchEffect::Gain { .. } => "gain",
+			PatchEffect::Reverb { .. } => "reverb",
+		};
+	}
+
+	fn settings(&self) -> Vec<(&'static str, f32)> {
+		return match *self {
+			PatchEffect::LowPass { cutoff, q } => vec![("cutoff", cutoff), ("q", q)],
+			PatchEffect::SoftClip { drive, output_gain } => vec![("drive", drive), ("output_gain", output_gain)],
+			PatchEffect::Gain { db } => vec![("db", db)],
+			PatchEffect::Reverb { decay, damping, mix } => vec![("decay", decay), ("damping", damping), ("mix", mix)],
+		};
+	}
+
+	// builds the effect from one [[effects]] table, every setting of the kind is required.
+	// decay has to be zero or more, cutoff and q more than zero
+	fn from_settings(kind: &str, settings: &[(String, f32)]) -> Result<PatchEffect, PatchError> {
+		let get = |key: &str| -> Result<f32, PatchError> {
+			return settings.iter()
+				.find(|(name, _)| name == key)
+				.map(|(_, value)| *value)
+				.ok_or_else(|| PatchError::MissingKey(format!("effects.{}", key)));
+		};
+		let effect = match kind {
+			"low_pass" => PatchEffect::LowPass { cutoff: get("cutoff")?, q: get("q")? },
+			"soft_clip" => PatchEffect::SoftClip { drive: get("drive")?, output_gain: get("output_gain")? },
+			"gain" => PatchEffect::Gain { db: get("db")? },
+			"reverb" => PatchEffect::Reverb { decay: get("decay")?, damping: get("damping")?, mix: get("mix")? },
+			_ => return Err(PatchError::InvalidValue("effects.kind".to_string())),
+		};
+		let known = effect.settings();
+		if let Some((name, _)) = settings.iter().find(|(name, _)| !known.iter().any(|(key, _)| key == name)) {
+			return Err(PatchError::UnknownKey(format!("effects.{}", name)));
+		}
+		//nan and infinity are never valid, and some settings can't go below zero
+		let invalid = known.iter().find(|(key, value)| {
+			return !value.is_finite() || match *key {
+				"cutoff" | "q" => *value <= 0.0,
+				"decay" => *value < 0.0,
+				_ => false,
+			};
+		});
+		if let Some((key, _)) = invalid {
+			return Err(PatchError::InvalidValue(format!("effects.{}", key)));
+		}
+		return Ok(effect);
+	}
+}
+
+// a patch is everything needed to recreate a sound: which wave table the
+// oscillator reads, how each note is shaped, how the pitch wobbles and the
+// effects the track is run through, in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+	pub waveform: Waveform,
+	pub envelope: Adsr,
+	pub lfo: Option<Lfo>,
+	pub volume: f32,
+	pub effects: Vec<PatchEffect>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PatchError {
+	Syntax(usize),
+	UnknownSection(String),
+	UnknownKey(String),
+	InvalidValue(String),
+	MissingKey(String),
+}
+
+impl fmt::Display for PatchError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			PatchError::Syntax(line) => write!(f, "syntax error on line {}", line),
+			PatchError::UnknownSection(section) => write!(f, "unknown section [{}]", section),
+			PatchError::UnknownKey(key) => write!(f, "unknown key {}", key),
+			PatchError::InvalidValue(key) => write!(f, "invalid value for {}", key),
+			PatchError::MissingKey(key) => write!(f, "missing key {}", key),
+		};
+	}
+}
+
+impl std::error::Error for PatchError {}
+
+impl Patch {
+	pub const fn new(waveform: Waveform, envelope: Adsr) -> Patch {
+		return Patch {
+			waveform,
+			envelope,
+			lfo: None,
+			volume: 1.0,
+			effects: Vec::new(),
+		}
+	}
+
+	pub fn serialize_to_toml(&self) -> String {
+		let mut toml = String::new();
+		toml.push_str(&format!("waveform = \"{}\"\n", self.waveform.name()));
+		toml.push_str(&format!("volume = {:?}\n", self.volume));
+
+		toml.push_str("\n[envelope]\n");
+		toml.push_str(&format!("attack = {:?}\n", self.envelope.attack));
+		toml.push_str(&format!("decay = {:?}\n", self.envelope.decay));
+		toml.push_str(&format!("sustain = {:?}\n", self.envelope.sustain));
+		toml.push_str(&format!("release = {:?}\n", self.envelope.release));
+
+		if let Some(lfo) = &self.lfo {
+			toml.push_str("\n[lfo]\n");
+			toml.push_str(&format!("rate = {:?}\n", lfo.rate));
+			toml.push_str(&format!("depth = {:?}\n", lfo.depth));
+			toml.push_str(&format!("shape = \"{}\"\n", shape_name(lfo.shape)));
+			if let Some(beats) = lfo.beats_per_cycle {
+				toml.push_str(&format!("beats = {:?}\n", beats));
+			}
+			if lfo.sync {
+				toml.push_str("sync = true\n");
+			}
+			if lfo.retrigger {
+				toml.push_str("retrigger = true\n");
+				toml.push_str(&format!("retrigger_phase = {:?}\n", lfo.retrigger_phase));
+			}
+		}
+
+		for effect in self.effects.iter() {
+			toml.push_str("\n[[effects]]\n");
+			toml.push_str(&format!("kind = \"{}\"\n", effect.kind()));
+			for (key, value) in effect.settings() {
+				toml.push_str(&format!("{} = {:?}\n", key, value));
+			}
+		}
+
+		return toml;
+	}
+
+	// only the small subset of toml that serialize_to_toml writes is understood:
+	// top level keys, [envelope] and [lfo] tables, [[effects]] tables, quoted
+	// strings, floats, booleans and # comments
+	pub fn from_toml(s: &str) -> Result<Patch, PatchError> {
+		let mut waveform: Option<Waveform> = None;
+		let mut volume: f32 = 1.0;
+		let mut envelope: [Option<f32>; 4] = [None; 4];
+		let mut lfo: Option<Lfo> = None;
+		//each [[effects]] table's kind and settings, turned into effects at the end
+		let mut effect_tables: Vec<EffectTable> = Vec::new();
+		let mut section = String::new();
+
+		for (line_number, raw_line) in s.lines().enumerate() {
+			let line = raw_line.split('#').next().unwrap_or("").trim();
+			if line.is_empty() {
+				continue;
+			}
+
+			if line.starts_with("[[") {
+				if !line.ends_with("]]") {
+					return Err(PatchError::Syntax(line_number + 1));
+				}
+				section = line[2..line.len() - 2].trim().to_string();
+				if section != "effects" {
+					return Err(PatchError::UnknownSection(section));
+				}
+				effect_tables.push((None, Vec::new()));
+				continue;
+			}
+
+			if line.starts_with('[') {
+				if !line.ends_with(']') {
+					return Err(PatchError::Syntax(line_number + 1));
+				}
+				section = line[1..line.len() - 1].trim().to_string();
+				match section.as_str() {
+					"envelope" => {},
+					"lfo" => { lfo = Some(Lfo::new(0.0, 0.0)) },
+					_ => return Err(PatchError::UnknownSection(section)),
+				}
+				continue;
+			}
+
+			let (key, value) = match line.split_once('=') {
+				Some((key, value)) => (key.trim(), value.trim()),
+				None => return Err(PatchError::Syntax(line_number + 1)),
+			};
+			let full_key = if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) };
+
+			if section == "effects" {
+				if let Some((kind, settings)) = effect_tables.last_mut() {
+					if key == "kind" {
+						*kind = Some(value.trim_matches('"').to_string());
+					} else {
+						settings.push((key.to_string(), parse_float(value, &full_key)?));
+					}
+				}
+				continue;
+			}
+
+			match full_key.as_str() {
+				"waveform" => {
+					let name = value.trim_matches('"');
+					waveform = Some(Waveform::from_name(name).ok_or(PatchError::InvalidValue(full_key))?);
+				},
+				"volume" => volume = parse_float(value, &full_key)?,
+				"envelope.attack" => envelope[0] = Some(parse_float(value, &full_key)?),
+				"envelope.decay" => envelope[1] = Some(parse_float(value, &full_key)?),
+				"envelope.sustain" => envelope[2] = Some(parse_float(value, &full_key)?),
+				"envelope.release" => envelope[3] = Some(parse_float(value, &full_key)?),
+				"lfo.rate" => {
+					let rate = parse_float(value, &full_key)?;
+					if let Some(lfo) = lfo.as_mut() { lfo.rate = rate }
+				},
+				"lfo.depth" => {
+					let depth = parse_float(value, &full_key)?;
+					if let Some(lfo) = lfo.as_mut() { lfo.depth = depth }
+				},
+				"lfo.shape" => {
+					let shape = shape_from_name(value.trim_matches('"')).ok_or_else(|| PatchError::InvalidValue(full_key.clone()))?;
+					if let Some(lfo) = lfo.as_mut() { lfo.shape = shape }
+				},
+				"lfo.beats" => {
+					let beats = parse_float(value, &full_key)?;
+					if let Some(lfo) = lfo.as_mut() { lfo.beats_per_cycle = Some(beats) }
+				},
+				"lfo.sync" => {
+					let sync = parse_bool(value, &full_key)?;
+					if let Some(lfo) = lfo.as_mut() { lfo.sync = sync }
+				},
+				"lfo.retrigger" => {
+					let retrigger = parse_bool(value, &full_key)?;
+					if let Some(lfo) = lfo.as_mut() { lfo.retrigger = retrigger }
+				},
+				"lfo.retrigger_phase" => {
+					let phase = parse_float(value, &full_key)?;
+					if let Some(lfo) = lfo.as_mut() { lfo.retrigger_phase = phase }
+				},
+				_ => return Err(PatchError::UnknownKey(full_key)),
+			}
+		}
+
+		let envelope_keys = ["envelope.attack", "envelope.decay", "envelope.sustain", "envelope.release"];
+		let mut adsr = [0.0; 4];
+		for (n, value) in envelope.iter().enumerate() {
+			adsr[n] = value.ok_or_else(|| PatchError::MissingKey(envelope_keys[n].to_string()))?;
+		}
+
+		let mut effects = Vec::with_capacity(effect_tables.len());
+		for (kind, settings) in effect_tables.iter() {
+			let kind = kind.as_ref().ok_or_else(|| PatchError::MissingKey("effects.kind".to_string()))?;
+			effects.push(PatchEffect::from_settings(kind, settings)?);
+		}
+
+		return Ok(Patch {
+			waveform: waveform.ok_or_else(|| PatchError::MissingKey("waveform".to_string()))?,
+			envelope: Adsr::new(adsr[0], adsr[1], adsr[2], adsr[3]),
+			lfo,
+			volume,
+			effects,
+		});
+	}
+}
+
+fn parse_float(value: &str, key: &str) -> Result<f32, PatchError> {
+	return value.parse::<f32>().map_err(|_| PatchError::InvalidValue(key.to_string()));
+}
+
+fn parse_bool(value: &str, key: &str) -> Result<bool, PatchError> {
+	return match value {
+		"true" => Ok(true),
+		"false" => Ok(false),
+		_ => Err(PatchError::InvalidValue(key.to_string())),
+	};
+}
+
+fn shape_name(shape: LfoShape) -> &'static str {
+	return match shape {
+		LfoShape::Sine => "sine",
+		LfoShape::Triangle => "triangle",
+		LfoShape::Square => "square",
+		LfoShape::Saw => "saw",
+	};
+}
+
+fn shape_from_name(name: &str) -> Option<LfoShape> {
+	return match name {
+		"sine" => Some(LfoShape::Sine),
+		"triangle" => Some(LfoShape::Triangle),
+		"square" => Some(LfoShape::Square),
+		"saw" => Some(LfoShape::Saw),
+		_ => None,
+	};
+}
@@ -0,0 +1,31 @@
+use crate::modulation::{Adsr, Lfo};
+use crate::patch::{Patch, Waveform};
+
+// built-in presets, use them as they are or as a starting point:
+// let mut patch = patch_library::PAD; patch.volume = 0.5;
+
+pub const ELECTRIC_PIANO: Patch = Patch {
+	waveform: Waveform::Triangle,
+	envelope: Adsr::new(0.005, 0.4, 0.3, 0.2),
+	lfo: Some(Lfo::new(4.0, 0.05)),
+	volume: 0.9,
+	effects: Vec::new(),
+};
+
+pub const PAD: Patch = Patch {
+	waveform: Waveform::Saw,
+	envelope: Adsr::new(0.6, 0.5, 0.8, 0.8),
+	lfo: Some(Lfo::new(0.3, 0.1)),
+	volume: 0.6,
+	effects: Vec::new(),
+};
+
+pub const BASS: Patch = Patch {
+	waveform: Waveform::Square,
+	envelope: Adsr::new(0.01, 0.15, 0.7, 0.05),
+	lfo: None,
+	volume: 0.8,
+	effects: Vec::new(),
+};
+
+pub const PLUCK: Patch = Patch::new(Waveform::Saw, Adsr::new(0.002, 0.2, 0.0, 0.05));
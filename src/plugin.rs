@@ -0,0 +1,215 @@
+use crate::composer::WavetableOscillator;
+use crate::modulation::Adsr;
+use crate::theory;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MidiMessage {
+	//velocity 0 counts as a note off, like on the wire
+	NoteOn { note: u8, velocity: u8 },
+	NoteOff { note: u8 },
+}
+
+// a midi message landing `offset` samples into the block being processed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MidiEvent {
+	pub offset: usize,
+	pub message: MidiMessage,
+}
+
+impl MidiEvent {
+	pub const fn new(offset: usize, message: MidiMessage) -> MidiEvent {
+		return MidiEvent {
+			offset,
+			message,
+		}
+	}
+}
+
+// what a host shows for a parameter: its name and the range of values set_param takes
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamInfo {
+	pub name: &'static str,
+	pub min: f32,
+	pub max: f32,
+	pub default: f32,
+}
+
+impl ParamInfo {
+	pub const fn new(name: &'static str, min: f32, max: f32, default: f32) -> ParamInfo {
+		return ParamInfo {
+			name,
+			min,
+			max,
+			default,
+		}
+	}
+}
+
+// the part of a plugin standard like vst3 that a host talks to: it hands over the
+// midi for a block and a buffer to fill, and reads and writes parameters by id.
+// wrapping an implementor in a real plugin format is left to crates like nih_plug
+pub trait SynthPlugin {
+	// fills the first block_size samples of output, mono. events are played at
+	// their offset and should be sorted by it
+	fn process_block(&mut self, midi_events: &[MidiEvent], output: &mut [f32], block_size: usize);
+	fn set_param(&mut self, id: u32, value: f32);
+	fn get_param(&self, id: u32) -> f32;
+	// ids go from 0 to param_count() - 1
+	fn param_count(&self) -> u32;
+	fn param_info(id: u32) -> ParamInfo where Self: Sized;
+}
+
+pub const PARAM_VOLUME: u32 = 0;
+pub const PARAM_ATTACK: u32 = 1;
+pub const PARAM_DECAY: u32 = 2;
+pub const PARAM_SUSTAIN: u32 = 3;
+pub const PARAM_RELEASE: u32 = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Stage {
+	Idle,
+	Attack,
+	Decay,
+	Sustain,
+	Release,
+}
+
+// one oscillator and an adsr, monophonic with the last note played winning. unlike
+// Adsr::apply the note's length isn't known ahead, so the release starts at note off
+pub struct BasicSynthPlugin {
+	oscillator: WavetableOscillator,
+	envelope: Adsr,
+	volume: f32,
+	stage: Stage,
+	level: f32,
+	//the level the release started from
+	release_level: f32,
+	note: Option<u8>,
+	velocity: f32,
+}
+
+impl BasicSynthPlugin {
+	pub fn new(oscillator: WavetableOscillator, envelope: Adsr) -> BasicSynthPlugin {
+		return BasicSynthPlugin {
+			oscillator,
+			envelope,
+			volume: 1.0,
+			stage: Stage::Idle,
+			level: 0.0,
+			release_level: 0.0,
+			note: None,
+			velocity: 0.0,
+		}
+	}
+
+	fn handle(&mut self, message: MidiMessage) {
+		match message {
+			MidiMessage::NoteOn { note, velocity } if velocity > 0 => {
+				self.oscillator.set_frequency(theory::midi_note_to_freq(note));
+				self.note = Some(note);
+				self.velocity = velocity as f32 / 127.0;
+				self.stage = Stage::Attack;
+			},
+			MidiMessage::NoteOn { note, .. } | MidiMessage::NoteOff { note } => {
+				if self.note == Some(note) {
+					self.note = None;
+					self.release_level = self.level;
+					self.stage = Stage::Release;
+				}
+			},
+		}
+	}
+
+	fn next_level(&mut self) -> f32 {
+		//how far one sample moves through a stage lasting `seconds`
+		let sample_rate = self.oscillator.sample_rate as f32;
+		let step = |seconds: f32| if seconds > 0.0 { 1.0 / (seconds * sample_rate) } else { 1.0 };
+		match self.stage {
+			Stage::Idle => self.level = 0.0,
+			Stage::Attack => {
+				self.level += step(self.envelope.attack);
+				if self.level >= 1.0 {
+					self.level = 1.0;
+					self.stage = Stage::Decay;
+				}
+			},
+			Stage::Decay => {
+				self.level -= (1.0 - self.envelope.sustain) * step(self.envelope.decay);
+				if self.level <= self.envelope.sustain {
+					self.level = self.envelope.sustain;
+					self.stage = Stage::Sustain;
+				}
+			},
+			Stage::Sustain => self.level = self.envelope.sustain,
+			Stage::Release => {
+				self.level -= self.release_level * step(self.envelope.release);
+				if self.level <= 0.0 {
+					self.level = 0.0;
+					self.stage = Stage::Idle;
+				}
+			},
+		}
+		return self.level;
+	}
+}
+
+impl SynthPlugin for BasicSynthPlugin {
+	fn process_block(&mut self, midi_events: &[MidiEvent], output: &mut [f32], block_size: usize) {
+		let mut events = midi_events.iter().peekable();
+		for (n, sample) in output.iter_mut().take(block_size).enumerate() {
+			while let Some(event) = events.next_if(|event| event.offset <= n) {
+				self.handle(event.message);
+			}
+			let level = self.next_level();
+			*sample = if self.stage == Stage::Idle {
+				0.0
+			} else {
+				self.oscillator.get_sample() * level * self.velocity * self.volume
+			};
+		}
+		//events past the end of the block still count
+		for event in events {
+			self.handle(event.message);
+		}
+	}
+
+	fn set_param(&mut self, id: u32, value: f32) {
+		let info = BasicSynthPlugin::param_info(id);
+		let value = value.clamp(info.min, info.max);
+		match id {
+			PARAM_VOLUME => self.volume = value,
+			PARAM_ATTACK => self.envelope.attack = value,
+			PARAM_DECAY => self.envelope.decay = value,
+			PARAM_SUSTAIN => self.envelope.sustain = value,
+			PARAM_RELEASE => self.envelope.release = value,
+			_ => {},
+		}
+	}
+
+	fn get_param(&self, id: u32) -> f32 {
+		return match id {
+			PARAM_VOLUME => self.volume,
+			PARAM_ATTACK => self.envelope.attack,
+			PARAM_DECAY => self.envelope.decay,
+			PARAM_SUSTAIN => self.envelope.sustain,
+			PARAM_RELEASE => self.envelope.release,
+			_ => 0.0,
+		};
+	}
+
+	fn param_count(&self) -> u32 {
+		return 5;
+	}
+
+	// unknown ids get an empty name and a range of nothing
+	fn param_info(id: u32) -> ParamInfo {
+		return match id {
+			PARAM_VOLUME => ParamInfo::new("volume", 0.0, 1.0, 1.0),
+			PARAM_ATTACK => ParamInfo::new("attack", 0.0, 10.0, 0.01),
+			PARAM_DECAY => ParamInfo::new("decay", 0.0, 10.0, 0.1),
+			PARAM_SUSTAIN => ParamInfo::new("sustain", 0.0, 1.0, 0.8),
+			PARAM_RELEASE => ParamInfo::new("release", 0.0, 10.0, 0.2),
+			_ => ParamInfo::new("", 0.0, 0.0, 0.0),
+		};
+	}
+}
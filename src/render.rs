@@ -0,0 +1,218 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rodio::Sink;
+use rodio::buffer::SamplesBuffer;
+use rodio::queue::SourcesQueueOutput;
+
+use crate::analysis;
+use crate::composer::{self, ProtoTrack, SynthConfig, SAMPLE_RATE};
+use crate::effects::{Dither, DitherMode, pan_gains};
+
+#[derive(Debug)]
+pub enum RenderError {
+	Wav(hound::Error),
+	Io(io::Error),
+}
+
+impl fmt::Display for RenderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			RenderError::Wav(error) => write!(f, "couldn't write wav file: {}", error),
+			RenderError::Io(error) => write!(f, "couldn't write wav metadata: {}", error),
+		};
+	}
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<hound::Error> for RenderError {
+	fn from(error: hound::Error) -> RenderError {
+		return RenderError::Wav(error);
+	}
+}
+
+impl From<io::Error> for RenderError {
+	fn from(error: io::Error) -> RenderError {
+		return RenderError::Io(error);
+	}
+}
+
+// bits_per_sample 32 writes floats, 16 and 24 write integers dithered with `dither`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+	pub target_lufs: Option<f32>,
+	pub bits_per_sample: u16,
+	pub dither: DitherMode,
+}
+
+impl Default for RenderOptions {
+	fn default() -> RenderOptions {
+		return RenderOptions {
+			target_lufs: None,
+			bits_per_sample: 32,
+			dither: DitherMode::Tpdf,
+		}
+	}
+}
+
+// plays the song into memory instead of the sound card: every track is queued into
+// an idle sink exactly like play_song does and the sink outputs are summed to mono
+pub fn render(prototracks: &[ProtoTrack], config: &SynthConfig) -> Vec<f32> {
+	let buffers = render_tracks(prototracks, config);
+	let mut mix: Vec<f32> = vec![0.0; buffers.iter().map(|buffer| buffer.len()).max().unwrap_or(0)];
+	for buffer in buffers.iter() {
+		for (mixed, sample) in mix.iter_mut().zip(buffer.iter()) {
+			*mixed += sample;
+		}
+	}
+	return mix;
+}
+
+// like render, but every track is placed in the stereo field by its pan before
+// summing. the result is interleaved left, right, left, right...
+pub fn mix_tracks(prototracks: &[ProtoTrack], config: &SynthConfig) -> Vec<f32> {
+	let buffers = render_tracks(prototracks, config);
+	let mut mix: Vec<f32> = vec![0.0; 2 * buffers.iter().map(|buffer| buffer.len()).max().unwrap_or(0)];
+	for (proto, buffer) in prototracks.iter().zip(buffers.iter()) {
+		let (left_gain, right_gain) = pan_gains(proto.pan);
+		for (frame, sample) in mix.chunks_exact_mut(2).zip(buffer.iter()) {
+			frame[0] += sample * left_gain;
+			frame[1] += sample * right_gain;
+		}
+	}
+	return mix;
+}
+
+// every track on its own, with its volume applied, all as long as the longest track
+fn render_tracks(prototracks: &[ProtoTrack], config: &SynthConfig) -> Vec<Vec<f32>> {
+	let (sinks, outputs): (Vec<Sink>, Vec<SourcesQueueOutput<f32>>) = prototracks.iter().map(|_| Sink::new_idle()).unzip();
+	let tracks = composer::prepare_tracks(prototracks, sinks, config);
+
+	let longest_duration = tracks.iter().map(|track| track.duration).fold(0.0, f32::max);
+	let len = (longest_duration * SAMPLE_RATE as f32) as usize;
+
+	//the tracks have to stay alive while their outputs are read, dropping a sink stops it
+	let mut buffers: Vec<Vec<f32>> = Vec::with_capacity(tracks.len());
+	for (track, output) in tracks.iter().zip(outputs) {
+		track.sink.play();
+		let mut buffer: Vec<f32> = vec![0.0; len];
+		for (rendered, sample) in buffer.iter_mut().zip(output) {
+			*rendered = sample;
+		}
+		buffers.push(buffer);
+	}
+
+	return buffers;
+}
+
+pub fn render_to_wav(prototracks: &[ProtoTrack], path: &Path, config: &SynthConfig, options: &RenderOptions) -> Result<(), RenderError> {
+	let mut samples = render(prototracks, config);
+	if let Some(target_lufs) = options.target_lufs {
+		analysis::normalize_to_lufs(&mut samples, SAMPLE_RATE, target_lufs);
+	}
+
+	let bits = options.bits_per_sample;
+	let spec = hound::WavSpec {
+		channels: 1,
+		sample_rate: SAMPLE_RATE,
+		bits_per_sample: bits,
+		sample_format: if bits == 32 { hound::SampleFormat::Float } else { hound::SampleFormat::Int },
+	};
+	let mut writer = hound::WavWriter::create(path, spec)?;
+	if bits == 32 {
+		for sample in samples {
+			writer.write_sample(sample)?;
+		}
+	} else {
+		let full_scale = ((1_i64 << (bits - 1)) - 1) as f32;
+		for sample in Dither::new(SamplesBuffer::new(1, SAMPLE_RATE, samples), bits, options.dither) {
+			writer.write_sample((sample * full_scale).round() as i32)?;
+		}
+	}
+	writer.finalize()?;
+
+	return Ok(());
+}
+
+// what ends up in the LIST INFO chunk of an exported file. a bpm of 0 takes the
+// tempo of the first track. empty strings are left out of the chunk
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WavMetadata {
+	pub title: String,
+	pub artist: String,
+	pub genre: String,
+	pub bpm: u32,
+	pub key: Option<String>,
+}
+
+// render_to_wav, then the metadata appended as an INFO list after the audio.
+// INFO has no fields for tempo or key so they go in the comment
+pub fn render_to_wav_with_metadata(prototracks: &[ProtoTrack], path: &Path, config: &SynthConfig, options: &RenderOptions, metadata: &WavMetadata) -> Result<(), RenderError> {
+	render_to_wav(prototracks, path, config, options)?;
+
+	let bpm = if metadata.bpm == 0 {
+		prototracks.first().map(|proto| proto.tempo).unwrap_or(0)
+	} else {
+		metadata.bpm
+	};
+	let mut comment = format!("bpm {}", bpm);
+	if let Some(key) = &metadata.key {
+		comment.push_str(&format!(", key {}", key));
+	}
+	let fields = [
+		(b"INAM", metadata.title.clone()),
+		(b"IART", metadata.artist.clone()),
+		(b"IGNR", metadata.genre.clone()),
+		(b"ICRD", creation_date()),
+		(b"ICMT", comment),
+	];
+
+	let mut info: Vec<u8> = b"INFO".to_vec();
+	for (id, text) in fields.iter().filter(|(_, text)| !text.is_empty()) {
+		//zero terminated, and every chunk starts on an even byte
+		let size = text.len() + 1;
+		info.extend_from_slice(*id);
+		info.extend_from_slice(&(size as u32).to_le_bytes());
+		info.extend_from_slice(text.as_bytes());
+		info.push(0);
+		if size % 2 == 1 {
+			info.push(0);
+		}
+	}
+
+	let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+	if file.seek(SeekFrom::End(0))? % 2 == 1 {
+		file.write_all(&[0])?;
+	}
+	file.write_all(b"LIST")?;
+	file.write_all(&(info.len() as u32).to_le_bytes())?;
+	file.write_all(&info)?;
+
+	//the riff size covers everything after itself
+	let riff_size = file.stream_position()? - 8;
+	file.seek(SeekFrom::Start(4))?;
+	file.write_all(&(riff_size as u32).to_le_bytes())?;
+
+	return Ok(());
+}
+
+// today as YYYY-MM-DD, the format ICRD expects
+fn creation_date() -> String {
+	let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs() / 86400).unwrap_or(0) as i64;
+
+	//days since 1970-01-01 to a gregorian date, counted in 400 year eras from 0000-03-01
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let day_of_era = z.rem_euclid(146097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+	return format!("{:04}-{:02}-{:02}", year, month, day);
+}
@@ -0,0 +1,403 @@
+use core::time::Duration;
+use std::fmt;
+use rand::Rng;
+use rodio::source::Source;
+
+use crate::composer::WavetableOscillator;
+use crate::effects::{AudioEffect, EffectChain, Gain, LowPassFilter, Reverb, SoftClip, SoftClipMode};
+use crate::patch::Waveform;
+
+//size of the tables build() makes for the oscillators
+const SYNTHDEF_TABLE_SIZE: usize = 128;
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+	//byte offset into the json where it stopped making sense
+	Syntax(usize),
+	UnknownUGen(String),
+	InvalidValue(String),
+	MissingKey(String),
+	//the first ugen has to be an oscillator and no other one can be
+	NoOscillator,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self {
+			ParseError::Syntax(offset) => write!(f, "json syntax error at byte {}", offset),
+			ParseError::UnknownUGen(name) => write!(f, "unknown ugen {}", name),
+			ParseError::InvalidValue(key) => write!(f, "invalid value for {}", key),
+			ParseError::MissingKey(key) => write!(f, "missing key {}", key),
+			ParseError::NoOscillator => write!(f, "a synthdef starts with exactly one oscillator"),
+		};
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+// one processing step after the oscillator, named after the closest supercollider
+// ugen. RLPF takes the reciprocal of q like supercollider does, Mul is a gain in db
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UGen {
+	Rlpf { freq: f32, rq: f32 },
+	Tanh { drive: f32 },
+	Mul { db: f32 },
+	FreeVerb { decay: f32, damp: f32, mix: f32 },
+}
+
+// a signal chain in the spirit of a supercollider synthdef: one oscillator at a
+// fixed frequency and level, then the ugens in order, then out. as json:
+//   {"name": "bass", "ugens": [
+//     {"ugen": "Saw", "freq": 55.0, "mul": 0.5},
+//     {"ugen": "RLPF", "freq": 800.0, "rq": 0.5},
+//     {"ugen": "Out"}]}
+// the oscillators are SinOsc, Saw, Pulse, LFTri and WhiteNoise, the trailing Out
+// is optional
+#[derive(Clone, Debug, PartialEq)]
+pub struct SynthDef {
+	pub name: String,
+	pub waveform: Waveform,
+	pub freq: f32,
+	pub mul: f32,
+	pub ugens: Vec<UGen>,
+}
+
+impl SynthDef {
+	pub fn new(name: &str, waveform: Waveform, freq: f32) -> SynthDef {
+		return SynthDef {
+			name: name.to_string(),
+			waveform,
+			freq,
+			mul: 1.0,
+			ugens: Vec::new(),
+		}
+	}
+
+	pub fn to_json(&self) -> String {
+		let mut ugens: Vec<String> = Vec::with_capacity(self.ugens.len() + 2);
+		ugens.push(format!("{{\"ugen\": \"{}\", \"freq\": {:?}, \"mul\": {:?}}}", oscillator_name(self.waveform), self.freq, self.mul));
+		for ugen in self.ugens.iter() {
+			ugens.push(match ugen {
+				UGen::Rlpf { freq, rq } => format!("{{\"ugen\": \"RLPF\", \"freq\": {:?}, \"rq\": {:?}}}", freq, rq),
+				UGen::Tanh { drive } => format!("{{\"ugen\": \"Tanh\", \"drive\": {:?}}}", drive),
+				UGen::Mul { db } => format!("{{\"ugen\": \"Mul\", \"db\": {:?}}}", db),
+				UGen::FreeVerb { decay, damp, mix } => {
+					format!("{{\"ugen\": \"FreeVerb\", \"decay\": {:?}, \"damp\": {:?}, \"mix\": {:?}}}", decay, damp, mix)
+				},
+			});
+		}
+		ugens.push("{\"ugen\": \"Out\"}".to_string());
+		return format!("{{\"name\": {}, \"ugens\": [\n\t{}\n]}}\n", quote(&self.name), ugens.join(",\n\t"));
+	}
+
+	pub fn from_json(s: &str) -> Result<SynthDef, ParseError> {
+		let mut parser = JsonParser { text: s.as_bytes(), position: 0 };
+		let root = parser.value()?;
+		parser.skip_whitespace();
+		if parser.position < s.len() {
+			return Err(ParseError::Syntax(parser.position));
+		}
+
+		let name = match root.get("name") {
+			Some(Json::String(name)) => name.clone(),
+			Some(_) => return Err(ParseError::InvalidValue("name".to_string())),
+			None => return Err(ParseError::MissingKey("name".to_string())),
+		};
+		let ugens = match root.get("ugens") {
+			Some(Json::Array(ugens)) => ugens,
+			Some(_) => return Err(ParseError::InvalidValue("ugens".to_string())),
+			None => return Err(ParseError::MissingKey("ugens".to_string())),
+		};
+
+		let mut oscillator: Option<(Waveform, f32, f32)> = None;
+		let mut chain: Vec<UGen> = Vec::new();
+		for ugen in ugens.iter() {
+			let kind = match ugen.get("ugen") {
+				Some(Json::String(kind)) => kind.as_str(),
+				_ => return Err(ParseError::MissingKey("ugen".to_string())),
+			};
+			if let Some(waveform) = oscillator_from_name(kind) {
+				if oscillator.is_some() || !chain.is_empty() {
+					return Err(ParseError::NoOscillator);
+				}
+				oscillator = Some((waveform, ugen.number("freq", 440.0)?, ugen.number("mul", 1.0)?));
+				continue;
+			}
+			if oscillator.is_none() {
+				return Err(ParseError::NoOscillator);
+			}
+			chain.push(match kind {
+				"RLPF" => UGen::Rlpf { freq: ugen.number("freq", 440.0)?, rq: ugen.number("rq", 1.0)? },
+				"Tanh" => UGen::Tanh { drive: ugen.number("drive", 1.0)? },
+				"Mul" => UGen::Mul { db: ugen.number("db", 0.0)? },
+				"FreeVerb" => UGen::FreeVerb {
+					decay: ugen.number("decay", 1.0)?,
+					damp: ugen.number("damp", 0.5)?,
+					mix: ugen.number("mix", 0.33)?,
+				},
+				"Out" => break,
+				_ => return Err(ParseError::UnknownUGen(kind.to_string())),
+			});
+		}
+
+		let (waveform, freq, mul) = oscillator.ok_or(ParseError::NoOscillator)?;
+		return Ok(SynthDef {
+			name,
+			waveform,
+			freq,
+			mul,
+			ugens: chain,
+		});
+	}
+
+	// the chain as a mono source at `rate` hz. it plays until it's dropped, put a
+	// take_duration on it for a note
+	pub fn build(&self, rate: u32) -> Box<dyn Source<Item = f32> + Send> {
+		let mut oscillator = WavetableOscillator::new(rate, waveform_table(self.waveform));
+		oscillator.set_frequency(self.freq);
+		let mut source: Box<dyn Source<Item = f32> + Send> = Box::new(oscillator.amplify(self.mul));
+
+		//runs of per sample effects share one chain, the reverb wraps whatever came before
+		let mut effects: Vec<Box<dyn AudioEffect>> = Vec::new();
+		for ugen in self.ugens.iter() {
+			match ugen {
+				UGen::Rlpf { freq, rq } => effects.push(Box::new(LowPassFilter::new(*freq, 1.0 / rq.max(0.01), rate))),
+				UGen::Tanh { drive } => effects.push(Box::new(SoftClip::new(SoftClipMode::Tanh, *drive, 1.0))),
+				UGen::Mul { db } => effects.push(Box::new(Gain::new(*db))),
+				UGen::FreeVerb { decay, damp, mix } => {
+					if !effects.is_empty() {
+						source = Box::new(EffectChain::new(source, std::mem::take(&mut effects)));
+					}
+					source = Box::new(Reverb::new(source, Duration::from_secs_f32(decay.max(0.0)), *damp, *mix));
+				},
+			}
+		}
+		if !effects.is_empty() {
+			source = Box::new(EffectChain::new(source, effects));
+		}
+		return source;
+	}
+}
+
+fn oscillator_name(waveform: Waveform) -> &'static str {
+	return match waveform {
+		Waveform::Sine => "SinOsc",
+		Waveform::Saw => "Saw",
+		Waveform::Square => "Pulse",
+		Waveform::Triangle => "LFTri",
+		Waveform::Noise => "WhiteNoise",
+	};
+}
+
+fn oscillator_from_name(name: &str) -> Option<Waveform> {
+	return match name {
+		"SinOsc" => Some(Waveform::Sine),
+		"Saw" => Some(Waveform::Saw),
+		"Pulse" => Some(Waveform::Square),
+		"LFTri" => Some(Waveform::Triangle),
+		"WhiteNoise" => Some(Waveform::Noise),
+		_ => None,
+	};
+}
+
+// the same shapes play_song gives its instruments
+fn waveform_table(waveform: Waveform) -> Vec<f32> {
+	let size = SYNTHDEF_TABLE_SIZE;
+	let mut rng = rand::thread_rng();
+	return (0..size).map(|n| {
+		let position = n as f32 / size as f32;
+		return match waveform {
+			Waveform::Sine => (2.0 * std::f32::consts::PI * position).sin(),
+			Waveform::Saw => -1.0 + 2.0 * position,
+			Waveform::Square => if position < 0.5 { 1.0 } else { -1.0 },
+			Waveform::Triangle => if position < 0.5 { -1.0 + 4.0 * position } else { 3.0 - 4.0 * position },
+			Waveform::Noise => rng.gen::<f32>() * 2.0 - 1.0,
+		};
+	}).collect();
+}
+
+fn quote(s: &str) -> String {
+	let mut quoted = String::from("\"");
+	for c in s.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	return quoted;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+	Null,
+	Bool(bool),
+	Number(f32),
+	String(String),
+	Array(Vec<Json>),
+	Object(Vec<(String, Json)>),
+}
+
+impl Json {
+	fn get(&self, key: &str) -> Option<&Json> {
+		return match self {
+			Json::Object(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+			_ => None,
+		};
+	}
+
+	// a number field of a ugen, `default` when it's left out
+	fn number(&self, key: &str, default: f32) -> Result<f32, ParseError> {
+		return match self.get(key) {
+			Some(Json::Number(value)) => Ok(*value),
+			Some(_) => Err(ParseError::InvalidValue(key.to_string())),
+			None => Ok(default),
+		};
+	}
+}
+
+// just enough json for synthdefs: no surrogate pairs in \u escapes
+struct JsonParser<'a> {
+	text: &'a [u8],
+	position: usize,
+}
+
+impl JsonParser<'_> {
+	fn skip_whitespace(&mut self) {
+		while self.position < self.text.len() && self.text[self.position].is_ascii_whitespace() {
+			self.position += 1;
+		}
+	}
+
+	fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+		self.skip_whitespace();
+		if self.text.get(self.position) != Some(&byte) {
+			return Err(ParseError::Syntax(self.position));
+		}
+		self.position += 1;
+		return Ok(());
+	}
+
+	fn keyword(&mut self, word: &str, value: Json) -> Result<Json, ParseError> {
+		if self.text[self.position..].starts_with(word.as_bytes()) {
+			self.position += word.len();
+			return Ok(value);
+		}
+		return Err(ParseError::Syntax(self.position));
+	}
+
+	fn value(&mut self) -> Result<Json, ParseError> {
+		self.skip_whitespace();
+		return match self.text.get(self.position) {
+			Some(b'{') => self.object(),
+			Some(b'[') => self.array(),
+			Some(b'"') => Ok(Json::String(self.string()?)),
+			Some(b't') => self.keyword("true", Json::Bool(true)),
+			Some(b'f') => self.keyword("false", Json::Bool(false)),
+			Some(b'n') => self.keyword("null", Json::Null),
+			Some(_) => self.number(),
+			None => Err(ParseError::Syntax(self.position)),
+		};
+	}
+
+	fn object(&mut self) -> Result<Json, ParseError> {
+		self.expect(b'{')?;
+		let mut entries: Vec<(String, Json)> = Vec::new();
+		self.skip_whitespace();
+		if self.text.get(self.position) == Some(&b'}') {
+			self.position += 1;
+			return Ok(Json::Object(entries));
+		}
+		loop {
+			self.skip_whitespace();
+			let key = self.string()?;
+			self.expect(b':')?;
+			entries.push((key, self.value()?));
+			self.skip_whitespace();
+			match self.text.get(self.position) {
+				Some(b',') => self.position += 1,
+				Some(b'}') => {
+					self.position += 1;
+					return Ok(Json::Object(entries));
+				},
+				_ => return Err(ParseError::Syntax(self.position)),
+			}
+		}
+	}
+
+	fn array(&mut self) -> Result<Json, ParseError> {
+		self.expect(b'[')?;
+		let mut values: Vec<Json> = Vec::new();
+		self.skip_whitespace();
+		if self.text.get(self.position) == Some(&b']') {
+			self.position += 1;
+			return Ok(Json::Array(values));
+		}
+		loop {
+			values.push(self.value()?);
+			self.skip_whitespace();
+			match self.text.get(self.position) {
+				Some(b',') => self.position += 1,
+				Some(b']') => {
+					self.position += 1;
+					return Ok(Json::Array(values));
+				},
+				_ => return Err(ParseError::Syntax(self.position)),
+			}
+		}
+	}
+
+	fn string(&mut self) -> Result<String, ParseError> {
+		if self.text.get(self.position) != Some(&b'"') {
+			return Err(ParseError::Syntax(self.position));
+		}
+		self.position += 1;
+		let mut bytes: Vec<u8> = Vec::new();
+		loop {
+			let byte = *self.text.get(self.position).ok_or(ParseError::Syntax(self.position))?;
+			self.position += 1;
+			match byte {
+				b'"' => break,
+				b'\\' => {
+					let escape = *self.text.get(self.position).ok_or(ParseError::Syntax(self.position))?;
+					self.position += 1;
+					match escape {
+						b'"' | b'\\' | b'/' => bytes.push(escape),
+						b'n' => bytes.push(b'\n'),
+						b't' => bytes.push(b'\t'),
+						b'r' => bytes.push(b'\r'),
+						b'b' => bytes.push(0x08),
+						b'f' => bytes.push(0x0c),
+						b'u' => {
+							let digits = self.text.get(self.position..self.position + 4).ok_or(ParseError::Syntax(self.position))?;
+							let code = std::str::from_utf8(digits).ok()
+								.and_then(|digits| u32::from_str_radix(digits, 16).ok())
+								.and_then(char::from_u32)
+								.ok_or(ParseError::Syntax(self.position))?;
+							self.position += 4;
+							let mut buffer = [0u8; 4];
+							bytes.extend_from_slice(code.encode_utf8(&mut buffer).as_bytes());
+						},
+						_ => return Err(ParseError::Syntax(self.position - 1)),
+					}
+				},
+				_ => bytes.push(byte),
+			}
+		}
+		return String::from_utf8(bytes).map_err(|_| ParseError::Syntax(self.position));
+	}
+
+	fn number(&mut self) -> Result<Json, ParseError> {
+		let start = self.position;
+		while self.position < self.text.len() && matches!(self.text[self.position], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') {
+			self.position += 1;
+		}
+		return std::str::from_utf8(&self.text[start..self.position]).ok()
+			.and_then(|number| number.parse::<f32>().ok())
+			.map(Json::Number)
+			.ok_or(ParseError::Syntax(start));
+	}
+}
@@ -0,0 +1,353 @@
+// moves a frequency by a number of equal tempered semitones, negative goes down
+pub fn transpose(frequency: f32, semitones: f32) -> f32 {
+	return frequency * 2.0_f32.powf(semitones / 12.0);
+}
+
+// nearest midi note number, a4 at 440 hz is 69. clamped to the midi range
+pub fn freq_to_midi_note(frequency: f32) -> u8 {
+	return (69.0 + 12.0 * (frequency / 440.0).log2()).round().clamp(0.0, 127.0) as u8;
+}
+
+// frequency of a midi note number in equal temperament, 69 is a4 at 440 hz
+pub fn midi_note_to_freq(note: u8) -> f32 {
+	return 440.0 * 2.0_f32.powf((note as f32 - 69.0) / 12.0);
+}
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+// name of a pitch class, 0 is C
+pub fn pitch_class_name(pitch_class: u8) -> &'static str {
+	return NOTE_NAMES[pitch_class as usize % 12];
+}
+
+// name and octave of the nearest note, middle c (261.63 hz) is C4
+pub fn note_name(frequency: f32) -> String {
+	let midi_note = freq_to_midi_note(frequency) as i32;
+	return format!("{}{}", NOTE_NAMES[(midi_note % 12) as usize], midi_note / 12 - 1);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interval {
+	Unison,
+	MinorSecond,
+	MajorSecond,
+	MinorThird,
+	MajorThird,
+	PerfectFourth,
+	Tritone,
+	PerfectFifth,
+	MinorSixth,
+	MajorSixth,
+	MinorSeventh,
+	MajorSeventh,
+	Octave,
+}
+
+impl Interval {
+	pub fn semitones(&self) -> u8 {
+		return match self {
+			Interval::Unison => 0,
+			Interval::MinorSecond => 1,
+			Interval::MajorSecond => 2,
+			Interval::MinorThird => 3,
+			Interval::MajorThird => 4,
+			Interval::PerfectFourth => 5,
+			Interval::Tritone => 6,
+			Interval::PerfectFifth => 7,
+			Interval::MinorSixth => 8,
+			Interval::MajorSixth => 9,
+			Interval::MinorSeventh => 10,
+			Interval::MajorSeventh => 11,
+			Interval::Octave => 12,
+		};
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Direction {
+	Up,
+	Down,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+	Major,
+	Minor,
+	Dorian,
+	Phrygian,
+	Lydian,
+	Mixolydian,
+	Aeolian,
+	Locrian,
+}
+
+impl Mode {
+	// semitones from the tonic for each of the seven degrees
+	pub fn intervals(&self) -> [u8; 7] {
+		return match self {
+			Mode::Major => [0, 2, 4, 5, 7, 9, 11],
+			Mode::Minor | Mode::Aeolian => [0, 2, 3, 5, 7, 8, 10],
+			Mode::Dorian => [0, 2, 3, 5, 7, 9, 10],
+			Mode::Phrygian => [0, 1, 3, 5, 7, 8, 10],
+			Mode::Lydian => [0, 2, 4, 6, 7, 9, 11],
+			Mode::Mixolydian => [0, 2, 4, 5, 7, 9, 10],
+			Mode::Locrian => [0, 1, 3, 5, 6, 8, 10],
+		};
+	}
+}
+
+// which pitch classes belong to a key, for checking melodies against it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScaleType {
+	Diatonic(Mode),
+	HarmonicMinor,
+	MajorPentatonic,
+	MinorPentatonic,
+	Chromatic,
+}
+
+impl ScaleType {
+	// semitones from the tonic of every note in the scale
+	pub fn intervals(&self) -> Vec<u8> {
+		return match self {
+			ScaleType::Diatonic(mode) => mode.intervals().to_vec(),
+			ScaleType::HarmonicMinor => vec![0, 2, 3, 5, 7, 8, 11],
+			ScaleType::MajorPentatonic => vec![0, 2, 4, 7, 9],
+			ScaleType::MinorPentatonic => vec![0, 3, 5, 7, 10],
+			ScaleType::Chromatic => (0..12).collect(),
+		};
+	}
+}
+
+// a mode starting from a concrete tonic frequency
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Scale {
+	pub tonic: f32,
+	pub mode: Mode,
+}
+
+impl Scale {
+	pub fn new(tonic: f32, mode: Mode) -> Scale {
+		return Scale {
+			tonic,
+			mode,
+		}
+	}
+
+	// degrees count from 1 like musicians do: 1 is the tonic, 8 the tonic an octave
+	// up and -1 the degree just below the tonic. 0 is treated as the tonic too
+	pub fn degree_frequency(&self, degree: i32) -> f32 {
+		let step = if degree > 0 { degree - 1 } else { degree };
+		let octave = step.div_euclid(7);
+		let semitones = self.mode.intervals()[step.rem_euclid(7) as usize] as i32 + 12 * octave;
+		return transpose(self.tonic, semitones as f32);
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChordQuality {
+	Major,
+	Minor,
+	Dominant7,
+}
+
+impl ChordQuality {
+	// semitones above the root of every chord tone, root included
+	pub fn intervals(&self) -> &'static [u8] {
+		return match self {
+			ChordQuality::Major => &[0, 4, 7],
+			ChordQuality::Minor => &[0, 3, 7],
+			ChordQuality::Dominant7 => &[0, 4, 7, 10],
+		};
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RomanNumeral {
+	I(ChordQuality),
+	II(ChordQuality),
+	III(ChordQuality),
+	IV(ChordQuality),
+	V(ChordQuality),
+	VI(ChordQuality),
+	VII(ChordQuality),
+}
+
+impl RomanNumeral {
+	// zero based scale degree
+	pub fn degree(&self) -> usize {
+		return match self {
+			RomanNumeral::I(_) => 0,
+			RomanNumeral::II(_) => 1,
+			RomanNumeral::III(_) => 2,
+			RomanNumeral::IV(_) => 3,
+			RomanNumeral::V(_) => 4,
+			RomanNumeral::VI(_) => 5,
+			RomanNumeral::VII(_) => 6,
+		};
+	}
+
+	pub fn quality(&self) -> ChordQuality {
+		return match self {
+			RomanNumeral::I(quality) | RomanNumeral::II(quality) | RomanNumeral::III(quality)
+			| RomanNumeral::IV(quality) | RomanNumeral::V(quality) | RomanNumeral::VI(quality)
+			| RomanNumeral::VII(quality) => *quality,
+		};
+	}
+
+	// frequency of the chord's root when the tonic of the mode is `key`
+	pub fn root(&self, key: f32, mode: Mode) -> f32 {
+		return transpose(key, mode.intervals()[self.degree()] as f32);
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Chord {
+	pub root: f32,
+	pub quality: ChordQuality,
+}
+
+impl Chord {
+	pub fn new(root: f32, quality: ChordQuality) -> Chord {
+		return Chord {
+			root,
+			quality,
+		}
+	}
+
+	// every chord tone in close position starting from the root
+	pub fn frequencies(&self) -> Vec<f32> {
+		return self.quality.intervals().iter().map(|interval| transpose(self.root, *interval as f32)).collect();
+	}
+}
+
+impl RomanNumeral {
+	pub fn chord(&self, key: f32, mode: Mode) -> Chord {
+		return Chord::new(self.root(key, mode), self.quality());
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VoicingStyle {
+	//every tone as close above the one below it as it can be, within an octave
+	Closed,
+	//closed with the second and fourth tones from the bottom an octave up
+	Open,
+	//closed with the second tone from the top dropped an octave
+	Drop2,
+	//closed with the third tone from the top dropped an octave
+	Drop3,
+}
+
+// the chord tones rearranged in the style, lowest first. the first tone stays the
+// bottom of a Closed or Open voicing, the drops can go below it
+pub fn voice_chord(freqs: &[f32], style: VoicingStyle) -> Vec<f32> {
+	let mut closed: Vec<f32> = Vec::with_capacity(freqs.len());
+	for freq in freqs.iter() {
+		let mut freq = *freq;
+		if let Some(below) = closed.last() {
+			while freq <= *below {
+				freq *= 2.0;
+			}
+			while freq / 2.0 > *below {
+				freq /= 2.0;
+			}
+		}
+		closed.push(freq);
+	}
+
+	let len = closed.len();
+	match style {
+		VoicingStyle::Closed => {},
+		VoicingStyle::Open => {
+			for freq in closed.iter_mut().skip(1).step_by(2) {
+				*freq *= 2.0;
+			}
+		},
+		VoicingStyle::Drop2 if len >= 2 => closed[len - 2] /= 2.0,
+		VoicingStyle::Drop3 if len >= 3 => closed[len - 3] /= 2.0,
+		_ => {},
+	}
+	closed.sort_by(|a, b| a.total_cmp(b));
+	return closed;
+}
+
+// moves every chord tone by octaves so the chord fills the register from
+// low_limit to high_limit hz: the range is split into one equal slice (in pitch)
+// per tone and each tone lands in the octave nearest the middle of its slice,
+// always above the tone before it. lowest first
+pub fn voice_chord_spread(freqs: &[f32], low_limit: f32, high_limit: f32) -> Vec<f32> {
+	if freqs.is_empty() || low_limit <= 0.0 || high_limit <= low_limit {
+		return freqs.to_vec();
+	}
+	let low = low_limit.log2();
+	let high = high_limit.log2();
+	let slice = (high - low) / freqs.len() as f32;
+
+	let mut voiced: Vec<f32> = Vec::with_capacity(freqs.len());
+	for (n, freq) in voice_chord(freqs, VoicingStyle::Closed).iter().enumerate() {
+		let center = low + (n as f32 + 0.5) * slice;
+		let mut octave = freq.log2() + (center - freq.log2()).round();
+		if let Some(below) = voiced.last() {
+			while octave <= below.log2() {
+				octave += 1.0;
+			}
+		}
+		//pull back inside the range unless that would cross the tone below
+		while octave > high && voiced.last().map(|below| octave - 1.0 > below.log2()).unwrap_or(true) {
+			octave -= 1.0;
+		}
+		while octave < low {
+			octave += 1.0;
+		}
+		voiced.push(2.0_f32.powf(octave));
+	}
+	return voiced;
+}
+
+// the just interval nearest to freq above root, with every odd number in the
+// ratio at most `limit` (5 for 5-limit and so on, 1 only allows octaves). the odd
+// parts are moved by octaves, so 3/1 becomes the fifth 3/2, and freq keeps its octave
+pub fn snap_to_just(freq: f32, root: f32, limit: u8) -> f32 {
+	if freq <= 0.0 || root <= 0.0 {
+		return freq;
+	}
+	let octaves = (freq / root).log2();
+	let octave = octaves.floor();
+	let position = octaves - octave;
+
+	//positions in the octave from 0.0 to 1.0. each ratio is also tried an octave
+	//up so a note just under the octave can snap onto it
+	let mut best = 0.0;
+	let mut best_distance = f32::MAX;
+	for p in (1..=limit.max(1) as u32).step_by(2) {
+		for q in (1..=limit.max(1) as u32).step_by(2) {
+			let ratio_position = (p as f32 / q as f32).log2().rem_euclid(1.0);
+			for candidate in [ratio_position, ratio_position + 1.0] {
+				let distance = (candidate - position).abs();
+				if distance < best_distance {
+					best = candidate;
+					best_distance = distance;
+				}
+			}
+		}
+	}
+	return root * 2.0_f32.powf(octave + best);
+}
+
+// how a note's frequency is worked out from where it sits relative to a root
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TuningSystem {
+	EqualTemperament,
+	JustIntonation { limit: u8 },
+}
+
+impl TuningSystem {
+	// frequencies come in equal tempered, that's left alone or snapped to just ratios
+	pub fn tune(&self, freq: f32, root: f32) -> f32 {
+		return match self {
+			TuningSystem::EqualTemperament => freq,
+			TuningSystem::JustIntonation { limit } => snap_to_just(freq, root, *limit),
+		};
+	}
+}
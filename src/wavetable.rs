@@ -0,0 +1,279 @@
+use core::time::Duration;
+use rodio::source::Source;
+
+use crate::composer::{WavetableOscillator, interpolate};
+use crate::modulation::Lfo;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WindowFunction {
+	Rectangular,
+	Triangular,
+	Hann,
+	Hamming,
+	Blackman,
+}
+
+impl WindowFunction {
+	// value of the window at n out of len samples
+	pub fn value(&self, n: usize, len: usize) -> f32 {
+		let phase = 2.0 * std::f32::consts::PI * n as f32 / len as f32;
+		return match self {
+			WindowFunction::Rectangular => 1.0,
+			WindowFunction::Triangular => 1.0 - (2.0 * n as f32 / len as f32 - 1.0).abs(),
+			WindowFunction::Hann => 0.5 - 0.5 * phase.cos(),
+			WindowFunction::Hamming => 0.54 - 0.46 * phase.cos(),
+			WindowFunction::Blackman => 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos(),
+		};
+	}
+}
+
+// draw a single cycle waveform sample by sample, then build() an oscillator from it
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavetableEditor {
+	table: Vec<f32>,
+}
+
+impl WavetableEditor {
+	// starts out silent
+	pub fn new(table_size: usize) -> WavetableEditor {
+		return WavetableEditor {
+			table: vec![0.0; table_size.max(1)],
+		}
+	}
+
+	pub fn from_oscillator(oscillator: &WavetableOscillator) -> WavetableEditor {
+		return WavetableEditor {
+			table: oscillator.wave_table.to_vec(),
+		}
+	}
+
+	pub fn table_size(&self) -> usize {
+		return self.table.len();
+	}
+
+	pub fn samples(&self) -> &[f32] {
+		return &self.table;
+	}
+
+	// out of range indices are ignored
+	pub fn set_sample(&mut self, index: usize, value: f32) {
+		if let Some(sample) = self.table.get_mut(index) {
+			*sample = value;
+		}
+	}
+
+	// scales the table so its highest peak is at 1.0, a silent table stays silent
+	pub fn normalize(&mut self) {
+		let peak = self.table.iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+		if peak > 0.0 {
+			for sample in self.table.iter_mut() {
+				*sample /= peak;
+			}
+		}
+	}
+
+	pub fn dc_offset(&self) -> f32 {
+		return self.table.iter().sum::<f32>() / self.table.len() as f32;
+	}
+
+	pub fn remove_dc(&mut self) {
+		let offset = self.dc_offset();
+		for sample in self.table.iter_mut() {
+			*sample -= offset;
+		}
+	}
+
+	pub fn reverse(&mut self) {
+		self.table.reverse();
+	}
+
+	// rotates the cycle, positive values move the waveform later in time
+	pub fn shift_phase(&mut self, samples: i32) {
+		let len = self.table.len() as i32;
+		let shift = samples.rem_euclid(len) as usize;
+		self.table.rotate_right(shift);
+	}
+
+	pub fn apply_window(&mut self, window: WindowFunction) {
+		let len = self.table.len();
+		for (n, sample) in self.table.iter_mut().enumerate() {
+			*sample *= window.value(n, len);
+		}
+	}
+
+	pub fn build(self, sample_rate: u32) -> WavetableOscillator {
+		return WavetableOscillator::new(sample_rate, self.table);
+	}
+}
+
+// how many harmonics a table played at `frequency` gets. without max_partial it's
+// every harmonic below nyquist, which is as bright as the table can be without
+// aliasing. max_partial sets the count outright, for a darker or buzzier tone
+fn partial_count(frequency: f32, sample_rate: u32, max_partial: Option<usize>) -> usize {
+	return match max_partial {
+		Some(partials) => partials,
+		None => (sample_rate as f32 / (2.0 * frequency.max(1.0))) as usize,
+	};
+}
+
+// rising saw from -1.0 to 1.0 built from its harmonics, lines up with the plain saw table
+pub fn build_bandlimited_saw(table_size: usize, frequency: f32, sample_rate: u32, max_partial: Option<usize>) -> Vec<f32> {
+	let partials = partial_count(frequency, sample_rate, max_partial);
+	return build_from_partials(table_size, (1..=partials).map(|k| (k, -2.0 / (std::f32::consts::PI * k as f32), 0.0)));
+}
+
+// square that's high for the first half of the cycle, odd harmonics only
+pub fn build_bandlimited_square(table_size: usize, frequency: f32, sample_rate: u32, max_partial: Option<usize>) -> Vec<f32> {
+	let partials = partial_count(frequency, sample_rate, max_partial);
+	return build_from_partials(table_size, (1..=partials).step_by(2).map(|k| (k, 4.0 / (std::f32::consts::PI * k as f32), 0.0)));
+}
+
+// triangle starting at its bottom and peaking halfway, odd harmonics only
+pub fn build_bandlimited_triangle(table_size: usize, frequency: f32, sample_rate: u32, max_partial: Option<usize>) -> Vec<f32> {
+	let partials = partial_count(frequency, sample_rate, max_partial);
+	let scale = -8.0 / (std::f32::consts::PI * std::f32::consts::PI);
+	return build_from_partials(table_size, (1..=partials).step_by(2).map(|k| (k, 0.0, scale / (k * k) as f32)));
+}
+
+// sums (harmonic, sine amplitude, cosine amplitude) partials into one cycle
+fn build_from_partials(table_size: usize, partials: impl Iterator<Item = (usize, f32, f32)>) -> Vec<f32> {
+	let table_size = table_size.max(1);
+	let mut table = vec![0.0; table_size];
+	for (harmonic, sine, cosine) in partials {
+		for (n, sample) in table.iter_mut().enumerate() {
+			let phase = 2.0 * std::f32::consts::PI * (harmonic * n) as f32 / table_size as f32;
+			*sample += sine * phase.sin() + cosine * phase.cos();
+		}
+	}
+	return table;
+}
+
+// the same cycle with everything above partial_limit harmonics taken out. the
+// table is analysed one harmonic at a time, so it doesn't need a power of two size
+pub(crate) fn bandlimit(table: &[f32], partial_limit: usize) -> Vec<f32> {
+	let len = table.len();
+	//the dc offset is kept as it is
+	let mean = table.iter().sum::<f32>() / len.max(1) as f32;
+	let partials = (1..=partial_limit.min(len / 2)).map(|k| {
+		let (mut sine, mut cosine) = (0.0, 0.0);
+		for (n, sample) in table.iter().enumerate() {
+			let phase = 2.0 * std::f32::consts::PI * (k * n) as f32 / len as f32;
+			sine += sample * phase.sin();
+			cosine += sample * phase.cos();
+		}
+		//nyquist only has a cosine and appears once in the spectrum instead of twice
+		let scale = if 2 * k == len { 1.0 } else { 2.0 } / len as f32;
+		(k, sine * scale, cosine * scale)
+	}).collect::<Vec<_>>();
+	return build_from_partials(len, partials.into_iter()).into_iter().map(|sample| sample + mean).collect();
+}
+
+// a stack of single cycle tables that the sound can move through. position 0.0 is
+// the first table, table_count - 1 the last, and anything in between blends the
+// two neighbouring tables. the scan lfo's value is added to the position in tables
+#[derive(Clone)]
+pub struct WavetableSynth {
+	sample_rate: u32,
+	tables: Vec<Vec<f32>>,
+	pub position: f32,
+	pub scan_lfo: Option<Lfo>,
+	phase: f32,
+	phase_increment: f32,
+	scan_step: f32,
+	scan_samples_left: u32,
+}
+
+impl WavetableSynth {
+	// empty tables have nothing to read and are left out, so positions count only
+	// the tables that have samples in them
+	pub fn new(sample_rate: u32, mut tables: Vec<Vec<f32>>) -> WavetableSynth {
+		tables.retain(|table| !table.is_empty());
+		return WavetableSynth {
+			sample_rate,
+			tables,
+			position: 0.0,
+			scan_lfo: None,
+			phase: 0.0,
+			phase_increment: 0.0,
+			scan_step: 0.0,
+			scan_samples_left: 0,
+		}
+	}
+
+	pub fn table_count(&self) -> usize {
+		return self.tables.len();
+	}
+
+	pub fn set_frequency(&mut self, frequency: f32) {
+		self.phase_increment = frequency / self.sample_rate as f32;
+	}
+
+	// glides the position from wherever it is now to target over `duration`
+	pub fn scan_to(&mut self, target: f32, duration: Duration) {
+		let target = target.clamp(0.0, self.last_position());
+		let samples = (duration.as_secs_f32() * self.sample_rate as f32) as u32;
+		if samples == 0 {
+			self.position = target;
+			self.scan_samples_left = 0;
+			return;
+		}
+		self.scan_step = (target - self.position) / samples as f32;
+		self.scan_samples_left = samples;
+	}
+
+	fn last_position(&self) -> f32 {
+		return self.tables.len().saturating_sub(1) as f32;
+	}
+
+	fn get_sample(&mut self) -> f32 {
+		if self.tables.is_empty() {
+			return 0.0;
+		}
+
+		if self.scan_samples_left > 0 {
+			self.position += self.scan_step;
+			self.scan_samples_left -= 1;
+		}
+
+		let mut position = self.position;
+		if let Some(lfo) = self.scan_lfo.as_mut() {
+			position += lfo.next_value(self.sample_rate);
+		}
+		let position = position.clamp(0.0, self.last_position());
+
+		let lower = &self.tables[position.floor() as usize];
+		let upper = &self.tables[position.ceil() as usize];
+		let lower_sample = interpolate(lower, self.phase * lower.len() as f32);
+		let upper_sample = interpolate(upper, self.phase * upper.len() as f32);
+		let blend = position.fract();
+
+		self.phase = (self.phase + self.phase_increment).fract();
+		return lower_sample + blend * (upper_sample - lower_sample);
+	}
+}
+
+impl Source for WavetableSynth {
+	fn current_frame_len(&self) -> Option<usize> {
+		return None;
+	}
+
+	fn channels(&self) -> u16 {
+		return 1;
+	}
+
+	fn sample_rate(&self) -> u32 {
+		return self.sample_rate;
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		return None;
+	}
+}
+
+impl Iterator for WavetableSynth {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		return Some(self.get_sample());
+	}
+}